@@ -0,0 +1,25 @@
+use fprint_rs::{audit, FPrintError};
+use std::{env, fs};
+
+fn main() -> Result<(), FPrintError> {
+    let mut args = env::args().skip(1);
+    let (log, key) = match (args.next(), args.next()) {
+        (Some(log), Some(key)) => (log, key),
+        _ => {
+            eprintln!("Usage: audit_verify <log> <key file>");
+            std::process::exit(2);
+        }
+    };
+
+    let key = fs::read(key)?;
+    match audit::verify(&log, &key) {
+        Ok(records) => println!("Audit log is intact, {} records.", records),
+        Err(FPrintError::AuditChainBroken(seq)) => {
+            println!("Audit log was tampered with at record {}!", seq);
+            std::process::exit(1);
+        }
+        Err(e) => return Err(e),
+    }
+
+    Ok(())
+}
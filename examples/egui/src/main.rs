@@ -0,0 +1,314 @@
+use eframe::egui;
+use fprint_rs::{
+    Device, EnrollResult, FPrint, FPrintError, Image, PrintData, SizeVariant, VerifyResult,
+};
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+/// Commands sent from the UI to the device thread.
+enum Command {
+    Enroll,
+    Verify,
+}
+
+/// Captured image copied out of libfprint, so it can be sent to the UI thread.
+struct Frame {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Frame {
+    fn from_image(image: &Image) -> Self {
+        let width = image.get_width() as usize;
+        let height = image.get_height() as usize;
+        let pixels = image.as_bytes().to_vec();
+
+        Frame {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// Events sent from the device thread to the UI.
+enum Event {
+    Ready { stages: i32 },
+    EnrollStage { passed: i32, prompt: String },
+    Enrolled,
+    Verified(bool),
+    Retry(String),
+    Frame(Frame),
+    Error(String),
+}
+
+struct Worker {
+    device: Device,
+    imaging: bool,
+    print: Option<PrintData>,
+    events: Sender<Event>,
+    ctx: egui::Context,
+}
+
+impl Worker {
+    fn send(&self, event: Event) {
+        let _ = self.events.send(event);
+        self.ctx.request_repaint();
+    }
+
+    fn send_frame(&self, image: &Image) {
+        if self.imaging {
+            self.send(Event::Frame(Frame::from_image(image)));
+        }
+    }
+
+    fn enroll(&mut self) -> Result<(), FPrintError> {
+        let mut passed = 0;
+        loop {
+            let (prompt, print) = match self.device.enroll_finger_image()? {
+                EnrollResult::Complete(print, image) => {
+                    self.send_frame(&image);
+                    passed += 1;
+                    ("Enroll complete!", Some(print))
+                }
+                EnrollResult::Pass(image) => {
+                    self.send_frame(&image);
+                    passed += 1;
+                    ("Enroll stage passed. Scan your finger again.", None)
+                }
+                EnrollResult::Fail => {
+                    passed = 0;
+                    (
+                        "Enroll failed, starting over. Scan your finger again.",
+                        None,
+                    )
+                }
+                EnrollResult::Retry => ("Didn't quite catch that. Please try again.", None),
+                EnrollResult::RetryTooShort => {
+                    ("Your swipe was too short, please try again.", None)
+                }
+                EnrollResult::RetryCenterFinger => (
+                    "Didn't catch that, please center your finger on the sensor and try again.",
+                    None,
+                ),
+                EnrollResult::RetryRemoveFinger => (
+                    "Scan failed, please remove your finger and then try again.",
+                    None,
+                ),
+                _ => ("Scan failed, please try again.", None),
+            };
+
+            self.send(Event::EnrollStage {
+                passed,
+                prompt: prompt.to_string(),
+            });
+
+            if let Some(print) = print {
+                self.print = Some(print);
+                self.send(Event::Enrolled);
+
+                return Ok(());
+            }
+        }
+    }
+
+    fn verify(&mut self) -> Result<(), FPrintError> {
+        let mut print = match self.print.take() {
+            Some(print) => print,
+            None => {
+                self.send(Event::Error("Enroll a finger first".to_string()));
+                return Ok(());
+            }
+        };
+
+        let result = loop {
+            let prompt = match self.device.verify_finger_image(&mut print)? {
+                VerifyResult::Match => break true,
+                VerifyResult::NoMatch => break false,
+                VerifyResult::Retry => "Scan didn't quite work. Please try again.",
+                VerifyResult::RetryTooShort => "Swipe was too short, please try again.",
+                VerifyResult::RetryCenterFinger => {
+                    "Please center your finger on the sensor and try again."
+                }
+                VerifyResult::RetryRemoveFinger => {
+                    "Please remove finger from the sensor and try again."
+                }
+                _ => "Scan failed. Please try again.",
+            };
+            self.send(Event::Retry(prompt.to_string()));
+        };
+
+        self.print = Some(print);
+        self.send(Event::Verified(result));
+
+        Ok(())
+    }
+}
+
+fn run_worker(commands: Receiver<Command>, events: Sender<Event>, ctx: egui::Context) {
+    let fail = |e: FPrintError| {
+        let _ = events.send(Event::Error(e.to_string()));
+        ctx.request_repaint();
+    };
+
+    let fprint = match FPrint::new() {
+        Ok(fprint) => fprint,
+        Err(e) => return fail(e),
+    };
+    let discovered = fprint.discover();
+    let device = match discovered.get(0) {
+        Some(device) => match device.open() {
+            Ok(device) => device,
+            Err(e) => return fail(e),
+        },
+        None => {
+            let _ = events.send(Event::Error("Device not found".to_string()));
+            return;
+        }
+    };
+
+    let mut worker = Worker {
+        imaging: device.get_img_width() != SizeVariant::NonImagingDevice,
+        device,
+        print: None,
+        events: events.clone(),
+        ctx: ctx.clone(),
+    };
+    worker.send(Event::Ready {
+        stages: worker.device.get_nr_enroll_stages(),
+    });
+
+    for command in commands {
+        let result = match command {
+            Command::Enroll => worker.enroll(),
+            Command::Verify => worker.verify(),
+        };
+
+        if let Err(e) = result {
+            fail(e);
+        }
+    }
+}
+
+struct DemoApp {
+    commands: Sender<Command>,
+    events: Receiver<Event>,
+    busy: bool,
+    enrolled: bool,
+    stages: i32,
+    passed: i32,
+    status: String,
+    texture: Option<egui::TextureHandle>,
+}
+
+impl DemoApp {
+    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let (commands, command_receiver) = mpsc::channel();
+        let (event_sender, events) = mpsc::channel();
+        let ctx = cc.egui_ctx.clone();
+        thread::spawn(move || run_worker(command_receiver, event_sender, ctx));
+
+        DemoApp {
+            commands,
+            events,
+            busy: true,
+            enrolled: false,
+            stages: 0,
+            passed: 0,
+            status: "Looking for a fingerprint reader...".to_string(),
+            texture: None,
+        }
+    }
+
+    fn handle_events(&mut self, ctx: &egui::Context) {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Event::Ready { stages } => {
+                    self.busy = false;
+                    self.stages = stages;
+                    self.status = "Reader is ready.".to_string();
+                }
+                Event::EnrollStage { passed, prompt } => {
+                    self.passed = passed;
+                    self.status = prompt;
+                }
+                Event::Enrolled => {
+                    self.busy = false;
+                    self.enrolled = true;
+                    self.status = "Enrollment completed! You can verify now.".to_string();
+                }
+                Event::Verified(matched) => {
+                    self.busy = false;
+                    self.status = if matched { "MATCH!" } else { "NO MATCH!" }.to_string();
+                }
+                Event::Retry(prompt) => self.status = prompt,
+                Event::Frame(frame) => {
+                    let image =
+                        egui::ColorImage::from_gray([frame.width, frame.height], &frame.pixels);
+                    self.texture = Some(ctx.load_texture("scan", image, Default::default()));
+                }
+                Event::Error(e) => {
+                    self.busy = false;
+                    self.status = format!("Error: {}", e);
+                }
+            }
+        }
+    }
+
+    fn start(&mut self, command: Command, status: &str) {
+        if self.commands.send(command).is_ok() {
+            self.busy = true;
+            self.status = status.to_string();
+        }
+    }
+}
+
+impl eframe::App for DemoApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.handle_events(ctx);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!self.busy, egui::Button::new("Enroll"))
+                    .clicked()
+                {
+                    self.passed = 0;
+                    self.start(Command::Enroll, "Scan your finger now.");
+                }
+                let can_verify = !self.busy && self.enrolled;
+                if ui
+                    .add_enabled(can_verify, egui::Button::new("Verify"))
+                    .clicked()
+                {
+                    self.start(Command::Verify, "Scan your finger now.");
+                }
+            });
+
+            if self.stages > 0 {
+                let progress = self.passed as f32 / self.stages as f32;
+                ui.add(
+                    egui::ProgressBar::new(progress)
+                        .text(format!("{} of {} stages", self.passed, self.stages)),
+                );
+            }
+
+            ui.label(&self.status);
+
+            if let Some(texture) = &self.texture {
+                ui.image(texture);
+            }
+        });
+    }
+}
+
+fn main() -> eframe::Result {
+    eframe::run_native(
+        "fprint demo",
+        eframe::NativeOptions::default(),
+        Box::new(|cc| Ok(Box::new(DemoApp::new(cc)))),
+    )
+}
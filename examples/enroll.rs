@@ -1,6 +1,7 @@
-use fprint_rs::{EnrollResult, FPrint, FPrintError, Finger};
+use fprint_rs::{FPrint, FPrintError, Finger, UserGuidance};
 use std::{
     io::{stdin, Read},
+    ops::ControlFlow,
 };
 
 fn main() -> Result<(), FPrintError> {
@@ -15,39 +16,23 @@ fn main() -> Result<(), FPrintError> {
 
     let fprint = FPrint::new()?;
     let discovered = fprint.discover();
-    let device = discovered.get(0).expect("Device not found").open();
+    let device = discovered.get(0).expect("Device not found").open()?;
 
     println!(
         "You will need to successfully scan your finger {} times to complete the process.",
         device.get_nr_enroll_stages()
     );
 
-    let mut counter = 1;
-    let print_data = loop {
-        println!("Scan your finger now (time: {}).", counter);
-        let enroll = device.enroll_finger_image()?;
-        match enroll {
-            EnrollResult::Complete(print, _) => {
-                println!("Enroll complete!");
-                break print;
-            },
-            EnrollResult::Fail => println!("Enroll failed, something wen't wrong :("),
-            EnrollResult::Pass(_) => {
-                println!("Enroll stage passed. Yay!");
-                counter += 1;
-            }
-            EnrollResult::Retry => println!("Didn't quite catch that. Please try again."),
-            EnrollResult::RetryTooShort => {
-                println!("Your swipe was too short, please try again.")
-            }
-            EnrollResult::RetryCenterFinger => println!(
-                "Didn't catch that, please center your finger on the sensor and try again."
-            ),
-            EnrollResult::RetryRemoveFinger => {
-                println!("Scan failed, please remove your finger and then try again.")
-            }
-        }
-    };
+    println!("Scan your finger now.");
+    let print_data = device.enroll_with(|attempt| {
+        println!(
+            "Scan {} of {}: {}",
+            attempt.stage,
+            attempt.total_stages,
+            UserGuidance::from(&attempt.result)
+        );
+        ControlFlow::Continue(())
+    })?;
 
     println!("Enrollment completed!");
 
@@ -0,0 +1,219 @@
+use fprint_rs::{
+    asynchronous::{AsyncContext, AsyncDevice},
+    EnrollResult, FPrintError, Finger, Image,
+};
+use gtk::{gdk, glib, prelude::*};
+
+const APP_ID: &str = "com.github.funkill.rust-fprint.gtk-enroll";
+
+/// Captured image copied out of libfprint, so it can be sent to the UI thread.
+struct Frame {
+    width: i32,
+    height: i32,
+    pixels: Vec<u8>,
+}
+
+impl Frame {
+    /// Copies the image, non-imaging devices return empty images.
+    fn from_image(image: &Image) -> Option<Self> {
+        let width = image.get_width();
+        let height = image.get_height();
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+        let pixels = image.as_bytes().to_vec();
+
+        Some(Frame {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    fn to_texture(&self) -> gdk::MemoryTexture {
+        let rgb = self
+            .pixels
+            .iter()
+            .flat_map(|&pixel| vec![pixel, pixel, pixel])
+            .collect::<Vec<_>>();
+        let bytes = glib::Bytes::from_owned(rgb);
+
+        gdk::MemoryTexture::new(
+            self.width,
+            self.height,
+            gdk::MemoryFormat::R8g8b8,
+            &bytes,
+            self.width as usize * 3,
+        )
+    }
+}
+
+/// Result of an intermediate enroll stage, sent from the fprint event thread to the UI.
+struct Stage {
+    passed: bool,
+    message: &'static str,
+    frame: Option<Frame>,
+}
+
+impl Stage {
+    fn new(result: &EnrollResult) -> Self {
+        let frame = match result {
+            EnrollResult::Pass(image) => Frame::from_image(image),
+            _ => None,
+        };
+
+        Stage {
+            passed: matches!(result, EnrollResult::Pass(_)),
+            message: describe(result),
+            frame,
+        }
+    }
+}
+
+/// Widgets showing the enrollment progress.
+#[derive(Clone)]
+struct View {
+    status: gtk::Label,
+    progress: gtk::ProgressBar,
+    picture: gtk::Picture,
+}
+
+impl View {
+    fn show_progress(&self, passed: i32, stages: i32) {
+        if stages > 0 {
+            self.progress
+                .set_fraction(f64::from(passed) / f64::from(stages));
+        }
+        self.progress
+            .set_text(Some(&format!("{} of {}", passed, stages)));
+    }
+
+    fn show_frame(&self, frame: &Frame) {
+        self.picture.set_paintable(Some(&frame.to_texture()));
+    }
+}
+
+fn describe(result: &EnrollResult) -> &'static str {
+    match result {
+        EnrollResult::Complete(_, _) => "Enroll complete!",
+        EnrollResult::Fail => "Enroll failed, something went wrong :(",
+        EnrollResult::Pass(_) => "Enroll stage passed. Scan your finger again.",
+        EnrollResult::Retry => "Didn't quite catch that. Please try again.",
+        EnrollResult::RetryTooShort => "Your swipe was too short, please try again.",
+        EnrollResult::RetryCenterFinger => {
+            "Didn't catch that, please center your finger on the sensor and try again."
+        }
+        EnrollResult::RetryRemoveFinger => {
+            "Scan failed, please remove your finger and then try again."
+        }
+        _ => "Scan failed, please try again.",
+    }
+}
+
+/// Shows results of enroll stages as they arrive. Runs on the GLib main loop until the
+/// sending side is dropped.
+async fn show_stages(view: View, stages: i32, receiver: async_channel::Receiver<Stage>) {
+    let mut passed = 0;
+    while let Ok(stage) = receiver.recv().await {
+        if stage.passed {
+            passed += 1;
+        }
+        view.show_progress(passed, stages);
+        view.status.set_text(stage.message);
+        if let Some(frame) = &stage.frame {
+            view.show_frame(frame);
+        }
+    }
+}
+
+async fn enroll(view: &View, device: &mut AsyncDevice<'_>) -> Result<(), FPrintError> {
+    let stages = device.get_nr_enroll_stages();
+    view.show_progress(0, stages);
+    view.status.set_text(&format!(
+        "You will need to successfully scan your finger {} times. Scan your finger now.",
+        stages
+    ));
+
+    // Stage callback runs on the fprint event thread, results are forwarded to the main loop.
+    let (sender, receiver) = async_channel::unbounded();
+    glib::spawn_future_local(show_stages(view.clone(), stages, receiver));
+
+    let result = device
+        .enroll(move |result| {
+            let _ = sender.try_send(Stage::new(result));
+        })
+        .await?;
+
+    match result {
+        EnrollResult::Complete(print, image) => {
+            view.show_progress(stages, stages);
+            if let Some(frame) = Frame::from_image(&image) {
+                view.show_frame(&frame);
+            }
+            print.save_to_disk(Finger::RightIndex)?;
+            view.status
+                .set_text("Enrollment completed! Print data saved.");
+        }
+        result => view.status.set_text(describe(&result)),
+    }
+
+    Ok(())
+}
+
+async fn run(view: View) -> Result<(), FPrintError> {
+    let context = AsyncContext::new()?;
+    let discovered = context.discover().await;
+    let mut device = match discovered.get(0) {
+        Some(device) => context.open(device).await?,
+        None => {
+            view.status.set_text("Device not found");
+            return Ok(());
+        }
+    };
+
+    enroll(&view, &mut device).await
+}
+
+fn build_ui(app: &gtk::Application) {
+    let status = gtk::Label::new(Some("Looking for a fingerprint reader..."));
+    let progress = gtk::ProgressBar::new();
+    progress.set_show_text(true);
+    let picture = gtk::Picture::new();
+    picture.set_size_request(256, 256);
+
+    let layout = gtk::Box::new(gtk::Orientation::Vertical, 12);
+    layout.set_margin_top(12);
+    layout.set_margin_bottom(12);
+    layout.set_margin_start(12);
+    layout.set_margin_end(12);
+    layout.append(&picture);
+    layout.append(&progress);
+    layout.append(&status);
+
+    let window = gtk::ApplicationWindow::builder()
+        .application(app)
+        .title("fprint enrollment")
+        .child(&layout)
+        .build();
+    window.present();
+
+    let view = View {
+        status,
+        progress,
+        picture,
+    };
+    // Futures of the asynchronous API are woken by the fprint event thread and polled by
+    // the GLib main loop, so the UI never blocks while waiting for a finger.
+    glib::spawn_future_local(async move {
+        if let Err(e) = run(view.clone()).await {
+            view.status.set_text(&format!("Error: {}", e));
+        }
+    });
+}
+
+fn main() -> glib::ExitCode {
+    let app = gtk::Application::builder().application_id(APP_ID).build();
+    app.connect_activate(build_ui);
+
+    app.run()
+}
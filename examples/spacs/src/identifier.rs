@@ -1,58 +1,26 @@
 mod common;
 
 use failure::Error;
-use fprint_rs::{FPrint, IdentifyResult};
-use rusqlite::NO_PARAMS;
-use std::collections::HashMap;
+use fprint_rs::{
+    attendance::{Identification, SqlitePrintStore},
+    FPrint,
+};
 
 fn main() -> Result<(), Error> {
     let fprint = FPrint::new()?;
     let discovered = fprint.discover();
-    let device = discovered.get(0).expect("Device not found").open();
-    let (fingers, users) = load_fingers()?;
+    let device = discovered.get(0).expect("Device not found").open()?;
+    let gallery = SqlitePrintStore::open(common::DB_PATH)?.load_gallery()?;
 
     loop {
-        let identity_result = device.identify_finger_image(&fingers);
-        if identity_result.is_err() {
-            eprintln!("Error: {:?}", identity_result);
-            continue;
-        }
-
-        match identity_result.unwrap() {
-            IdentifyResult::Matched(offset) => match users.get(&offset) {
-                Some(user_id) => println!("Found finger for user with id {}", user_id),
-                None => eprintln!("Unknown offset"),
-            },
-            IdentifyResult::Error(e) => eprintln!("Identity error: {}", e),
+        match gallery.identify(&device) {
+            Ok(Identification::User(user_id)) => {
+                println!("Found finger for user with id {}", user_id)
+            }
+            Ok(Identification::NoMatch) => println!("Finger not found"),
+            Ok(Identification::Retry(reason)) => eprintln!("Identity error: {}", reason),
+            Ok(_) => eprintln!("Identity error"),
+            Err(e) => eprintln!("Error: {:?}", e),
         }
     }
 }
-
-#[allow(clippy::type_complexity)]
-fn load_fingers() -> Result<(Vec<Vec<u8>>, HashMap<usize, i32>), Error> {
-    let fingers = vec![];
-    let user_offsets = HashMap::new();
-    let result = rusqlite::Connection::open(crate::common::DB_PATH)?
-        .prepare("SELECT DISTINCT * FROM fingers")?
-        .query_map(NO_PARAMS, |row| {
-            let id: i32 = row.get(1)?;
-            let length: isize = row.get(3)?;
-            let finger: Vec<u8> = row.get(2)?;
-            assert_eq!(finger.len(), length as usize);
-
-            Ok((id, finger))
-        })?
-        .filter_map(|item| item.ok())
-        .enumerate()
-        .fold(
-            (fingers, user_offsets),
-            |(mut fingers, mut user_offsets), (offset, (user_id, finger))| {
-                fingers.push(finger);
-                user_offsets.insert(offset, user_id);
-
-                (fingers, user_offsets)
-            },
-        );
-
-    Ok(result)
-}
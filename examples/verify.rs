@@ -4,7 +4,7 @@ use std::io::{stdin, Read};
 fn main() -> Result<(), FPrintError> {
     let fprint = FPrint::new()?;
     let discovered = fprint.discover();
-    let device = discovered.get(0).expect("Device not found").open();
+    let device = discovered.get(0).expect("Device not found").open()?;
 
     println!("Opened device. Loading previously enrolled right index finger data...");
 
@@ -32,6 +32,7 @@ fn main() -> Result<(), FPrintError> {
                 VerifyResult::RetryRemoveFinger => {
                     println!("Please remove finger from the sensor and try again.")
                 }
+                _ => println!("Scan failed. Please try again."),
             }
         }
 
@@ -1,6 +1,7 @@
-use fprint_rs::{Device, EnrollResult, FPrint, FPrintError, PrintData, VerifyResult};
+use fprint_rs::{Device, FPrint, FPrintError, PrintData, UserGuidance, VerifyResult};
 use std::{
     io::{stdin, Read},
+    ops::ControlFlow,
 };
 
 fn enroll(device: &Device) -> Result<PrintData, FPrintError> {
@@ -9,32 +10,16 @@ fn enroll(device: &Device) -> Result<PrintData, FPrintError> {
         device.get_nr_enroll_stages()
     );
 
-    let mut counter = 1;
-    let print_data = loop {
-        println!("Scan your finger now (time: {}).", counter);
-        let enroll = device.enroll_finger_image()?;
-        match enroll {
-            EnrollResult::Complete(print, _) => {
-                println!("Enroll complete!");
-                break print;
-            },
-            EnrollResult::Fail => println!("Enroll failed, something wen't wrong :("),
-            EnrollResult::Pass(_) => {
-                println!("Enroll stage passed. Yay!");
-                counter += 1;
-            }
-            EnrollResult::Retry => println!("Didn't quite catch that. Please try again."),
-            EnrollResult::RetryTooShort => {
-                println!("Your swipe was too short, please try again.")
-            }
-            EnrollResult::RetryCenterFinger => println!(
-                "Didn't catch that, please center your finger on the sensor and try again."
-            ),
-            EnrollResult::RetryRemoveFinger => {
-                println!("Scan failed, please remove your finger and then try again.")
-            }
-        }
-    };
+    println!("Scan your finger now.");
+    let print_data = device.enroll_with(|attempt| {
+        println!(
+            "Scan {} of {}: {}",
+            attempt.stage,
+            attempt.total_stages,
+            UserGuidance::from(&attempt.result)
+        );
+        ControlFlow::Continue(())
+    })?;
 
     println!("Enrollment completed!");
 
@@ -44,7 +29,7 @@ fn enroll(device: &Device) -> Result<PrintData, FPrintError> {
 fn main() -> Result<(), FPrintError> {
     let fprint = FPrint::new()?;
     let discovered = fprint.discover();
-    let device = discovered.get(0).expect("Device not found").open();
+    let device = discovered.get(0).expect("Device not found").open()?;
     println!("Opened device. It's now time to enroll your finger.");
 
     let mut print_data = enroll(&device)?;
@@ -77,6 +62,7 @@ fn main() -> Result<(), FPrintError> {
                 VerifyResult::RetryRemoveFinger => {
                     println!("Please remove finger from the sensor and try again.")
                 }
+                _ => println!("Scan failed. Please try again."),
             }
         }
 
@@ -0,0 +1,31 @@
+//! Conversion of images captured by `fprint-rs` into OpenCV `Mat`, so captures can be
+//! processed by OpenCV algorithms.
+//!
+//! The crate is not a member of the workspace: opencv needs a newer `clang-sys` than
+//! bindgen of `fprint-sys`, and both link to libclang, so they can not be built together
+//! in one dependency graph. Build it from its directory.
+
+use fprint_rs::Image;
+use opencv::{
+    core::{Mat, Scalar},
+    prelude::*,
+};
+
+/// Conversion of `Image` into `Mat`.
+pub trait ToMat {
+    /// Copies greyscale data of the image into a single channel 8-bit `Mat`.
+    fn to_mat(&self) -> opencv::Result<Mat>;
+}
+
+impl ToMat for Image {
+    fn to_mat(&self) -> opencv::Result<Mat> {
+        let mut pixels = Vec::new();
+        let (width, height) = self.copy_data_into(&mut pixels);
+
+        let mut mat =
+            Mat::new_rows_cols_with_default(height, width, u8::opencv_type(), Scalar::all(0.))?;
+        mat.data_bytes_mut()?.copy_from_slice(&pixels);
+
+        Ok(mat)
+    }
+}
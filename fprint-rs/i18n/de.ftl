@@ -0,0 +1,10 @@
+scan-finger = Bitte legen Sie jetzt Ihren Finger auf.
+enroll-complete = Registrierung abgeschlossen!
+enroll-stage-passed = Schritt erfolgreich. Bitte legen Sie Ihren Finger erneut auf.
+enroll-failed = Registrierung fehlgeschlagen, sie beginnt von vorn. Bitte legen Sie Ihren Finger erneut auf.
+match = Übereinstimmung!
+no-match = Keine Übereinstimmung!
+scan-again = Das hat nicht ganz geklappt. Bitte versuchen Sie es erneut.
+swipe-too-short = Die Wischbewegung war zu kurz, bitte versuchen Sie es erneut.
+center-finger = Bitte legen Sie Ihren Finger mittig auf den Sensor und versuchen Sie es erneut.
+remove-finger = Scan fehlgeschlagen, bitte nehmen Sie den Finger vom Sensor und versuchen Sie es erneut.
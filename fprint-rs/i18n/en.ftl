@@ -0,0 +1,10 @@
+scan-finger = Scan your finger now.
+enroll-complete = Enroll complete!
+enroll-stage-passed = Enroll stage passed. Scan your finger again.
+enroll-failed = Enroll failed, starting over. Scan your finger again.
+match = MATCH!
+no-match = NO MATCH!
+scan-again = Didn't quite catch that. Please try again.
+swipe-too-short = Your swipe was too short, please try again.
+center-finger = Didn't catch that, please center your finger on the sensor and try again.
+remove-finger = Scan failed, please remove your finger and then try again.
@@ -0,0 +1,10 @@
+scan-finger = Приложите палец к сканеру.
+enroll-complete = Регистрация завершена!
+enroll-stage-passed = Этап пройден. Приложите палец ещё раз.
+enroll-failed = Регистрация не удалась и начнётся заново. Приложите палец ещё раз.
+match = Совпадение!
+no-match = Нет совпадения!
+scan-again = Не удалось считать отпечаток. Попробуйте ещё раз.
+swipe-too-short = Слишком короткое движение пальцем, попробуйте ещё раз.
+center-finger = Расположите палец по центру сканера и попробуйте ещё раз.
+remove-finger = Сканирование не удалось, уберите палец со сканера и попробуйте ещё раз.
@@ -0,0 +1,126 @@
+#ifndef FPRINT_RS_H
+#define FPRINT_RS_H
+
+/* Generated by cbindgen from src/capi.rs, do not edit manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ libfprint context with the list of discovered devices.
+ */
+typedef struct FprsContext FprsContext;
+
+/*
+ Opened device.
+ */
+typedef struct FprsDevice FprsDevice;
+
+/*
+ Called for every enrollment stage or retried scan with the libfprint result code.
+ */
+typedef void (*FprsResultCallback)(int result, void *user_data);
+
+/*
+ Initialises libfprint and discovers devices. Returns NULL on failure.
+ */
+FprsContext *fprs_init(void);
+
+/*
+ Frees the context and deinitialises libfprint.
+ */
+void fprs_exit(FprsContext *ctx);
+
+/*
+ Scans the system for devices again, replacing the list of devices discovered before.
+ Returns the number of discovered devices.
+ */
+int fprs_discover(FprsContext *ctx);
+
+/*
+ Returns the number of devices discovered by `fprs_init()` or `fprs_discover()`.
+ */
+int fprs_device_count(const FprsContext *ctx);
+
+/*
+ Opens discovered device by its index. Returns NULL if there is no such device or it
+ could not be opened.
+ */
+FprsDevice *fprs_device_open(FprsContext *ctx, uintptr_t index);
+
+/*
+ Closes the device.
+ */
+void fprs_device_close(FprsDevice *dev);
+
+/*
+ Returns the number of enroll stages required by the device.
+ */
+int fprs_enroll_stages(FprsDevice *dev);
+
+/*
+ Runs enrollment until the print is complete. `callback` is invoked for every stage.
+ On success serialized template is stored into `out_data`/`out_len` and must be freed
+ with `fprs_template_free()`.
+ */
+int fprs_enroll(FprsDevice *dev,
+                FprsResultCallback callback,
+                void *user_data,
+                uint8_t **out_data,
+                uintptr_t *out_len);
+
+/*
+ Wraps the print data returned by `fprs_enroll()` into a self-describing template with
+ the `finger` (1 for the left thumb to 10 for the right little finger) and the time
+ of enrollment, see `Template`. The template must be freed with `fprs_template_free()`.
+ */
+int fprs_template_serialize(const uint8_t *data,
+                            uintptr_t len,
+                            int finger,
+                            uint8_t **out_data,
+                            uintptr_t *out_len);
+
+/*
+ Extracts the print data from a template written by `fprs_template_serialize()`. The finger
+ of the template is stored into `out_finger`, 0 for labeled templates. The print data must
+ be freed with `fprs_template_free()`.
+ */
+int fprs_template_deserialize(const uint8_t *data,
+                              uintptr_t len,
+                              int *out_finger,
+                              uint8_t **out_data,
+                              uintptr_t *out_len);
+
+/*
+ Frees template returned by `fprs_enroll()`, `fprs_template_serialize()` or
+ `fprs_template_deserialize()`.
+ */
+void fprs_template_free(uint8_t *data, uintptr_t len);
+
+/*
+ Scans a finger and verifies it against the serialized template, retrying scans until
+ the result is known. `callback` is invoked for every retried scan.
+ Returns 1 on match, 0 on no match.
+ */
+int fprs_verify(FprsDevice *dev,
+                const uint8_t *data,
+                uintptr_t len,
+                FprsResultCallback callback,
+                void *user_data);
+
+/*
+ Scans a finger and looks it up in `count` serialized templates, retrying scans until
+ the result is known. `callback` is invoked for every retried scan.
+ Returns 1 on match (index of the matched template is stored into `offset`), 0 on no match.
+ */
+int fprs_identify(FprsDevice *dev,
+                  const uint8_t *const *templates,
+                  const uintptr_t *lens,
+                  uintptr_t count,
+                  uintptr_t *offset,
+                  FprsResultCallback callback,
+                  void *user_data);
+
+#endif /* FPRINT_RS_H */
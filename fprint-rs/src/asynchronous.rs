@@ -0,0 +1,796 @@
+//! Non-blocking operations on top of the asynchronous libfprint API (`fp_async_*`).
+//!
+//! libfprint is not thread-safe, so `AsyncContext` owns a thread which starts all operations
+//! and handles libfprint events. Results are delivered through futures, which can be awaited
+//! on any executor, e.g. from GUI or service event loops:
+//!
+//! ```ignore
+//! let context = AsyncContext::new()?;
+//! let devices = context.discover().await;
+//! let device = context.open(&devices.get(0).unwrap()).await?;
+//! let result = device.verify(&template).await?;
+//! ```
+//!
+//! Blocking functions of `FPrint` and `Device` can be used while the context is alive, all
+//! calls into libfprint are serialized by a process-wide lock. A blocking scan holds the lock
+//! until it finishes, so asynchronous operations make no progress meanwhile.
+
+use crate::{CancelToken, DiscoveredDev, DiscoveredDevices, DriverInfo, EnrollResult, FPrint};
+use crate::{IdentifyResult, PrintData, RawImage, VerifyResult};
+use std::{
+    convert::TryFrom,
+    future::Future,
+    marker::PhantomData,
+    os::raw::{c_int, c_void},
+    pin::Pin,
+    ptr,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex, PoisonError,
+    },
+    task::{Context, Poll, Waker},
+    thread::{self, JoinHandle},
+};
+
+/// How long the event thread waits for libfprint events before checking for new commands.
+const EVENTS_TIMEOUT_USEC: i32 = 50_000;
+
+/// Callback receiving results of intermediate enroll stages.
+pub type StageCallback = Box<dyn FnMut(&EnrollResult) + Send>;
+
+/// Result of an operation, shared between its future and the event thread.
+struct Shared<T> {
+    state: Mutex<(Option<T>, Option<Waker>)>,
+}
+
+// Results own their libfprint handles, they are moved from the event thread to the future
+// and never used by both threads.
+unsafe impl<T> Send for Shared<T> {}
+unsafe impl<T> Sync for Shared<T> {}
+
+/// Future of an operation started by `AsyncContext` or `AsyncDevice`. Dropping it does not
+/// cancel the operation, the result is discarded when the operation finishes. An operation
+/// of `AsyncDevice` whose future was dropped is stopped when the next one is started.
+pub struct Operation<'a, T> {
+    shared: Arc<Shared<T>>,
+    _borrow: PhantomData<&'a ()>,
+}
+
+impl<T> Future for Operation<'_, T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<T> {
+        let mut state = self
+            .shared
+            .state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        match state.0.take() {
+            Some(value) => Poll::Ready(value),
+            None => {
+                state.1 = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Event thread side of an `Operation`.
+struct Completer<T>(Arc<Shared<T>>);
+
+impl<T> Completer<T> {
+    fn complete(self, value: T) {
+        let mut state = self.0.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.0 = Some(value);
+        if let Some(waker) = state.1.take() {
+            waker.wake();
+        }
+    }
+}
+
+fn operation<'a, T>() -> (Operation<'a, T>, Completer<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new((None, None)),
+    });
+    let operation = Operation {
+        shared: shared.clone(),
+        _borrow: PhantomData,
+    };
+
+    (operation, Completer(shared))
+}
+
+/// libfprint pointer passed to the event thread.
+struct Raw<T>(*mut T);
+
+impl<T> Clone for Raw<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Raw<T> {}
+
+// Pointers are only used by libfprint calls on the event thread.
+unsafe impl<T> Send for Raw<T> {}
+
+type OpenCompleter = Completer<crate::Result<Opened>>;
+
+/// Device opened by the event thread, not yet owned by an `AsyncDevice`. It is closed when
+/// dropped, e.g. when the future of `AsyncContext::open()` is dropped before it completes.
+struct Opened {
+    device: Option<Raw<fprint_sys::fp_dev>>,
+    info: DriverInfo,
+    commands: Sender<Command>,
+}
+
+impl Opened {
+    fn into_raw(mut self) -> (Raw<fprint_sys::fp_dev>, DriverInfo) {
+        let device = self.device.take().expect("device is taken only once");
+
+        (device, std::mem::take(&mut self.info))
+    }
+}
+
+impl Drop for Opened {
+    fn drop(&mut self) {
+        if let Some(device) = self.device.take() {
+            send(&self.commands, Command::Close(device));
+        }
+    }
+}
+
+enum Command {
+    Discover(Completer<DiscoveredDevices>),
+    Open(Raw<fprint_sys::fp_dscv_dev>, OpenCompleter),
+    Start(Raw<fprint_sys::fp_dev>, Request, Option<CancelToken>),
+    Close(Raw<fprint_sys::fp_dev>),
+    Stop,
+}
+
+enum Request {
+    Capture(bool, Completer<crate::Result<RawImage>>),
+    Verify(Vec<u8>, Completer<crate::Result<VerifyResult>>),
+    Identify(Vec<Vec<u8>>, Completer<crate::Result<IdentifyResult>>),
+    Enroll(StageCallback, Completer<crate::Result<EnrollResult>>),
+}
+
+impl Request {
+    /// Completes the request, which was never started, with `FPrintError::Cancelled`.
+    fn cancel(self) {
+        match self {
+            Request::Capture(_, completer) => completer.complete(cancelled()),
+            Request::Verify(_, completer) => completer.complete(cancelled()),
+            Request::Identify(_, completer) => completer.complete(cancelled()),
+            Request::Enroll(_, completer) => completer.complete(cancelled()),
+        }
+    }
+}
+
+/// libfprint context with the thread running asynchronous operations.
+pub struct AsyncContext {
+    commands: Sender<Command>,
+    thread: Option<JoinHandle<()>>,
+    _fprint: FPrint,
+}
+
+impl AsyncContext {
+    /// Initialises libfprint and starts the event thread.
+    pub fn new() -> crate::Result<Self> {
+        let fprint = FPrint::new()?;
+        let (commands, receiver) = mpsc::channel();
+        let sender = commands.clone();
+        let events_fprint = fprint.clone();
+        let thread = thread::Builder::new()
+            .name("fprint-events".to_string())
+            .spawn(move || EventLoop::new(sender, events_fprint).run(receiver))?;
+
+        Ok(AsyncContext {
+            commands,
+            thread: Some(thread),
+            _fprint: fprint,
+        })
+    }
+
+    /// Scans the system for supported devices.
+    pub fn discover(&self) -> Operation<'static, DiscoveredDevices> {
+        let (operation, completer) = operation();
+        send(&self.commands, Command::Discover(completer));
+
+        operation
+    }
+
+    /// Opens the discovered device. If the future is dropped before it completes, the device
+    /// is closed as soon as it is opened.
+    pub fn open<'a>(
+        &'a self,
+        device: &'a DiscoveredDev,
+    ) -> impl Future<Output = crate::Result<AsyncDevice<'a>>> + 'a {
+        let (operation, completer) = operation::<'a, _>();
+        send(
+            &self.commands,
+            Command::Open(Raw(device.as_raw()), completer),
+        );
+
+        async move {
+            operation.await.map(|opened| {
+                let (device, info) = opened.into_raw();
+                AsyncDevice {
+                    device,
+                    info,
+                    commands: self.commands.clone(),
+                    cancel: None,
+                    _context: PhantomData,
+                }
+            })
+        }
+    }
+}
+
+impl Drop for AsyncContext {
+    /// Cancels running operations and waits for the event thread to finish.
+    fn drop(&mut self) {
+        send(&self.commands, Command::Stop);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Device opened by `AsyncContext::open()`. Operations borrow the device mutably until their
+/// futures are dropped, so only one of them runs at a time. The device is closed when dropped,
+/// its running operation is cancelled.
+pub struct AsyncDevice<'a> {
+    device: Raw<fprint_sys::fp_dev>,
+    info: DriverInfo,
+    commands: Sender<Command>,
+    cancel: Option<CancelToken>,
+    _context: PhantomData<&'a AsyncContext>,
+}
+
+impl AsyncDevice<'_> {
+    /// Sets token stopping operations of the device, including scans waiting for a finger.
+    /// Stopped operations complete with `FPrintError::Cancelled`, as well as operations
+    /// started while the token is cancelled.
+    pub fn cancel_token(mut self, cancel: CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Gets information about the driver of the device, see `Device::get_driver_info()`.
+    pub fn get_driver_info(&self) -> &DriverInfo {
+        &self.info
+    }
+
+    /// Gets the number of enroll stages required to enroll a fingerprint with the device.
+    pub fn get_nr_enroll_stages(&self) -> i32 {
+        self.info.enroll_stages
+    }
+
+    /// Captures an image, see `Device::capture_image()`.
+    pub fn capture(&mut self, unconditional: bool) -> Operation<'_, crate::Result<RawImage>> {
+        let (operation, completer) = operation();
+        self.start(Request::Capture(unconditional, completer));
+
+        operation
+    }
+
+    /// Scans a finger and verifies it against the serialized print,
+    /// see `Device::verify_finger_image()`.
+    pub fn verify(&mut self, template: &[u8]) -> Operation<'_, crate::Result<VerifyResult>> {
+        let (operation, completer) = operation();
+        self.start(Request::Verify(template.to_vec(), completer));
+
+        operation
+    }
+
+    /// Scans a finger and looks it up in the serialized prints,
+    /// see `Device::identify_finger_image()`.
+    pub fn identify<T: AsRef<[u8]>>(
+        &mut self,
+        gallery: &[T],
+    ) -> Operation<'_, crate::Result<IdentifyResult>> {
+        let gallery = gallery.iter().map(|data| data.as_ref().to_vec()).collect();
+        let (operation, completer) = operation();
+        self.start(Request::Identify(gallery, completer));
+
+        operation
+    }
+
+    /// Runs enroll stages until the enrollment completes or fails. Results of intermediate
+    /// stages are passed to `on_stage` on the event thread.
+    pub fn enroll(
+        &mut self,
+        on_stage: impl FnMut(&EnrollResult) + Send + 'static,
+    ) -> Operation<'_, crate::Result<EnrollResult>> {
+        let (operation, completer) = operation();
+        self.start(Request::Enroll(Box::new(on_stage), completer));
+
+        operation
+    }
+
+    fn start(&self, request: Request) {
+        send(
+            &self.commands,
+            Command::Start(self.device, request, self.cancel.clone()),
+        );
+    }
+}
+
+impl Drop for AsyncDevice<'_> {
+    fn drop(&mut self) {
+        send(&self.commands, Command::Close(self.device));
+    }
+}
+
+fn send(commands: &Sender<Command>, command: Command) {
+    // The event thread runs until the context is dropped, so sending can not fail.
+    let _ = commands.send(command);
+}
+
+/// Operation running in libfprint. Its address is passed to libfprint callbacks.
+struct Running {
+    device: *mut fprint_sys::fp_dev,
+    task: Task,
+    /// Prints passed to libfprint, freed when the operation is stopped.
+    prints: Vec<*mut fprint_sys::fp_print_data>,
+    finished: bool,
+    stopping: bool,
+    stopped: bool,
+    /// Device should be closed once the operation is stopped.
+    close: bool,
+    cancel: Option<CancelToken>,
+}
+
+enum Task {
+    Capture(
+        Completer<crate::Result<RawImage>>,
+        Option<crate::Result<RawImage>>,
+    ),
+    Verify(
+        Completer<crate::Result<VerifyResult>>,
+        Option<crate::Result<VerifyResult>>,
+    ),
+    Identify(
+        Completer<crate::Result<IdentifyResult>>,
+        Option<crate::Result<IdentifyResult>>,
+    ),
+    Enroll(
+        StageCallback,
+        Completer<crate::Result<EnrollResult>>,
+        Option<crate::Result<EnrollResult>>,
+    ),
+}
+
+impl Running {
+    /// Records the result reported by libfprint, taking ownership of `print` and `image`.
+    /// Images are kept only where the blocking API returns them.
+    unsafe fn record(
+        &mut self,
+        result: c_int,
+        offset: usize,
+        print: *mut fprint_sys::fp_print_data,
+        image: *mut fprint_sys::fp_img,
+    ) {
+        let keep_image = !self.finished
+            && match self.task {
+                Task::Capture(_, _) => result == 0,
+                Task::Enroll(_, _, _) => {
+                    result == fprint_sys::fp_enroll_result_FP_ENROLL_COMPLETE as c_int
+                        || result == fprint_sys::fp_enroll_result_FP_ENROLL_PASS as c_int
+                }
+                _ => false,
+            };
+        let image = if keep_image {
+            RawImage::from_raw(image)
+        } else {
+            fprint_sys::fp_img_free(image);
+            RawImage::new()
+        };
+        let print = PrintData::from_raw(print);
+
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+
+        match &mut self.task {
+            Task::Capture(_, slot) => {
+                *slot = Some(match result {
+                    0 => Ok(image),
+                    _ if result == -libc::ENOTSUP => Err(crate::FPrintError::NotSupported(
+                        crate::NotSupportContext::CapturingImage,
+                    )),
+                    _ => Err(crate::FPrintError::Other(result)),
+                })
+            }
+            Task::Verify(_, slot) => {
+                *slot = Some(if result < 0 {
+                    Err(crate::FPrintError::VerifyFailed(result))
+                } else {
+                    VerifyResult::try_from(result as u32)
+                })
+            }
+            Task::Identify(_, slot) => {
+                *slot = Some(if result == -libc::ENOTSUP {
+                    Err(crate::FPrintError::NotSupported(
+                        crate::NotSupportContext::Identify,
+                    ))
+                } else if result < 0 {
+                    Err(crate::FPrintError::IdentifyFailed(result))
+                } else {
+                    VerifyResult::try_from(result as u32).map(|result| match result {
+                        VerifyResult::Match => IdentifyResult::Matched(offset),
+                        n => IdentifyResult::Error(n),
+                    })
+                })
+            }
+            Task::Enroll(on_stage, _, slot) => {
+                let result = if result < 0 {
+                    Err(crate::FPrintError::UnexpectedAbort(result))
+                } else {
+                    EnrollResult::try_from((result as u32, print, image))
+                };
+
+                match result {
+                    Ok(EnrollResult::Complete(_, _)) | Ok(EnrollResult::Fail) | Err(_) => {
+                        *slot = Some(result)
+                    }
+                    Ok(stage) => {
+                        on_stage(&stage);
+                        self.finished = false;
+                    }
+                }
+            }
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        matches!(&self.cancel, Some(cancel) if cancel.is_cancelled())
+    }
+
+    /// Asks libfprint to stop the operation, `stopped` is set once it is done.
+    fn stop(&mut self) {
+        self.stopping = true;
+        let data = self as *mut Running as *mut c_void;
+        let result = unsafe {
+            match self.task {
+                Task::Capture(_, _) => {
+                    fprint_sys::fp_async_capture_stop(self.device, Some(stopped), data)
+                }
+                Task::Verify(_, _) => {
+                    fprint_sys::fp_async_verify_stop(self.device, Some(stopped), data)
+                }
+                Task::Identify(_, _) => {
+                    fprint_sys::fp_async_identify_stop(self.device, Some(stopped), data)
+                }
+                Task::Enroll(_, _, _) => {
+                    fprint_sys::fp_async_enroll_stop(self.device, Some(stopped), data)
+                }
+            }
+        };
+
+        if result < 0 {
+            self.stopped = true;
+        }
+    }
+
+    /// Frees the prints and completes the future. Operations stopped before libfprint
+    /// reported their result are completed with `FPrintError::Cancelled`.
+    fn complete(mut self) {
+        for print in self.prints.drain(..) {
+            unsafe { fprint_sys::fp_print_data_free(print) };
+        }
+
+        match self.task {
+            Task::Capture(completer, result) => {
+                completer.complete(result.unwrap_or_else(cancelled))
+            }
+            Task::Verify(completer, result) => completer.complete(result.unwrap_or_else(cancelled)),
+            Task::Identify(completer, result) => {
+                completer.complete(result.unwrap_or_else(cancelled))
+            }
+            Task::Enroll(_, completer, result) => {
+                completer.complete(result.unwrap_or_else(cancelled))
+            }
+        }
+    }
+}
+
+fn cancelled<T>() -> crate::Result<T> {
+    Err(crate::FPrintError::Cancelled)
+}
+
+/// State of the event thread. Operations and close flags are boxed, their addresses are
+/// passed to libfprint callbacks.
+#[allow(clippy::vec_box)]
+struct EventLoop {
+    sender: Sender<Command>,
+    fprint: FPrint,
+    running: Vec<Box<Running>>,
+    /// Requests waiting for the abandoned operation of their device to stop.
+    pending: Vec<(Raw<fprint_sys::fp_dev>, Request, Option<CancelToken>)>,
+    /// Devices being closed, set to `true` by the close callback.
+    closing: Vec<Box<bool>>,
+    stopping: bool,
+}
+
+impl EventLoop {
+    fn new(sender: Sender<Command>, fprint: FPrint) -> Self {
+        EventLoop {
+            sender,
+            fprint,
+            running: Vec::new(),
+            pending: Vec::new(),
+            closing: Vec::new(),
+            stopping: false,
+        }
+    }
+
+    fn run(mut self, commands: Receiver<Command>) {
+        loop {
+            // Operations are only touched under the libfprint lock, their callbacks may also
+            // run in blocking calls of other threads. The lock is released between iterations.
+            let _lock = crate::context::lock();
+            while let Ok(command) = commands.try_recv() {
+                self.handle(command);
+            }
+
+            for running in &mut self.running {
+                if running.finished || self.stopping || running.is_cancelled() {
+                    running.finished = true;
+                    if !running.stopping {
+                        running.stop();
+                    }
+                }
+            }
+
+            self.collect();
+            if self.stopping && self.running.is_empty() && self.closing.is_empty() {
+                return;
+            }
+
+            let mut timeout = fprint_sys::timeval {
+                tv_sec: 0,
+                tv_usec: EVENTS_TIMEOUT_USEC.into(),
+            };
+            unsafe { fprint_sys::fp_handle_events_timeout(&mut timeout) };
+
+            self.collect();
+        }
+    }
+
+    /// Completes stopped operations, starts requests waiting for them and forgets closed devices.
+    fn collect(&mut self) {
+        let (stopped, running): (Vec<_>, Vec<_>) =
+            self.running.drain(..).partition(|running| running.stopped);
+        self.running = running;
+
+        for running in stopped {
+            if running.close {
+                self.close(running.device);
+            }
+            running.complete();
+        }
+
+        for (device, request, cancel) in std::mem::take(&mut self.pending) {
+            if self.stopping {
+                request.cancel();
+            } else if self.is_busy(device.0) {
+                self.pending.push((device, request, cancel));
+            } else {
+                self.start(device.0, request, cancel);
+            }
+        }
+
+        self.closing.retain(|closed| !**closed);
+    }
+
+    fn handle(&mut self, command: Command) {
+        match command {
+            Command::Discover(completer) => completer.complete(self.fprint.discover()),
+            Command::Open(device, completer) => {
+                let data = Box::into_raw(Box::new((completer, self.sender.clone())));
+                let result = unsafe {
+                    fprint_sys::fp_async_dev_open(device.0, Some(opened), data as *mut c_void)
+                };
+                if result < 0 {
+                    let (completer, _) = *unsafe { Box::from_raw(data) };
+                    completer.complete(Err(crate::FPrintError::Other(result)));
+                }
+            }
+            Command::Start(device, request, cancel) => {
+                // Operations borrow their device mutably, so a new one is only started after
+                // the future of the previous one was dropped, and its result is not needed.
+                if let Some(running) = self.find_running(device.0) {
+                    running.finished = true;
+                }
+                self.cancel_pending(device.0);
+
+                if self.is_busy(device.0) {
+                    self.pending.push((device, request, cancel));
+                } else {
+                    self.start(device.0, request, cancel);
+                }
+            }
+            Command::Close(device) => {
+                self.cancel_pending(device.0);
+
+                match self.find_running(device.0) {
+                    Some(running) => {
+                        running.finished = true;
+                        running.close = true;
+                    }
+                    None => self.close(device.0),
+                }
+            }
+            Command::Stop => self.stopping = true,
+        }
+    }
+
+    fn find_running(&mut self, device: *mut fprint_sys::fp_dev) -> Option<&mut Running> {
+        self.running
+            .iter_mut()
+            .find(|running| running.device == device)
+            .map(|running| &mut **running)
+    }
+
+    /// Cancels the request waiting for the device, if any.
+    fn cancel_pending(&mut self, device: *mut fprint_sys::fp_dev) {
+        if let Some(index) = self
+            .pending
+            .iter()
+            .position(|(pending, _, _)| pending.0 == device)
+        {
+            self.pending.remove(index).1.cancel();
+        }
+    }
+
+    fn is_busy(&self, device: *mut fprint_sys::fp_dev) -> bool {
+        self.running.iter().any(|running| running.device == device)
+    }
+
+    fn start(
+        &mut self,
+        device: *mut fprint_sys::fp_dev,
+        request: Request,
+        cancel: Option<CancelToken>,
+    ) {
+        let mut prints = Vec::new();
+        let (task, unconditional) = match request {
+            Request::Capture(unconditional, completer) => {
+                (Task::Capture(completer, None), unconditional)
+            }
+            Request::Verify(template, completer) => match PrintData::from_bytes_raw(&template) {
+                Ok(print) => {
+                    prints.push(print);
+                    (Task::Verify(completer, None), false)
+                }
+                Err(e) => return completer.complete(Err(e)),
+            },
+            Request::Identify(gallery, completer) => {
+                for data in &gallery {
+                    match PrintData::from_bytes_raw(data) {
+                        Ok(print) => prints.push(print),
+                        Err(e) => {
+                            for print in prints {
+                                unsafe { fprint_sys::fp_print_data_free(print) };
+                            }
+                            return completer.complete(Err(e));
+                        }
+                    }
+                }
+                prints.push(ptr::null_mut());
+
+                (Task::Identify(completer, None), false)
+            }
+            Request::Enroll(on_stage, completer) => {
+                (Task::Enroll(on_stage, completer, None), false)
+            }
+        };
+
+        let mut running = Box::new(Running {
+            device,
+            task,
+            prints,
+            finished: false,
+            stopping: false,
+            stopped: false,
+            close: false,
+            cancel,
+        });
+        let data = &mut *running as *mut Running as *mut c_void;
+
+        let result = unsafe {
+            match running.task {
+                Task::Capture(_, _) => fprint_sys::fp_async_capture_start(
+                    device,
+                    unconditional as c_int,
+                    Some(captured),
+                    data,
+                ),
+                Task::Verify(_, _) => fprint_sys::fp_async_verify_start(
+                    device,
+                    running.prints[0],
+                    Some(captured),
+                    data,
+                ),
+                Task::Identify(_, _) => fprint_sys::fp_async_identify_start(
+                    device,
+                    running.prints.as_mut_ptr(),
+                    Some(identified),
+                    data,
+                ),
+                Task::Enroll(_, _, _) => {
+                    fprint_sys::fp_async_enroll_start(device, Some(enrolled), data)
+                }
+            }
+        };
+
+        if result < 0 {
+            unsafe { running.record(result, 0, ptr::null_mut(), ptr::null_mut()) };
+            running.complete();
+        } else {
+            self.running.push(running);
+        }
+    }
+
+    fn close(&mut self, device: *mut fprint_sys::fp_dev) {
+        let mut closed = Box::new(false);
+        let data = &mut *closed as *mut bool as *mut c_void;
+        unsafe { fprint_sys::fp_async_dev_close(device, Some(device_closed), data) };
+        self.closing.push(closed);
+    }
+}
+
+unsafe extern "C" fn opened(device: *mut fprint_sys::fp_dev, status: c_int, data: *mut c_void) {
+    let (completer, sender) = *Box::from_raw(data as *mut (OpenCompleter, Sender<Command>));
+    if status == 0 {
+        completer.complete(Ok(Opened {
+            device: Some(Raw(device)),
+            info: DriverInfo::read(device),
+            commands: sender,
+        }));
+    } else {
+        send(&sender, Command::Close(Raw(device)));
+        completer.complete(Err(crate::FPrintError::Other(status)));
+    }
+}
+
+unsafe extern "C" fn device_closed(_: *mut fprint_sys::fp_dev, data: *mut c_void) {
+    *(data as *mut bool) = true;
+}
+
+unsafe extern "C" fn stopped(_: *mut fprint_sys::fp_dev, data: *mut c_void) {
+    (*(data as *mut Running)).stopped = true;
+}
+
+unsafe extern "C" fn captured(
+    _: *mut fprint_sys::fp_dev,
+    result: c_int,
+    image: *mut fprint_sys::fp_img,
+    data: *mut c_void,
+) {
+    (*(data as *mut Running)).record(result, 0, ptr::null_mut(), image);
+}
+
+unsafe extern "C" fn identified(
+    _: *mut fprint_sys::fp_dev,
+    result: c_int,
+    offset: usize,
+    image: *mut fprint_sys::fp_img,
+    data: *mut c_void,
+) {
+    (*(data as *mut Running)).record(result, offset, ptr::null_mut(), image);
+}
+
+unsafe extern "C" fn enrolled(
+    _: *mut fprint_sys::fp_dev,
+    result: c_int,
+    print: *mut fprint_sys::fp_print_data,
+    image: *mut fprint_sys::fp_img,
+    data: *mut c_void,
+) {
+    (*(data as *mut Running)).record(result, 0, print, image);
+}
@@ -0,0 +1,262 @@
+//! User-keyed enrollment and identification backed by SQLite.
+//!
+//! Prints are stored in a database together with the id of their owner. For identification
+//! all prints are loaded into a `KeyedGallery`, which maps the offset of the matched print
+//! back to the user id.
+
+use crate::{
+    Consent, Device, EnrollResult, Gallery, IdentifyResult, PrintData, TemplateBytes, VerifyResult,
+};
+use rusqlite::{Connection, Statement, ToSql, NO_PARAMS};
+use std::path::Path;
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS fingers (
+    id        INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id   INTEGER,
+    finger    BLOB,
+    size_data INTEGER
+)";
+
+const INSERT_PRINT: &str = "INSERT INTO fingers
+    (user_id, finger, size_data, consent_policy, consent_at, consent_operator)
+    VALUES (?, ?, ?, ?, ?, ?)";
+
+/// Columns added after the initial schema. They are added to existing databases on open.
+const CONSENT_COLUMNS: &[(&str, &str)] = &[
+    ("consent_policy", "TEXT"),
+    ("consent_at", "INTEGER"),
+    ("consent_operator", "TEXT"),
+];
+
+/// Print stored in the database.
+#[derive(Debug, Clone)]
+pub struct PrintRecord {
+    pub id: i64,
+    pub user_id: i64,
+    pub data: TemplateBytes,
+    pub consent: Consent,
+}
+
+/// SQLite storage of prints keyed by user id. One user can have any number of prints.
+pub struct SqlitePrintStore {
+    conn: Connection,
+}
+
+impl SqlitePrintStore {
+    /// Opens the database, creating it and the prints table if needed.
+    pub fn open(path: impl AsRef<Path>) -> crate::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute(CREATE_TABLE, NO_PARAMS)?;
+        migrate(&conn)?;
+
+        Ok(SqlitePrintStore { conn })
+    }
+
+    /// Saves a print for the user.
+    pub fn save(&self, user_id: i64, data: &PrintData) -> crate::Result<()> {
+        self.save_with_consent(user_id, data, &Consent::default())
+    }
+
+    /// Saves a print for the user together with the consent metadata.
+    pub fn save_with_consent(
+        &self,
+        user_id: i64,
+        data: &PrintData,
+        consent: &Consent,
+    ) -> crate::Result<()> {
+        let data = data.as_bytes()?;
+        let mut stmt = self.conn.prepare_cached(INSERT_PRINT)?;
+        insert(&mut stmt, user_id, &data, consent)
+    }
+
+    /// Imports serialized prints in a single transaction using one prepared statement, for bulk
+    /// migrations. Either all prints are imported or none. Returns number of imported prints.
+    pub fn import<T: AsRef<[u8]>>(
+        &mut self,
+        prints: impl IntoIterator<Item = (i64, T, Consent)>,
+    ) -> crate::Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut imported = 0;
+        {
+            let mut stmt = tx.prepare(INSERT_PRINT)?;
+            for (user_id, data, consent) in prints {
+                let data = data.as_ref();
+                PrintData::from_bytes(data)?;
+                insert(&mut stmt, user_id, data, &consent)?;
+                imported += 1;
+            }
+        }
+        tx.commit()?;
+
+        Ok(imported)
+    }
+
+    /// Loads all stored prints. Records with damaged data (when stored size does not match
+    /// the data length) are skipped.
+    pub fn records(&self) -> crate::Result<Vec<PrintRecord>> {
+        let mut records = Vec::new();
+        self.for_each_record(|record| {
+            records.push(record);
+            Ok(())
+        })?;
+
+        Ok(records)
+    }
+
+    /// Streams stored prints to `f` one by one, without loading the whole table in memory,
+    /// so large databases can be exported. Records with damaged data are skipped.
+    pub fn for_each_record(
+        &self,
+        mut f: impl FnMut(PrintRecord) -> crate::Result<()>,
+    ) -> crate::Result<()> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, user_id, finger, size_data, consent_policy, consent_at, consent_operator
+                 FROM fingers ORDER BY id",
+        )?;
+        let rows = stmt.query_map(NO_PARAMS, |row| {
+            let size: i64 = row.get(3)?;
+            let record = PrintRecord {
+                id: row.get(0)?,
+                user_id: row.get(1)?,
+                data: TemplateBytes::from(row.get::<_, Vec<u8>>(2)?),
+                consent: Consent {
+                    policy_version: row.get(4)?,
+                    timestamp: row.get(5)?,
+                    operator_id: row.get(6)?,
+                },
+            };
+
+            Ok((size, record))
+        })?;
+
+        for row in rows {
+            let (size, record) = row?;
+            if record.data.len() as i64 == size {
+                f(record)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes all prints of the user. Freed pages are overwritten and the database is
+    /// vacuumed, so print data does not remain in the file. Returns number of removed prints.
+    /// For erasure requests remove the user from the gallery too, with
+    /// `KeyedGallery::remove_user()`.
+    pub fn secure_delete(&self, user_id: i64) -> crate::Result<usize> {
+        self.conn.execute_batch("PRAGMA secure_delete = ON")?;
+        let removed = self
+            .conn
+            .execute("DELETE FROM fingers WHERE user_id = ?", &[&user_id])?;
+        self.conn.execute_batch("VACUUM")?;
+
+        Ok(removed)
+    }
+
+    /// Loads all stored prints into a gallery for identification.
+    pub fn load_gallery(&self) -> crate::Result<KeyedGallery> {
+        let mut gallery = KeyedGallery::new();
+        self.for_each_record(|record| gallery.push(record.user_id, record.data.into_vec()))?;
+
+        Ok(gallery)
+    }
+}
+
+fn insert(stmt: &mut Statement, user_id: i64, data: &[u8], consent: &Consent) -> crate::Result<()> {
+    stmt.execute(&[
+        &user_id as &dyn ToSql,
+        &data,
+        &(data.len() as i64),
+        &consent.policy_version,
+        &consent.timestamp,
+        &consent.operator_id,
+    ])?;
+
+    Ok(())
+}
+
+/// Adds columns missing in databases created by older versions.
+fn migrate(conn: &Connection) -> crate::Result<()> {
+    let mut stmt = conn.prepare("PRAGMA table_info(fingers)")?;
+    let columns = stmt
+        .query_map(NO_PARAMS, |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (name, kind) in CONSENT_COLUMNS {
+        if !columns.iter().any(|column| column == name) {
+            let sql = format!("ALTER TABLE fingers ADD COLUMN {} {}", name, kind);
+            conn.execute(&sql, NO_PARAMS)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Result of identification against a `KeyedGallery`.
+#[derive(Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Identification {
+    /// Scanned finger belongs to the user with this id.
+    User(i64),
+    /// Scanned finger was not found in the gallery.
+    NoMatch,
+    /// Scan did not succeed and should be retried.
+    Retry(VerifyResult),
+}
+
+/// Collection of prints where every print is assigned to a user, see `Gallery`.
+#[derive(Debug, Default)]
+pub struct KeyedGallery {
+    prints: Gallery<i64>,
+}
+
+impl KeyedGallery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a print of the user. Print data is checked to be loadable by libfprint, so
+    /// offsets reported by identification always point to the right user.
+    pub fn push(&mut self, user_id: i64, data: Vec<u8>) -> crate::Result<()> {
+        let data = TemplateBytes::from(data);
+        self.prints.insert_bytes(user_id, &*data).map(|_| ())
+    }
+
+    /// Removes all prints of the user and returns their number.
+    pub fn remove_user(&mut self, user_id: i64) -> usize {
+        let before = self.prints.len();
+        self.prints.retain(|user, _| *user != user_id);
+
+        before - self.prints.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.prints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Performs a new scan and looks up the owner of the scanned finger.
+    pub fn identify(&self, device: &Device) -> crate::Result<Identification> {
+        let result = match device.identify(&self.prints)? {
+            IdentifyResult::Matched(matched) => Identification::User(*matched.key),
+            IdentifyResult::Error(VerifyResult::NoMatch) => Identification::NoMatch,
+            IdentifyResult::Error(reason) => Identification::Retry(reason),
+        };
+
+        Ok(result)
+    }
+}
+
+/// Runs enrollment stages until the print is complete. Every stage result is passed
+/// to `on_result`, so it can be reported to the user.
+pub fn enroll(
+    device: &Device,
+    mut on_result: impl FnMut(&EnrollResult),
+) -> crate::Result<PrintData> {
+    device
+        .enroll(|attempt| on_result(&attempt.result))
+        .map(|(print, _)| print)
+}
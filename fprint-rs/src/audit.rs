@@ -0,0 +1,392 @@
+//! Append-only, tamper-evident audit log of enroll, verify and identify events.
+//!
+//! Every record is written as a JSON line containing the MAC of the previous record, so
+//! the records form a chain. MACs are HMAC-SHA256 keyed with a secret of the writer: records
+//! can not be modified, removed or inserted without breaking the chain, which is checked
+//! by `verify()`.
+
+use crate::{util::hmac_sha256, Device, EnrollResult, Finger, IdentifyResult, VerifyResult};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use zeroize::Zeroizing;
+
+/// MAC used as the previous one by the first record of a log.
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Audited operation.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum AuditAction {
+    Enroll,
+    Verify,
+    Identify,
+}
+
+/// Outcome of the audited operation.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum AuditOutcome {
+    /// Enrollment stage passed.
+    Passed,
+    /// Enrollment completed.
+    Enrolled,
+    /// Scanned finger matched the print.
+    Matched,
+    /// Scanned finger did not match.
+    NoMatch,
+    /// Scan did not succeed and should be retried.
+    Retry,
+    /// Operation failed.
+    Failed,
+}
+
+/// Audited event.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    /// Key of the user performing the operation, if known.
+    pub user: Option<String>,
+    /// Finger used in the operation, if known.
+    pub finger: Option<String>,
+    pub action: AuditAction,
+    pub outcome: AuditOutcome,
+    /// Name of the driver of the device.
+    pub device: String,
+    /// Devtype of the device.
+    pub devtype: u32,
+}
+
+impl AuditEvent {
+    pub fn new(device: &Device, action: AuditAction, outcome: AuditOutcome) -> Self {
+        AuditEvent {
+            user: None,
+            finger: None,
+            action,
+            outcome,
+            device: device.get_driver_info().name.clone(),
+            devtype: device.get_dev_type(),
+        }
+    }
+
+    /// Creates event for the enroll stage `result`.
+    pub fn enroll(device: &Device, result: &crate::Result<EnrollResult>) -> Self {
+        let outcome = match result {
+            Ok(EnrollResult::Complete(_, _)) => AuditOutcome::Enrolled,
+            Ok(EnrollResult::Pass(_)) => AuditOutcome::Passed,
+            Ok(EnrollResult::Fail) | Err(_) => AuditOutcome::Failed,
+            Ok(_) => AuditOutcome::Retry,
+        };
+
+        Self::new(device, AuditAction::Enroll, outcome)
+    }
+
+    /// Creates event for the verification `result`.
+    pub fn verify(device: &Device, result: &crate::Result<VerifyResult>) -> Self {
+        let outcome = match result {
+            Ok(result) => verify_outcome(result),
+            Err(_) => AuditOutcome::Failed,
+        };
+
+        Self::new(device, AuditAction::Verify, outcome)
+    }
+
+    /// Creates event for the identification `result`.
+    pub fn identify(device: &Device, result: &crate::Result<IdentifyResult>) -> Self {
+        let outcome = match result {
+            Ok(IdentifyResult::Matched(_)) => AuditOutcome::Matched,
+            Ok(IdentifyResult::Error(result)) => verify_outcome(result),
+            Err(_) => AuditOutcome::Failed,
+        };
+
+        Self::new(device, AuditAction::Identify, outcome)
+    }
+
+    /// Creates event for the `PrintStore::authenticate()` decision.
+    pub fn authenticate(device: &Device, result: &crate::Result<crate::AuthDecision>) -> Self {
+        let outcome = match result {
+            Ok(crate::AuthDecision::Accepted(_)) => AuditOutcome::Matched,
+            Ok(crate::AuthDecision::Rejected) => AuditOutcome::NoMatch,
+            Ok(crate::AuthDecision::Inconclusive(_)) => AuditOutcome::Retry,
+            Ok(_) | Err(_) => AuditOutcome::Failed,
+        };
+
+        let event = Self::new(device, AuditAction::Verify, outcome);
+        match result {
+            Ok(crate::AuthDecision::Accepted(finger)) => event.with_finger(*finger),
+            _ => event,
+        }
+    }
+
+    pub fn with_user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    pub fn with_finger(mut self, finger: Finger) -> Self {
+        self.finger = Some(finger.to_string());
+        self
+    }
+}
+
+/// Record of the log.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Number of the record in the log, starting from 0.
+    pub seq: u64,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+    /// MAC of the previous record.
+    pub prev: String,
+    /// MAC of this record.
+    pub mac: String,
+}
+
+impl AuditRecord {
+    fn compute_mac(&self, key: &[u8]) -> crate::Result<String> {
+        let payload = serde_json::to_vec(&(self.seq, self.timestamp, &self.event, &self.prev))
+            .map_err(std::io::Error::from)?;
+
+        hmac_sha256(key, &payload)
+    }
+}
+
+/// Writer of the audit log.
+pub struct AuditLog {
+    file: File,
+    key: Zeroizing<Vec<u8>>,
+    seq: u64,
+    last: String,
+}
+
+impl AuditLog {
+    /// Opens the log for appending, creating it if needed. Existing records are verified
+    /// with the `key` and new records continue their chain.
+    pub fn open(path: impl AsRef<Path>, key: &[u8]) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .mode(0o600)
+            .open(path)?;
+        let (seq, last) = read_chain(path, key)?;
+
+        Ok(AuditLog {
+            file,
+            key: Zeroizing::new(key.to_vec()),
+            seq,
+            last,
+        })
+    }
+
+    /// Appends the event to the log. Record is synced to disk before returning.
+    pub fn append(&mut self, event: AuditEvent) -> crate::Result<AuditRecord> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        let mut record = AuditRecord {
+            seq: self.seq,
+            timestamp,
+            event,
+            prev: self.last.clone(),
+            mac: String::new(),
+        };
+        record.mac = record.compute_mac(&self.key)?;
+
+        let mut line = serde_json::to_vec(&record).map_err(std::io::Error::from)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.file.sync_data()?;
+
+        self.seq += 1;
+        self.last = record.mac.clone();
+
+        Ok(record)
+    }
+}
+
+/// Verifies the chain of the log and returns the number of records in it.
+/// `FPrintError::AuditChainBroken` with the number of the first bad record is returned
+/// if the log was tampered with.
+pub fn verify(path: impl AsRef<Path>, key: &[u8]) -> crate::Result<u64> {
+    read_chain(path.as_ref(), key).map(|(seq, _)| seq)
+}
+
+fn read_chain(path: &Path, key: &[u8]) -> crate::Result<(u64, String)> {
+    let mut seq = 0;
+    let mut last = GENESIS.to_string();
+
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        let record: AuditRecord =
+            serde_json::from_str(&line).map_err(|_| crate::FPrintError::AuditChainBroken(seq))?;
+
+        let valid = record.seq == seq
+            && crate::constant_time_eq(record.prev.as_bytes(), last.as_bytes())
+            && crate::constant_time_eq(record.mac.as_bytes(), record.compute_mac(key)?.as_bytes());
+        if !valid {
+            return Err(crate::FPrintError::AuditChainBroken(seq));
+        }
+
+        seq += 1;
+        last = record.mac;
+    }
+
+    Ok((seq, last))
+}
+
+fn verify_outcome(result: &VerifyResult) -> AuditOutcome {
+    match result {
+        VerifyResult::Match => AuditOutcome::Matched,
+        VerifyResult::NoMatch => AuditOutcome::NoMatch,
+        _ => AuditOutcome::Retry,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::PathBuf};
+
+    const KEY: &[u8] = b"audit key";
+
+    /// Log in the temporary directory, removed when dropped.
+    struct TempLog(PathBuf);
+
+    impl TempLog {
+        fn new(name: &str) -> Self {
+            let name = format!("fprint-rs-{}-{}.log", std::process::id(), name);
+            let log = TempLog(std::env::temp_dir().join(name));
+            let _ = fs::remove_file(&log.0);
+            log
+        }
+
+        fn lines(&self) -> Vec<String> {
+            let log = fs::read_to_string(&self.0).unwrap();
+            log.lines().map(str::to_string).collect()
+        }
+
+        fn write_lines(&self, lines: &[String]) {
+            fs::write(
+                &self.0,
+                lines
+                    .iter()
+                    .map(|line| format!("{}\n", line))
+                    .collect::<String>(),
+            )
+            .unwrap();
+        }
+    }
+
+    impl Drop for TempLog {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn event(user: &str, outcome: AuditOutcome) -> AuditEvent {
+        AuditEvent {
+            user: None,
+            finger: None,
+            action: AuditAction::Verify,
+            outcome,
+            device: "upekts".to_string(),
+            devtype: 0,
+        }
+        .with_user(user)
+        .with_finger(Finger::RightIndex)
+    }
+
+    fn written(name: &str) -> TempLog {
+        let log = TempLog::new(name);
+        let mut writer = AuditLog::open(&log.0, KEY).unwrap();
+        writer
+            .append(event("alice", AuditOutcome::Matched))
+            .unwrap();
+        writer.append(event("bob", AuditOutcome::NoMatch)).unwrap();
+        writer.append(event("carol", AuditOutcome::Retry)).unwrap();
+        log
+    }
+
+    fn is_broken(result: crate::Result<u64>, at: u64) -> bool {
+        matches!(result, Err(crate::FPrintError::AuditChainBroken(seq)) if seq == at)
+    }
+
+    #[test]
+    fn records_are_chained() {
+        let log = TempLog::new("chain");
+        let mut writer = AuditLog::open(&log.0, KEY).unwrap();
+        let first = writer
+            .append(event("alice", AuditOutcome::Matched))
+            .unwrap();
+        let second = writer.append(event("bob", AuditOutcome::NoMatch)).unwrap();
+
+        assert_eq!((first.seq, second.seq), (0, 1));
+        assert_eq!(first.prev, GENESIS);
+        assert_eq!(second.prev, first.mac);
+        assert_eq!(second.event.user.as_deref(), Some("bob"));
+        assert_eq!(verify(&log.0, KEY).unwrap(), 2);
+    }
+
+    #[test]
+    fn reopened_log_continues_chain() {
+        let log = written("reopen");
+        let last: AuditRecord = serde_json::from_str(&log.lines()[2]).unwrap();
+
+        let mut writer = AuditLog::open(&log.0, KEY).unwrap();
+        let record = writer.append(event("dave", AuditOutcome::Failed)).unwrap();
+
+        assert_eq!(record.seq, 3);
+        assert_eq!(record.prev, last.mac);
+        assert_eq!(verify(&log.0, KEY).unwrap(), 4);
+    }
+
+    #[test]
+    fn tampered_records_break_chain() {
+        let log = written("tamper");
+        let lines = log.lines();
+
+        let mut modified = lines.clone();
+        modified[1] = modified[1].replace("no_match", "matched");
+        log.write_lines(&modified);
+        assert!(is_broken(verify(&log.0, KEY), 1));
+        assert!(AuditLog::open(&log.0, KEY).is_err());
+
+        log.write_lines(&[lines[0].clone(), lines[2].clone()]);
+        assert!(is_broken(verify(&log.0, KEY), 1));
+
+        log.write_lines(&[lines[0].clone(), lines[0].clone()]);
+        assert!(is_broken(verify(&log.0, KEY), 1));
+
+        log.write_lines(&[lines[0].clone(), "not a record".to_string()]);
+        assert!(is_broken(verify(&log.0, KEY), 1));
+
+        log.write_lines(&lines);
+        assert!(is_broken(verify(&log.0, b"other key"), 0));
+        assert_eq!(verify(&log.0, KEY).unwrap(), 3);
+    }
+
+    #[test]
+    fn verify_results_map_to_outcomes() {
+        assert_eq!(verify_outcome(&VerifyResult::Match), AuditOutcome::Matched);
+        assert_eq!(
+            verify_outcome(&VerifyResult::NoMatch),
+            AuditOutcome::NoMatch
+        );
+        assert_eq!(verify_outcome(&VerifyResult::Retry), AuditOutcome::Retry);
+        assert_eq!(
+            verify_outcome(&VerifyResult::RetryTooShort),
+            AuditOutcome::Retry
+        );
+    }
+}
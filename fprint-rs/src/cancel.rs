@@ -0,0 +1,46 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Token aborting multi-stage operations, e.g. from a "Cancel" button or a shutdown signal.
+/// Clones share the state, so one clone is passed to the operation and another is cancelled:
+///
+/// ```ignore
+/// let cancel = CancelToken::new();
+/// let mut session = EnrollSession::new(&device).cancel_token(cancel.clone());
+/// on_cancel_clicked(move || cancel.cancel());
+/// ```
+///
+/// Cancelled operations return `FPrintError::Cancelled`.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels operations using the token, now and until it is reset.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Makes the token usable for new operations.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+
+    /// Returns `FPrintError::Cancelled` if the token is cancelled.
+    pub fn check(&self) -> crate::Result<()> {
+        if self.is_cancelled() {
+            Err(crate::FPrintError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
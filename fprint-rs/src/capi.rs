@@ -0,0 +1,368 @@
+//! Stable C ABI.
+//!
+//! Small `extern "C"` surface over the high-level API for C/C++ applications. Build it as
+//! a shared library with:
+//!
+//! ```bash
+//! cargo build -p fprint-rs --release --features capi
+//! ```
+//!
+//! Declarations are available in `include/fprint_rs.h` (generated by cbindgen from this module).
+//!
+//! Functions returning `int` return a non-negative result on success and a negative errno-like
+//! code on failure. Devices must be closed before the context they were opened from is freed.
+//! Panics never unwind into the caller, they are reported as `-EIO` or NULL.
+//!
+//! # Safety
+//!
+//! All pointers passed to these functions must be either NULL or valid pointers previously
+//! returned by this library (or, for input buffers, pointing to `len` readable bytes).
+//! Context and device handles must not be used from several threads at the same time.
+
+#![allow(clippy::missing_safety_doc)]
+
+use crate::{
+    Device, DiscoveredDevices, EnrollResult, FPrint, Finger, IdentifyResult, IdentifyScratch,
+    PrintData, Template, VerifyResult,
+};
+use std::{
+    convert::TryFrom,
+    os::raw::{c_int, c_void},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+use zeroize::Zeroize;
+
+/// Called for every enrollment stage or retried scan with the libfprint result code.
+pub type FprsResultCallback = Option<unsafe extern "C" fn(result: c_int, user_data: *mut c_void)>;
+
+/// libfprint context with the list of discovered devices.
+pub struct FprsContext {
+    devices: DiscoveredDevices,
+    fprint: FPrint,
+}
+
+/// Opened device.
+pub struct FprsDevice(Device);
+
+/// Initialises libfprint and discovers devices. Returns NULL on failure.
+#[no_mangle]
+pub extern "C" fn fprs_init() -> *mut FprsContext {
+    guard(ptr::null_mut(), || match FPrint::new() {
+        Ok(fprint) => Box::into_raw(Box::new(FprsContext {
+            devices: fprint.discover(),
+            fprint,
+        })),
+        Err(_) => ptr::null_mut(),
+    })
+}
+
+/// Frees the context and deinitialises libfprint.
+#[no_mangle]
+pub unsafe extern "C" fn fprs_exit(ctx: *mut FprsContext) {
+    guard((), || {
+        if !ctx.is_null() {
+            drop(Box::from_raw(ctx));
+        }
+    })
+}
+
+/// Scans the system for devices again, replacing the list of devices discovered before.
+/// Returns the number of discovered devices.
+#[no_mangle]
+pub unsafe extern "C" fn fprs_discover(ctx: *mut FprsContext) -> c_int {
+    guard(-libc::EIO, || match ctx.as_mut() {
+        Some(ctx) => {
+            ctx.devices = ctx.fprint.discover();
+            ctx.devices.len() as c_int
+        }
+        None => -libc::EINVAL,
+    })
+}
+
+/// Returns the number of devices discovered by `fprs_init()` or `fprs_discover()`.
+#[no_mangle]
+pub unsafe extern "C" fn fprs_device_count(ctx: *const FprsContext) -> c_int {
+    guard(-libc::EIO, || match ctx.as_ref() {
+        Some(ctx) => ctx.devices.len() as c_int,
+        None => -libc::EINVAL,
+    })
+}
+
+/// Opens discovered device by its index. Returns NULL if there is no such device or it
+/// could not be opened.
+#[no_mangle]
+pub unsafe extern "C" fn fprs_device_open(ctx: *mut FprsContext, index: usize) -> *mut FprsDevice {
+    guard(ptr::null_mut(), || {
+        let ctx = match ctx.as_ref() {
+            Some(ctx) => ctx,
+            None => return ptr::null_mut(),
+        };
+
+        match ctx.devices.get(index) {
+            Some(device) => match device.open() {
+                Ok(device) => Box::into_raw(Box::new(FprsDevice(device))),
+                Err(_) => ptr::null_mut(),
+            },
+            None => ptr::null_mut(),
+        }
+    })
+}
+
+/// Closes the device.
+#[no_mangle]
+pub unsafe extern "C" fn fprs_device_close(dev: *mut FprsDevice) {
+    guard((), || {
+        if !dev.is_null() {
+            drop(Box::from_raw(dev));
+        }
+    })
+}
+
+/// Returns the number of enroll stages required by the device.
+#[no_mangle]
+pub unsafe extern "C" fn fprs_enroll_stages(dev: *mut FprsDevice) -> c_int {
+    guard(-libc::EIO, || match dev.as_ref() {
+        Some(dev) => dev.0.get_nr_enroll_stages(),
+        None => -libc::EINVAL,
+    })
+}
+
+/// Runs enrollment until the print is complete. `callback` is invoked for every stage.
+/// On success serialized template is stored into `out_data`/`out_len` and must be freed
+/// with `fprs_template_free()`.
+#[no_mangle]
+pub unsafe extern "C" fn fprs_enroll(
+    dev: *mut FprsDevice,
+    callback: FprsResultCallback,
+    user_data: *mut c_void,
+    out_data: *mut *mut u8,
+    out_len: *mut usize,
+) -> c_int {
+    guard(-libc::EIO, || {
+        let dev = match dev.as_ref() {
+            Some(dev) if !out_data.is_null() && !out_len.is_null() => dev,
+            _ => return -libc::EINVAL,
+        };
+
+        loop {
+            let result = match dev.0.enroll_finger_image() {
+                Ok(result) => result,
+                Err(e) => return error_code(&e),
+            };
+
+            if let Some(callback) = callback {
+                callback(enroll_code(&result), user_data);
+            }
+
+            if let EnrollResult::Complete(print, _) = result {
+                return match print.as_bytes() {
+                    Ok(bytes) => store_bytes(&bytes, out_data, out_len),
+                    Err(e) => error_code(&e),
+                };
+            }
+        }
+    })
+}
+
+/// Wraps the print data returned by `fprs_enroll()` into a self-describing template with
+/// the `finger` (1 for the left thumb to 10 for the right little finger) and the time
+/// of enrollment, see `Template`. The template must be freed with `fprs_template_free()`.
+#[no_mangle]
+pub unsafe extern "C" fn fprs_template_serialize(
+    data: *const u8,
+    len: usize,
+    finger: c_int,
+    out_data: *mut *mut u8,
+    out_len: *mut usize,
+) -> c_int {
+    guard(-libc::EIO, || {
+        if data.is_null() || out_data.is_null() || out_len.is_null() {
+            return -libc::EINVAL;
+        }
+        let finger = match u32::try_from(finger)
+            .ok()
+            .and_then(|f| Finger::try_from(f).ok())
+        {
+            Some(finger) => finger,
+            None => return -libc::EINVAL,
+        };
+
+        let bytes = PrintData::from_bytes(slice::from_raw_parts(data, len))
+            .and_then(|print| Template::new(print, finger).to_bytes());
+        match bytes {
+            Ok(bytes) => store_bytes(&bytes, out_data, out_len),
+            Err(e) => error_code(&e),
+        }
+    })
+}
+
+/// Extracts the print data from a template written by `fprs_template_serialize()`. The finger
+/// of the template is stored into `out_finger`, 0 for labeled templates. The print data must
+/// be freed with `fprs_template_free()`.
+#[no_mangle]
+pub unsafe extern "C" fn fprs_template_deserialize(
+    data: *const u8,
+    len: usize,
+    out_finger: *mut c_int,
+    out_data: *mut *mut u8,
+    out_len: *mut usize,
+) -> c_int {
+    guard(-libc::EIO, || {
+        if data.is_null() || out_finger.is_null() || out_data.is_null() || out_len.is_null() {
+            return -libc::EINVAL;
+        }
+
+        let template = match Template::from_bytes(slice::from_raw_parts(data, len)) {
+            Ok(template) => template,
+            Err(e) => return error_code(&e),
+        };
+        match template.get_print().as_bytes() {
+            Ok(bytes) => {
+                *out_finger = template.get_finger().map_or(0, |finger| finger as c_int);
+                store_bytes(&bytes, out_data, out_len)
+            }
+            Err(e) => error_code(&e),
+        }
+    })
+}
+
+/// Frees template returned by `fprs_enroll()`, `fprs_template_serialize()` or
+/// `fprs_template_deserialize()`.
+#[no_mangle]
+pub unsafe extern "C" fn fprs_template_free(data: *mut u8, len: usize) {
+    guard((), || {
+        if !data.is_null() {
+            Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)).zeroize();
+        }
+    })
+}
+
+/// Scans a finger and verifies it against the serialized template, retrying scans until
+/// the result is known. `callback` is invoked for every retried scan.
+/// Returns 1 on match, 0 on no match.
+#[no_mangle]
+pub unsafe extern "C" fn fprs_verify(
+    dev: *mut FprsDevice,
+    data: *const u8,
+    len: usize,
+    callback: FprsResultCallback,
+    user_data: *mut c_void,
+) -> c_int {
+    guard(-libc::EIO, || {
+        let dev = match dev.as_ref() {
+            Some(dev) if !data.is_null() => dev,
+            _ => return -libc::EINVAL,
+        };
+        let mut print = match PrintData::from_bytes(slice::from_raw_parts(data, len)) {
+            Ok(print) => print,
+            Err(e) => return error_code(&e),
+        };
+
+        loop {
+            match dev.0.verify_finger_image(&mut print) {
+                Ok(VerifyResult::Match) => return 1,
+                Ok(VerifyResult::NoMatch) => return 0,
+                Ok(retry) => {
+                    if let Some(callback) = callback {
+                        callback(retry as c_int, user_data);
+                    }
+                }
+                Err(e) => return error_code(&e),
+            }
+        }
+    })
+}
+
+/// Scans a finger and looks it up in `count` serialized templates, retrying scans until
+/// the result is known. `callback` is invoked for every retried scan.
+/// Returns 1 on match (index of the matched template is stored into `offset`), 0 on no match.
+#[no_mangle]
+pub unsafe extern "C" fn fprs_identify(
+    dev: *mut FprsDevice,
+    templates: *const *const u8,
+    lens: *const usize,
+    count: usize,
+    offset: *mut usize,
+    callback: FprsResultCallback,
+    user_data: *mut c_void,
+) -> c_int {
+    guard(-libc::EIO, || {
+        let dev = match dev.as_ref() {
+            Some(dev) if !templates.is_null() && !lens.is_null() && !offset.is_null() => dev,
+            _ => return -libc::EINVAL,
+        };
+
+        let templates = slice::from_raw_parts(templates, count);
+        let lens = slice::from_raw_parts(lens, count);
+        if templates.iter().any(|data| data.is_null()) {
+            return -libc::EINVAL;
+        }
+        let gallery = templates
+            .iter()
+            .zip(lens)
+            .map(|(&data, &len)| slice::from_raw_parts(data, len))
+            .collect::<Vec<_>>();
+
+        let mut scratch = IdentifyScratch::default();
+        loop {
+            match dev.0.identify_finger_image_with(&gallery, &mut scratch) {
+                Ok(IdentifyResult::Matched(matched)) => {
+                    *offset = matched;
+                    return 1;
+                }
+                Ok(IdentifyResult::Error(VerifyResult::NoMatch)) => return 0,
+                Ok(IdentifyResult::Error(retry)) => {
+                    if let Some(callback) = callback {
+                        callback(retry as c_int, user_data);
+                    }
+                }
+                Err(e) => return error_code(&e),
+            }
+        }
+    })
+}
+
+/// Runs the body of an exported function, returning `on_panic` if it panics, so panics
+/// do not unwind across the FFI boundary.
+fn guard<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(on_panic)
+}
+
+/// Copies `bytes` into a buffer owned by the caller, freed with `fprs_template_free()`.
+unsafe fn store_bytes(bytes: &[u8], out_data: *mut *mut u8, out_len: *mut usize) -> c_int {
+    let bytes = bytes.to_vec().into_boxed_slice();
+    *out_len = bytes.len();
+    *out_data = Box::into_raw(bytes) as *mut u8;
+
+    0
+}
+
+fn enroll_code(result: &EnrollResult) -> c_int {
+    match result {
+        EnrollResult::Complete(_, _) => 1,
+        EnrollResult::Fail => 2,
+        EnrollResult::Pass(_) => 3,
+        EnrollResult::Retry => 100,
+        EnrollResult::RetryTooShort => 101,
+        EnrollResult::RetryCenterFinger => 102,
+        EnrollResult::RetryRemoveFinger => 103,
+    }
+}
+
+fn error_code(e: &crate::FPrintError) -> c_int {
+    match e {
+        crate::FPrintError::UnexpectedAbort(code)
+        | crate::FPrintError::VerifyFailed(code)
+        | crate::FPrintError::IdentifyFailed(code)
+            if *code < 0 =>
+        {
+            *code
+        }
+        crate::FPrintError::NotSupported(_) => -libc::ENOTSUP,
+        crate::FPrintError::NeedError
+        | crate::FPrintError::CorruptTemplate { .. }
+        | crate::FPrintError::InvalidTemplate(_) => -libc::EINVAL,
+        _ => -libc::EIO,
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    marker::PhantomData,
+    sync::{Condvar, Mutex, PoisonError},
+    thread::{self, ThreadId},
+};
+
+/// Number of alive `Context`s in the process. libfprint is initialised by the first one
+/// and deinitialised by the last one.
+static CONTEXTS: Mutex<usize> = Mutex::new(0);
+
+/// Thread holding the libfprint lock and how many times it has taken it.
+static OWNER: Mutex<Option<(ThreadId, usize)>> = Mutex::new(None);
+static RELEASED: Condvar = Condvar::new();
+
+/// Guard of the process-wide libfprint lock returned by `lock()`.
+pub(crate) struct LibraryGuard(PhantomData<*const ()>);
+
+/// Takes the process-wide libfprint lock. libfprint is not thread-safe, so every call
+/// driving it (initialisation, discovery, opening and closing devices, scans and event
+/// handling) is made under the lock, and devices can be used from any thread. Blocking scans
+/// hold the lock until they finish, so scans of different devices run one after another.
+///
+/// The lock is reentrant, libfprint callbacks running under it may call the crate again.
+pub(crate) fn lock() -> LibraryGuard {
+    let thread = thread::current().id();
+    let mut owner = OWNER.lock().unwrap_or_else(PoisonError::into_inner);
+    loop {
+        match &mut *owner {
+            Some((id, depth)) if *id == thread => *depth += 1,
+            Some(_) => {
+                owner = RELEASED.wait(owner).unwrap_or_else(PoisonError::into_inner);
+                continue;
+            }
+            None => *owner = Some((thread, 1)),
+        }
+
+        return LibraryGuard(PhantomData);
+    }
+}
+
+impl Drop for LibraryGuard {
+    fn drop(&mut self) {
+        let mut owner = OWNER.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((_, depth)) = &mut *owner {
+            *depth -= 1;
+            if *depth == 0 {
+                *owner = None;
+                RELEASED.notify_one();
+            }
+        }
+    }
+}
+
+/// Initialised libfprint. It is shared by `FPrint` and the devices discovered through it,
+/// libfprint is deinitialised when the last of them is dropped.
+#[derive(Debug)]
+pub(crate) struct Context(());
+
+impl Context {
+    /// Initialises libfprint unless another context is alive.
+    pub(crate) fn acquire() -> crate::Result<Self> {
+        // The libfprint lock is always taken before `CONTEXTS`.
+        let _lock = lock();
+        let mut contexts = CONTEXTS.lock().unwrap_or_else(PoisonError::into_inner);
+        if *contexts == 0 {
+            let res = unsafe { fprint_sys::fp_init() } as i32;
+            if res != 0 {
+                return Err(crate::FPrintError::InitError(res));
+            }
+        }
+        *contexts += 1;
+
+        Ok(Context(()))
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        let _lock = lock();
+        let mut contexts = CONTEXTS.lock().unwrap_or_else(PoisonError::into_inner);
+        *contexts -= 1;
+
+        // With `no-exit` libfprint stays initialised, for applications which share it
+        // with other libraries.
+        if *contexts == 0 && cfg!(not(feature = "no-exit")) {
+            unsafe { fprint_sys::fp_exit() };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    #[test]
+    fn lock_is_reentrant() {
+        let _outer = lock();
+        let _inner = lock();
+    }
+
+    #[test]
+    fn lock_excludes_other_threads() {
+        let guard = lock();
+        let taken = Arc::new(AtomicBool::new(false));
+        let other = {
+            let taken = taken.clone();
+            thread::spawn(move || {
+                let _lock = lock();
+                taken.store(true, Ordering::SeqCst);
+            })
+        };
+
+        thread::sleep(Duration::from_millis(50));
+        assert!(!taken.load(Ordering::SeqCst));
+
+        drop(guard);
+        other.join().unwrap();
+        assert!(taken.load(Ordering::SeqCst));
+    }
+}
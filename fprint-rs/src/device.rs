@@ -1,34 +1,131 @@
-use crate::print_data::PrintData;
-use crate::{Driver, Finger};
+use crate::print_data::{PrintData, TemplateBytes};
+use crate::print_store::{device_dir, finger_file};
+use crate::scan::{self, Interrupt};
+use crate::{context::Context, CancelToken, DeviceOptions, Driver, DriverInfo, Finger};
 use std::{
+    collections::HashMap,
     convert::TryFrom,
+    ffi::CString,
     fmt::{Display, Error, Formatter},
+    io,
+    ops::{ControlFlow, Deref},
     os::{
         raw::{c_char, c_int, c_uchar},
         unix::ffi::OsStrExt,
     },
-    path::Path,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
+/// Shortest interval of `Device::with_progress()` ticks.
+const MIN_TICK_INTERVAL: Duration = Duration::from_millis(10);
+
 ///
-#[derive(Debug, Clone)]
-pub struct Device(*mut fprint_sys::fp_dev);
+#[derive(Debug)]
+pub struct Device(
+    *mut fprint_sys::fp_dev,
+    DriverInfo,
+    DeviceOptions,
+    Option<Arc<Context>>,
+);
+
+// The device can be moved to a worker thread: its handle is only used by calls made through
+// the `Device`, and calls driving libfprint are serialized by the process-wide lock
+// of `context::lock()`. It is not `Sync`, operations of one device must not be interleaved,
+// see `SyncDevice` for sharing it between threads.
+unsafe impl Send for Device {}
 
 impl Device {
-    pub fn new(device: *mut fprint_sys::fp_dev) -> Self {
-        Device(device)
+    /// Wraps a device handle opened by libfprint.
+    ///
+    /// # Safety
+    ///
+    /// `device` must be a valid handle returned by `fp_dev_open()`, not owned by anything
+    /// else. It is closed when the `Device` is dropped.
+    pub unsafe fn new(device: *mut fprint_sys::fp_dev) -> Self {
+        Self::with_options(device, DeviceOptions::default())
+    }
+
+    /// Same as `Device::new()`, with the policy of the device set by `options`.
+    ///
+    /// # Safety
+    ///
+    /// See `Device::new()`.
+    pub unsafe fn with_options(device: *mut fprint_sys::fp_dev, options: DeviceOptions) -> Self {
+        Device(device, DriverInfo::read(device), options, None)
+    }
+
+    /// Keeps libfprint initialised while the device is alive.
+    pub(crate) fn in_context(mut self, context: Option<Arc<Context>>) -> Self {
+        self.3 = context;
+        self
+    }
+
+    /// Same as `Device::new()`.
+    ///
+    /// # Safety
+    ///
+    /// See `Device::new()`.
+    pub unsafe fn from_raw(raw: *mut fprint_sys::fp_dev) -> Self {
+        Self::new(raw)
+    }
+
+    /// Returns the raw device handle for calling libfprint functions which are not wrapped.
+    /// It stays owned by the wrapper.
+    pub fn as_raw(&self) -> *mut fprint_sys::fp_dev {
+        self.0
+    }
+
+    /// Consumes the wrapper and returns the raw device handle, the caller becomes responsible
+    /// for freeing it.
+    ///
+    /// The handle is only valid while libfprint is initialised, so the reference to libfprint
+    /// held by the device is leaked: `fp_exit()` is never called afterwards.
+    pub fn into_raw(mut self) -> *mut fprint_sys::fp_dev {
+        std::mem::forget(self.3.take());
+        std::mem::replace(&mut self.0, std::ptr::null_mut())
+    }
+
+    /// Closes the device, releasing the USB interface at a chosen point instead of when
+    /// the device is dropped. Fails if the device was not opened.
+    pub fn close(mut self) -> crate::Result<()> {
+        let device = std::mem::replace(&mut self.0, std::ptr::null_mut());
+        if device.is_null() {
+            return Err(crate::FPrintError::NullPtr(
+                crate::NullPtrContext::OpenDevice,
+            ));
+        }
+
+        let _lock = crate::context::lock();
+        unsafe { fprint_sys::fp_dev_close(device) };
+
+        Ok(())
+    }
+
+    /// Gets options the device was opened with.
+    pub fn get_options(&self) -> &DeviceOptions {
+        &self.2
     }
 
     /// Get the `Driver` for a fingerprint device.
     pub fn get_driver(&self) -> Driver {
-        let driver = unsafe { fprint_sys::fp_dev_get_driver(self.0) };
+        unsafe { Driver::new(fprint_sys::fp_dev_get_driver(self.0)) }
+    }
 
-        Driver::new(driver)
+    /// Gets metadata of the driver and the device cached when the device was opened.
+    pub fn get_driver_info(&self) -> &DriverInfo {
+        &self.1
     }
 
     /// Gets the number of enroll stages required to enroll a fingerprint with the device.
     pub fn get_nr_enroll_stages(&self) -> i32 {
-        unsafe { fprint_sys::fp_dev_get_nr_enroll_stages(self.0) as i32 }
+        self.1.enroll_stages
     }
 
     /// Alias for `get_nr_enroll_stages`
@@ -38,7 +135,7 @@ impl Device {
 
     /// Gets the devtype for a device.
     pub fn get_dev_type(&self) -> u32 {
-        unsafe { fprint_sys::fp_dev_get_devtype(self.0) }
+        self.1.devtype
     }
 
     /// Determines if a stored print is compatible with a certain device.
@@ -59,7 +156,7 @@ impl Device {
     pub fn supports_imaging(&self) -> bool {
         let result = unsafe { fprint_sys::fp_dev_supports_imaging(self.0) };
 
-        result == 0
+        result != 0
     }
 
     /// Determines if a device is capable of identification through `identify_finger` and similar.
@@ -67,7 +164,7 @@ impl Device {
     pub fn supports_identification(&self) -> bool {
         let result = unsafe { fprint_sys::fp_dev_supports_identification(self.0) };
 
-        result == 0
+        result != 0
     }
 
     /// Gets the expected width of images that will be captured from the device.
@@ -87,12 +184,6 @@ impl Device {
     pub fn load_data(&self, finger: Finger) -> crate::Result<PrintData> {
         let mut data: *mut fprint_sys::fp_print_data = std::ptr::null_mut();
         let result = unsafe { fprint_sys::fp_print_data_load(self.0, finger as u32, &mut data) };
-        if data.is_null() {
-            return Err(crate::FPrintError::NullPtr(
-                crate::NullPtrContext::LoadPrintData,
-            ));
-        }
-
         if result == -libc::ENOENT {
             return Err(crate::FPrintError::FingerprintNotFound(finger));
         }
@@ -101,13 +192,18 @@ impl Device {
             return Err(crate::FPrintError::Obscure(result));
         }
 
-        Ok(PrintData::with_data(data))
+        if data.is_null() {
+            return Err(crate::FPrintError::NullPtr(
+                crate::NullPtrContext::LoadPrintData,
+            ));
+        }
+
+        Ok(PrintData(data))
     }
 
     /// Removes a stored print from disk previously saved with `PrintData::save_to_disk()`.
     pub fn delete_data(&self, finger: Finger) -> crate::Result<()> {
         let result = unsafe { fprint_sys::fp_print_data_delete(self.0, finger as u32) };
-        assert_eq!({ result <= 0 }, true);
 
         if result == 0 {
             Ok(())
@@ -116,19 +212,49 @@ impl Device {
         }
     }
 
+    /// Loads a print saved with `PrintData::save_to_dir()` under `base`.
+    /// Returns `FPrintError::FingerprintNotFound` if there is no such print.
+    pub fn load_data_from(
+        &self,
+        base: impl AsRef<Path>,
+        finger: Finger,
+    ) -> crate::Result<PrintData> {
+        crate::print_store::read_print(&self.print_path(base.as_ref(), finger), finger)
+    }
+
+    /// Removes a print saved with `PrintData::save_to_dir()` under `base`. The file is
+    /// overwritten before unlinking.
+    pub fn delete_data_from(&self, base: impl AsRef<Path>, finger: Finger) -> crate::Result<()> {
+        crate::print_store::remove_print(&self.print_path(base.as_ref(), finger), finger)
+    }
+
+    fn print_path(&self, base: &Path, finger: Finger) -> PathBuf {
+        base.join(device_dir(self.1.driver_id, self.1.devtype))
+            .join(finger_file(finger))
+    }
+
+    /// Removes all prints of the device type saved with `PrintData::save_to_disk()`,
+    /// overwriting them before unlinking. Missing fingers are skipped. Returns number
+    /// of removed prints.
+    pub fn delete_all_data(&self) -> crate::Result<usize> {
+        crate::print_store::delete_saved_prints(self.1.driver_id, self.1.devtype)
+    }
+
     /// Captures a fp_img from a device. The returned image is the raw image provided
     /// by the device, you may wish to standardize it.
     ///
     /// If set, the `unconditional` flag indicates that the device should capture an image
     /// unconditionally, regardless of whether a finger is there or not. If unset, this function
     /// will block until a finger is detected on the sensor.
-    pub fn capture_image(&self, unconditional: bool) -> crate::Result<Image> {
+    pub fn capture_image(&self, unconditional: bool) -> crate::Result<RawImage> {
         let mut image: *mut fprint_sys::fp_img = std::ptr::null_mut();
-        let result =
-            unsafe { fprint_sys::fp_dev_img_capture(self.0, unconditional as i32, &mut image) };
+        let result = {
+            let _lock = crate::context::lock();
+            unsafe { fprint_sys::fp_dev_img_capture(self.0, unconditional as i32, &mut image) }
+        };
 
         match result {
-            0 => Ok(Image::with_image(image)),
+            0 => Ok(RawImage(Image(image))),
             _ if result == -libc::ENOTSUP => Err(crate::FPrintError::NotSupported(
                 crate::NotSupportContext::CapturingImage,
             )),
@@ -136,6 +262,39 @@ impl Device {
         }
     }
 
+    /// Same as `Device::capture_image()`, but the scan is stopped with `FPrintError::Cancelled`
+    /// when `cancel` is cancelled, even while waiting for a finger.
+    pub fn capture_image_cancellable(
+        &self,
+        unconditional: bool,
+        cancel: &CancelToken,
+    ) -> crate::Result<RawImage> {
+        let interrupt = Interrupt {
+            cancel: Some(cancel),
+            deadline: None,
+        };
+        scan::capture(self, unconditional, &interrupt)
+    }
+
+    /// Same as `Device::capture_image()`, but the scan is stopped with `FPrintError::TimedOut`
+    /// if no finger arrives within `timeout`.
+    pub fn capture_image_timeout(
+        &self,
+        unconditional: bool,
+        timeout: Duration,
+    ) -> crate::Result<RawImage> {
+        let interrupt = Interrupt {
+            cancel: None,
+            deadline: Some(Instant::now() + timeout),
+        };
+        scan::capture(self, unconditional, &interrupt)
+    }
+
+    /// Captures an image in the capture mode set by `DeviceOptions::unconditional_capture()`.
+    pub fn capture(&self) -> crate::Result<RawImage> {
+        self.capture_image(self.2.is_unconditional_capture())
+    }
+
     /// Performs an enroll stage. See [Enrolling](https://fprint.freedesktop.org/libfprint-stable/libfprint-Devices-operations.html#enrolling)
     /// for an explanation of enroll stages.
     ///
@@ -170,33 +329,323 @@ impl Device {
     /// during any other enrollment stages, hence it is actually legal to pass NULL as this
     /// argument for all but the final stage.
     ///
-    /// If the device is an imaging device, it can also return the image from the scan, even
-    /// when the enroll fails with a `Retry` or `Fail` code. It is legal to call this function
-    /// even on non-imaging devices, just don't expect them to provide images.
+    /// If the device is an imaging device, `Complete` and `Pass` results contain the image
+    /// of the scan. Images of failed and retried scans are freed right away, use
+    /// `Device::enroll()` to get them. It is legal to call this function even on non-imaging
+    /// devices, their results contain empty images.
     pub fn enroll_finger_image(&self) -> crate::Result<EnrollResult> {
-        let mut print = PrintData::new();
-        let mut image = Image::new();
-        let result = unsafe { fprint_sys::fp_enroll_finger_img(self.0, &mut print.0, &mut image.0) };
+        self.enroll_scan().0
+    }
 
-        if result < 0 {
-            Err(crate::FPrintError::UnexpectedAbort(result))
+    /// Performs an enroll stage like `Device::enroll_finger_image()`. Images of `Complete`
+    /// and `Pass` results are moved into the result and `None` is returned next to it, the
+    /// image of a failed or retried scan is returned next to the result instead of being freed.
+    fn enroll_scan(&self) -> (crate::Result<EnrollResult>, Option<RawImage>) {
+        let mut print: *mut fprint_sys::fp_print_data = std::ptr::null_mut();
+        let mut image: *mut fprint_sys::fp_img = std::ptr::null_mut();
+        let result = {
+            let _lock = crate::context::lock();
+            unsafe { fprint_sys::fp_enroll_finger_img(self.0, &mut print, &mut image) }
+        };
+
+        let (result, image) = if result < 0 {
+            unsafe { fprint_sys::fp_img_free(image) };
+            (Err(crate::FPrintError::UnexpectedAbort(result)), None)
         } else {
-            EnrollResult::try_from((result as u32, print, image))
+            let result = result as u32;
+            let image = if image.is_null() {
+                None
+            } else {
+                Some(RawImage(Image(image)))
+            };
+            match result {
+                fprint_sys::fp_enroll_result_FP_ENROLL_COMPLETE => {
+                    let image = image.unwrap_or_default();
+                    (Ok(EnrollResult::Complete(PrintData(print), image)), None)
+                }
+                fprint_sys::fp_enroll_result_FP_ENROLL_PASS => {
+                    (Ok(EnrollResult::Pass(image.unwrap_or_default())), None)
+                }
+                _ => (
+                    EnrollResult::try_from((result, PrintData::new(), RawImage::new())),
+                    image,
+                ),
+            }
+        };
+
+        #[cfg(feature = "metrics")]
+        if self.2.is_metrics_enabled() {
+            crate::metrics::record_enroll(self, &result);
         }
+
+        (result, image)
     }
 
     /// Performs a new scan and verify it against a previously enrolled print.
-    /// If the device is an imaging device, it can also return the image from the scan, even
-    /// when the verify fails with a RETRY code. It is legal to call this function even on
-    /// non-imaging devices, just don't expect them to provide images.
+    /// It is legal to call this function on both imaging and non-imaging devices. The image
+    /// of the scan is not requested from libfprint, so it is not kept in memory.
     pub fn verify_finger_image(&self, print: &mut PrintData) -> crate::Result<VerifyResult> {
-        let mut image: *mut fprint_sys::fp_img = std::ptr::null_mut();
-        let result = unsafe { fprint_sys::fp_verify_finger_img(self.0, print.0, &mut image) };
+        let result = {
+            let _lock = crate::context::lock();
+            unsafe { fprint_sys::fp_verify_finger_img(self.0, print.0, std::ptr::null_mut()) }
+        };
 
-        if result < 0 {
+        let result = if result < 0 {
             Err(crate::FPrintError::VerifyFailed(result))
         } else {
             VerifyResult::try_from(result as u32)
+        };
+
+        #[cfg(feature = "metrics")]
+        if self.2.is_metrics_enabled() {
+            crate::metrics::record_verify(self, &result);
+        }
+
+        result
+    }
+
+    /// Same as `Device::verify_finger_image()`, but the scan is stopped with
+    /// `FPrintError::Cancelled` when `cancel` is cancelled, even while waiting for a finger.
+    pub fn verify_finger_image_cancellable(
+        &self,
+        print: &PrintData,
+        cancel: &CancelToken,
+    ) -> crate::Result<VerifyResult> {
+        let interrupt = Interrupt {
+            cancel: Some(cancel),
+            deadline: None,
+        };
+        scan::verify(self, print, &interrupt)
+    }
+
+    /// Same as `Device::verify_finger_image()`, but the scan is stopped with
+    /// `FPrintError::TimedOut` if no finger arrives within `timeout`.
+    pub fn verify_finger_image_timeout(
+        &self,
+        print: &PrintData,
+        timeout: Duration,
+    ) -> crate::Result<VerifyResult> {
+        let interrupt = Interrupt {
+            cancel: None,
+            deadline: Some(Instant::now() + timeout),
+        };
+        scan::verify(self, print, &interrupt)
+    }
+
+    /// Runs `operation` on the device, calling `on_tick` with the elapsed time every `interval`
+    /// until it returns. libfprint blocks while waiting for a finger, so this lets UIs animate
+    /// a countdown or play prompts during capture, enroll or verify calls:
+    ///
+    /// ```ignore
+    /// let interval = Duration::from_millis(500);
+    /// let result = device.with_progress(interval, |elapsed| redraw(elapsed), |device| {
+    ///     device.verify_finger_image(&mut print)
+    /// })?;
+    /// ```
+    ///
+    /// `on_tick` is called from a helper thread while `operation` runs on the calling one,
+    /// so it must not use the device. Intervals shorter than 10 ms are raised to 10 ms, so
+    /// a zero interval does not turn the helper thread into a busy loop.
+    pub fn with_progress<T>(
+        &self,
+        interval: Duration,
+        mut on_tick: impl FnMut(Duration) + Send,
+        operation: impl FnOnce(&Self) -> T,
+    ) -> T {
+        let interval = interval.max(MIN_TICK_INTERVAL);
+        let started = Instant::now();
+        let (done, finished) = mpsc::channel::<()>();
+
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(interval) {
+                    on_tick(started.elapsed());
+                }
+            });
+
+            let result = operation(self);
+            drop(done);
+
+            result
+        })
+    }
+
+    /// Runs enrollment stages until the print is complete. Every scan is passed to `on_attempt`
+    /// with the number of its stage, so it can be reported to the user. The image of the scan
+    /// is available through `EnrollAttempt::get_image()`, images of scans other than `Pass`
+    /// and `Complete` are freed after `on_attempt` returns.
+    /// Returns the enrolled print together with statistics of the enrollment.
+    pub fn enroll(
+        &self,
+        mut on_attempt: impl FnMut(&EnrollAttempt),
+    ) -> crate::Result<(PrintData, EnrollReport)> {
+        self.enroll_until(|attempt| {
+            on_attempt(attempt);
+            ControlFlow::Continue(())
+        })
+    }
+
+    /// Runs the whole enrollment like `Device::enroll()` and returns the enrolled print.
+    /// `on_progress` is called with every scan and may abort the enrollment by returning
+    /// `ControlFlow::Break`, then `FPrintError::Cancelled` is returned:
+    ///
+    /// ```ignore
+    /// let print = device.enroll_with(|attempt| {
+    ///     println!("Scan {} of {}", attempt.stage, attempt.total_stages);
+    ///     ControlFlow::Continue(())
+    /// })?;
+    /// ```
+    ///
+    /// libfprint keeps an aborted enrollment in progress, the next enroll call continues it.
+    pub fn enroll_with(
+        &self,
+        on_progress: impl FnMut(&EnrollAttempt) -> ControlFlow<()>,
+    ) -> crate::Result<PrintData> {
+        self.enroll_until(on_progress).map(|(print, _)| print)
+    }
+
+    /// Runs enrollment stages like `Device::enroll()` until the print is complete or
+    /// `on_attempt` breaks. `EnrollAttempt::image` is set from `Device::enroll_scan()`, so it is
+    /// `None` for `Pass` and `Complete` results.
+    fn enroll_until(
+        &self,
+        mut on_attempt: impl FnMut(&EnrollAttempt) -> ControlFlow<()>,
+    ) -> crate::Result<(PrintData, EnrollReport)> {
+        let total_stages = self.get_nr_enroll_stages().max(0) as u32;
+        let started = Instant::now();
+        let mut report = EnrollReport {
+            stages: 0,
+            retries_per_stage: Vec::new(),
+            restarts: 0,
+            duration: Duration::default(),
+            quality: None,
+        };
+
+        loop {
+            let (result, image) = self.enroll_scan();
+            let attempt = EnrollAttempt {
+                stage: report.stages + 1,
+                total_stages,
+                result: result?,
+                image,
+            };
+            let flow = on_attempt(&attempt);
+
+            if flow.is_break() && !matches!(attempt.result, EnrollResult::Complete(_, _)) {
+                return Err(crate::FPrintError::Cancelled);
+            }
+
+            match attempt.result {
+                EnrollResult::Complete(print, image) => {
+                    report.stages += 1;
+                    report.duration = started.elapsed();
+                    report.quality = image.standardize().get_minutiae_count();
+
+                    return Ok((print, report));
+                }
+                EnrollResult::Pass(_) => report.stages += 1,
+                EnrollResult::Fail => {
+                    report.stages = 0;
+                    report.restarts += 1;
+                }
+                _ => {
+                    let stage = report.stages as usize;
+                    if report.retries_per_stage.len() <= stage {
+                        report.retries_per_stage.resize(stage + 1, 0);
+                    }
+                    report.retries_per_stage[stage] += 1;
+                }
+            }
+        }
+    }
+
+    /// Verifies a finger like `Device::verify_finger_image()`, repeating unsuccessful scans
+    /// as allowed by the retry policy of the device. When no more attempts are allowed,
+    /// the last `Retry` result is the decision.
+    pub fn verify(&self, print: &mut PrintData) -> crate::Result<VerifyOutcome> {
+        self.verify_with(|| self.verify_finger_image(print))
+    }
+
+    /// Verifies a finger against several prints of it, e.g. enrolled in different sessions,
+    /// repeating unsuccessful scans as allowed by the retry policy of the device. The decision
+    /// is `Match` if any of the prints matches. If the device supports identification,
+    /// a single scan is matched against all prints, otherwise the finger is verified against
+    /// the prints one by one until one of them matches.
+    pub fn verify_any(&self, prints: &mut [PrintData]) -> crate::Result<VerifyOutcome> {
+        if prints.len() > 1 && self.supports_identification() {
+            let mut raw: Vec<_> = prints
+                .iter()
+                .map(PrintData::as_raw)
+                .chain(std::iter::once(std::ptr::null_mut()))
+                .collect();
+
+            return self.verify_with(|| {
+                let result = self.identify_prints(raw.as_mut_ptr(), prints.len())?;
+
+                Ok(match result {
+                    IdentifyResult::Matched(_) => VerifyResult::Match,
+                    IdentifyResult::Error(reason) => reason,
+                })
+            });
+        }
+
+        let mut outcome = VerifyOutcome {
+            decision: VerifyResult::NoMatch,
+            attempts: 0,
+            retries_by_reason: HashMap::new(),
+            duration: Duration::default(),
+        };
+        for print in prints {
+            let next = self.verify(print)?;
+            outcome.decision = next.decision;
+            outcome.attempts += next.attempts;
+            outcome.duration += next.duration;
+            for (reason, count) in next.retries_by_reason {
+                *outcome.retries_by_reason.entry(reason).or_insert(0) += count;
+            }
+
+            if outcome.decision != VerifyResult::NoMatch {
+                break;
+            }
+        }
+
+        Ok(outcome)
+    }
+
+    /// Repeats `scan` as allowed by the retry policy of the device.
+    fn verify_with(
+        &self,
+        mut scan: impl FnMut() -> crate::Result<VerifyResult>,
+    ) -> crate::Result<VerifyOutcome> {
+        let policy = self.2.get_retry_policy();
+        let started = Instant::now();
+        let mut outcome = VerifyOutcome {
+            decision: VerifyResult::Retry,
+            attempts: 0,
+            retries_by_reason: HashMap::new(),
+            duration: Duration::default(),
+        };
+
+        loop {
+            let result = scan()?;
+            outcome.attempts += 1;
+
+            let done = match result {
+                VerifyResult::Match | VerifyResult::NoMatch => true,
+                reason => {
+                    *outcome.retries_by_reason.entry(reason).or_insert(0) += 1;
+                    !policy.allows(outcome.attempts)
+                }
+            };
+
+            if done {
+                outcome.decision = result;
+                outcome.duration = started.elapsed();
+
+                return Ok(outcome);
+            }
+
+            std::thread::sleep(policy.delay);
         }
     }
 
@@ -215,46 +664,218 @@ impl Device {
     /// as soon as it finds a matching print.
     ///
     /// Not all devices support identification. -ENOTSUP will be returned when this is the case.
-    pub fn identify_finger_image(&self, gallery: &[Vec<u8>]) -> crate::Result<IdentifyResult> {
-        let mut image: *mut fprint_sys::fp_img = std::ptr::null_mut();
-        let mut offset = 0;
+    ///
+    /// Every print of the gallery must be loadable by libfprint, otherwise an error is returned.
+    pub fn identify_finger_image<T: AsRef<[u8]>>(
+        &self,
+        gallery: &[T],
+    ) -> crate::Result<IdentifyResult> {
+        self.identify_finger_image_with(gallery, &mut IdentifyScratch::default())
+    }
 
-        let mut gallery = gallery
-            .iter()
-            .map(PrintData::from_bytes_raw)
-            .filter(Result::is_ok)
-            .map(Result::unwrap)
-            .collect::<Vec<_>>();
-        gallery.push(std::ptr::null_mut());
-        let gallery = gallery.as_ptr() as *mut *mut fprint_sys::fp_print_data;
+    /// Same as `Device::identify_finger_image()`, but prints parsed from the gallery are kept
+    /// in `scratch`, so identification loops over the same gallery parse it only once.
+    pub fn identify_finger_image_with<T: AsRef<[u8]>>(
+        &self,
+        gallery: &[T],
+        scratch: &mut IdentifyScratch,
+    ) -> crate::Result<IdentifyResult> {
+        scratch.load(gallery)?;
 
-        let result =
-            unsafe { fprint_sys::fp_identify_finger_img(self.0, gallery, &mut offset, &mut image) };
+        self.identify_prints(scratch.raw.as_mut_ptr(), scratch.prints.len())
+    }
 
-        if result == -libc::ENOTSUP {
+    /// Performs a new scan and identifies the scanned finger against the prints of `gallery`
+    /// enrolled with the driver and the devtype of the device. `IdentifyResult::Matched`
+    /// contains the matched print with its key and index.
+    ///
+    /// When the gallery has no compatible prints, no scan is performed and
+    /// `VerifyResult::NoMatch` is returned.
+    pub fn identify<'a, K>(
+        &self,
+        gallery: &'a crate::Gallery<K>,
+    ) -> crate::Result<IdentifyResult<crate::GalleryMatch<'a, K>>> {
+        let bucket = match gallery.get_bucket(self.1.driver_id, self.1.devtype) {
+            Some(bucket) => bucket,
+            None => return Ok(IdentifyResult::Error(VerifyResult::NoMatch)),
+        };
+        let indices = bucket.indices();
+        let result = self.identify_prints(bucket.as_raw(), indices.len())?;
+
+        Ok(match result {
+            IdentifyResult::Matched(offset) => match gallery.get_match(indices[offset]) {
+                Some(matched) => IdentifyResult::Matched(matched),
+                None => IdentifyResult::Error(VerifyResult::NoMatch),
+            },
+            IdentifyResult::Error(reason) => IdentifyResult::Error(reason),
+        })
+    }
+
+    /// Identifies against the NULL-terminated array of `len` prints. Offsets outside
+    /// of the array are reported as `NoMatch`.
+    pub(crate) fn identify_prints(
+        &self,
+        prints: *mut *mut fprint_sys::fp_print_data,
+        len: usize,
+    ) -> crate::Result<IdentifyResult> {
+        let mut image: *mut fprint_sys::fp_img = std::ptr::null_mut();
+        let mut offset = 0;
+        let result = {
+            let _lock = crate::context::lock();
+            unsafe { fprint_sys::fp_identify_finger_img(self.0, prints, &mut offset, &mut image) }
+        };
+        unsafe { fprint_sys::fp_img_free(image) };
+
+        let result = if result == -libc::ENOTSUP {
             Err(crate::FPrintError::NotSupported(
                 crate::NotSupportContext::Identify,
             ))
         } else if result < 0 {
             Err(crate::FPrintError::IdentifyFailed(result))
         } else {
-            let result = match VerifyResult::try_from(result as u32)? {
-                VerifyResult::Match => IdentifyResult::Matched(offset),
+            VerifyResult::try_from(result as u32).map(|result| match result {
+                VerifyResult::Match if offset < len => IdentifyResult::Matched(offset),
+                VerifyResult::Match => IdentifyResult::Error(VerifyResult::NoMatch),
                 n => IdentifyResult::Error(n),
-            };
+            })
+        };
 
-            Ok(result)
+        #[cfg(feature = "metrics")]
+        if self.2.is_metrics_enabled() {
+            crate::metrics::record_identify(self, &result);
         }
+
+        result
+    }
+}
+
+/// Prints parsed by `Device::identify_finger_image_with()`, kept with the data they were
+/// parsed from. The gallery is parsed again only when its data changes.
+#[derive(Debug, Default)]
+pub struct IdentifyScratch {
+    data: Vec<TemplateBytes>,
+    prints: Vec<PrintData>,
+    /// Pointers of `prints` followed by NULL.
+    raw: Vec<*mut fprint_sys::fp_print_data>,
+}
+
+// Pointers of the array point to the prints owned by the scratch, so it can be moved along
+// with them. It is not `Sync`, libfprint gets the array for writing.
+unsafe impl Send for IdentifyScratch {}
+
+impl IdentifyScratch {
+    /// Parses the gallery unless it holds the same data as the cached one.
+    fn load<T: AsRef<[u8]>>(&mut self, gallery: &[T]) -> crate::Result<()> {
+        let cached = self.data.len() == gallery.len()
+            && self
+                .data
+                .iter()
+                .zip(gallery)
+                .all(|(cached, data)| **cached == *data.as_ref());
+        if cached && !self.raw.is_empty() {
+            return Ok(());
+        }
+
+        self.data.clear();
+        self.prints.clear();
+        self.raw.clear();
+
+        let prints = gallery
+            .iter()
+            .map(PrintData::from_bytes)
+            .collect::<crate::Result<Vec<_>>>()?;
+        self.data = gallery
+            .iter()
+            .map(|data| TemplateBytes::from(data.as_ref().to_vec()))
+            .collect();
+        self.raw = prints
+            .iter()
+            .map(PrintData::as_raw)
+            .chain(std::iter::once(std::ptr::null_mut()))
+            .collect();
+        self.prints = prints;
+
+        Ok(())
     }
 }
 
 impl Drop for Device {
     fn drop(&mut self) {
-        unsafe { fprint_sys::fp_dev_close(self.0) }
+        if !self.0.is_null() {
+            let _lock = crate::context::lock();
+            unsafe { fprint_sys::fp_dev_close(self.0) }
+        }
+    }
+}
+
+/// Reference-counted `Device` for sharing one opened device between several owners,
+/// e.g. GUI widgets. The device is closed when the last handle is dropped. The handle
+/// is `Rc`-backed and stays on one thread, see `SyncDevice` for sharing between threads.
+#[derive(Debug, Clone)]
+pub struct DeviceHandle(Rc<Device>);
+
+impl DeviceHandle {
+    pub fn new(device: Device) -> Self {
+        DeviceHandle(Rc::new(device))
+    }
+
+    /// Returns the device back if this is the only handle.
+    pub fn try_unwrap(self) -> Result<Device, Self> {
+        Rc::try_unwrap(self.0).map_err(DeviceHandle)
+    }
+}
+
+impl From<Device> for DeviceHandle {
+    fn from(device: Device) -> Self {
+        Self::new(device)
+    }
+}
+
+impl Deref for DeviceHandle {
+    type Target = Device;
+
+    fn deref(&self) -> &Device {
+        &self.0
+    }
+}
+
+/// `Device` shared between threads. Operations are serialized by a mutex:
+///
+/// ```ignore
+/// let device = SyncDevice::new(device);
+/// let worker = device.clone();
+/// thread::spawn(move || worker.lock().capture_image(false));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SyncDevice(Arc<Mutex<Device>>);
+
+impl SyncDevice {
+    pub fn new(device: Device) -> Self {
+        SyncDevice(Arc::new(Mutex::new(device)))
+    }
+
+    /// Locks the device for running operations, blocking while another thread uses it.
+    pub fn lock(&self) -> MutexGuard<'_, Device> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the device back if this is the only handle.
+    pub fn try_unwrap(self) -> Result<Device, Self> {
+        Arc::try_unwrap(self.0)
+            .map(|device| device.into_inner().unwrap_or_else(PoisonError::into_inner))
+            .map_err(SyncDevice)
+    }
+}
+
+impl From<Device> for SyncDevice {
+    fn from(device: Device) -> Self {
+        Self::new(device)
     }
 }
 
 #[derive(Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum SizeVariant {
     NonImagingDevice,
     Variable,
@@ -272,6 +893,8 @@ impl From<c_int> for SizeVariant {
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum CaptureResult {
     Complete = 0,
     Fail = 1,
@@ -289,88 +912,301 @@ impl TryFrom<u32> for CaptureResult {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Image owned by the wrapper, it is freed when dropped.
+#[derive(Debug, PartialEq, Eq)]
 pub struct Image(*mut fprint_sys::fp_img);
 
+// The image is owned by the wrapper and only used through it, libfprint calls processing
+// images are serialized by `context::lock()`.
+unsafe impl Send for Image {}
+
 impl Image {
     pub fn new() -> Self {
-        let inner = std::ptr::null_mut();
-
-        Image::with_image(inner)
+        Image(std::ptr::null_mut())
     }
 
-    pub fn with_image(image: *mut fprint_sys::fp_img) -> Self {
+    /// Wraps an image allocated by libfprint.
+    ///
+    /// # Safety
+    ///
+    /// `image` must be NULL or a valid image not owned by anything else. It is freed when
+    /// the `Image` is dropped.
+    pub unsafe fn with_image(image: *mut fprint_sys::fp_img) -> Self {
         Image(image)
     }
 
-    /// Gets the pixel height of an image.
+    /// Same as `Image::with_image()`.
+    ///
+    /// # Safety
+    ///
+    /// See `Image::with_image()`.
+    pub unsafe fn from_raw(raw: *mut fprint_sys::fp_img) -> Self {
+        Self::with_image(raw)
+    }
+
+    /// Returns the raw image for calling libfprint functions which are not wrapped.
+    /// It stays owned by the wrapper.
+    pub fn as_raw(&self) -> *mut fprint_sys::fp_img {
+        self.0
+    }
+
+    /// Consumes the wrapper and returns the raw image, the caller becomes responsible
+    /// for freeing it.
+    pub fn into_raw(mut self) -> *mut fprint_sys::fp_img {
+        std::mem::replace(&mut self.0, std::ptr::null_mut())
+    }
+
+    /// Gets the pixel height of an image, 0 for an empty image.
     pub fn get_height(&self) -> i32 {
+        if self.0.is_null() {
+            return 0;
+        }
+
         unsafe { fprint_sys::fp_img_get_height(self.0) }
     }
 
-    /// Gets the pixel width of an image.
+    /// Gets the pixel width of an image, 0 for an empty image.
     pub fn get_width(&self) -> i32 {
+        if self.0.is_null() {
+            return 0;
+        }
+
         unsafe { fprint_sys::fp_img_get_width(self.0) }
     }
 
     /// Gets the greyscale data for an image. This data must not be modified or freed,
     /// and must not be used after dropping `Image`.
-    /// Returns a pointer to libfprint's internal data for the image
+    /// Returns a pointer to libfprint's internal data for the image, NULL for an empty image.
+    /// See `Image::as_bytes()` for safe access.
     pub fn get_data(&self) -> *const c_uchar {
+        if self.0.is_null() {
+            return std::ptr::null();
+        }
+
         unsafe { fprint_sys::fp_img_get_data(self.0) }
     }
 
+    /// Greyscale data of the image, `width * height` bytes row by row. Empty if there is
+    /// no image or its reported size is invalid.
+    pub fn as_bytes(&self) -> &[u8] {
+        let data = self.get_data();
+        let len = usize::try_from(self.get_width())
+            .ok()
+            .zip(usize::try_from(self.get_height()).ok())
+            .and_then(|(width, height)| width.checked_mul(height));
+
+        match len {
+            Some(len) if !data.is_null() => unsafe { std::slice::from_raw_parts(data, len) },
+            _ => &[],
+        }
+    }
+
+    /// Copies greyscale data of the image into `buf`, reusing its allocation.
+    /// Returns width and height of the image.
+    pub fn copy_data_into(&self, buf: &mut Vec<u8>) -> (i32, i32) {
+        buf.clear();
+        buf.extend_from_slice(self.as_bytes());
+
+        (self.get_width(), self.get_height())
+    }
+
+    /// Same as `Image::copy_data_into()`, with contrast of the copy stretched by `normalize()`.
+    pub fn copy_normalized_into(&self, buf: &mut Vec<u8>) -> (i32, i32) {
+        let size = self.copy_data_into(buf);
+        crate::normalize(buf);
+
+        size
+    }
+
+    /// Renders the image for logs and bug reports: contrast is stretched and the image is
+    /// reduced by `anonymize()`, so it shows contact area and scan quality without being
+    /// usable as a biometric.
+    pub fn anonymized(&self) -> crate::AnonymizedImage {
+        let mut pixels = Vec::new();
+        let (width, height) = self.copy_normalized_into(&mut pixels);
+        crate::anonymize(&mut pixels, width.max(0) as usize);
+
+        crate::AnonymizedImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Assesses quality of the scan with `assess_quality()`, e.g. to reject poor captures
+    /// before enrolling them. Raw images of some devices have inverted colors, standardize
+    /// them first to get correct dry and wet indicators.
+    pub fn quality(&self) -> crate::QualityReport {
+        crate::assess_quality(self.as_bytes(), self.get_width().max(0) as usize)
+    }
+
     /// A quick convenience function to save an image to a file in [PGM format](http://netpbm.sourceforge.net/doc/pgm.html).
+    /// The file is created or truncated, its directory must exist.
     pub fn save_to_file(&self, path: impl AsRef<Path>) -> crate::Result<()> {
+        if self.0.is_null() {
+            return Err(crate::FPrintError::NullPtr(
+                crate::NullPtrContext::SaveImage,
+            ));
+        }
+
+        let path = CString::new(path.as_ref().as_os_str().as_bytes())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Path contains a NUL byte"))?;
+
+        // libfprint does not modify the path, the pointer is mutable only in its signature.
+        let result =
+            unsafe { fprint_sys::fp_img_save_to_file(self.0, path.as_ptr() as *mut c_char) };
+        match result {
+            0 => Ok(()),
+            // Failed `fopen()` and short writes are reported as negative errno.
+            _ if result < 0 => Err(io::Error::from_raw_os_error(-result).into()),
+            _ => Err(crate::FPrintError::SaveImage(result)),
+        }
+    }
+
+    /// Writes the image in PGM format to the `writer`, e.g. to send a scan over the network
+    /// or to store it in a database without a temporary file.
+    pub fn write_pgm(&self, writer: impl io::Write) -> crate::Result<()> {
+        let data = self.as_bytes();
+        let (width, height) = match data {
+            [] => (0, 0),
+            _ => (self.get_width(), self.get_height()),
+        };
+
+        crate::image_ops::write_pgm(writer, width, height, data)
+    }
+
+    /// Same as `Image::write_pgm()`, but the image is written into a new buffer.
+    pub fn to_pgm_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_pgm(&mut bytes)
+            .expect("writing into Vec does not fail");
+
+        bytes
+    }
+
+    /// Same as `Image::save_to_file()`, but missing parent directories are created first.
+    pub fn save_to_file_creating_dirs(&self, path: impl AsRef<Path>) -> crate::Result<()> {
         let path = path.as_ref();
-        if !path.exists() {
-            return Err(crate::FPrintError::PathNotExists);
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
         }
 
-        let path = path.as_os_str().as_bytes().as_ptr();
+        self.save_to_file(path)
+    }
+}
 
-        let result = unsafe { fprint_sys::fp_img_save_to_file(self.0, path as *mut c_char) };
-        if result == 0 {
-            Ok(())
-        } else {
-            Err(crate::FPrintError::SavePrint(result))
+impl Default for Image {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Image {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe { fprint_sys::fp_img_free(self.0) }
         }
     }
+}
 
-    /// [Standardizes](https://fprint.freedesktop.org/libfprint-stable/libfprint-Image-operations.html#img_std)
-    /// an image by normalizing its orientation, colors, etc. It is safe to call this multiple
-    /// times on an image, `libfprint` keeps track of the work it needs to do to make an image
-    /// standard and will not perform these operations more than once for a given image.
-    pub fn standardize(&self) {
-        unsafe { fprint_sys::fp_img_standardize(self.0) };
+/// Raw image provided by the device, see `StandardImage` and `BinarizedImage`
+/// for the processed ones.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RawImage(Image);
+
+impl RawImage {
+    pub fn new() -> Self {
+        RawImage(Image::new())
     }
 
-    /// Get a binarized form of a standardized scanned image. This is where the fingerprint image
-    /// has been "enhanced" and is a set of pure black ridges on a pure white background.
-    /// Internally, image processing happens on top of the binarized image.
+    /// Wraps an image captured by libfprint.
     ///
-    /// The image must have been standardized otherwise this function will fail (this version of
-    /// `binarized` standardized image before binarizing).
+    /// # Safety
     ///
-    /// It is safe to binarize an image and free the original while continuing to use
-    /// the binarized version.
+    /// See `Image::with_image()`, the image must not be standardized or binarized.
+    pub unsafe fn from_raw(raw: *mut fprint_sys::fp_img) -> Self {
+        RawImage(Image::from_raw(raw))
+    }
+
+    /// [Standardizes](https://fprint.freedesktop.org/libfprint-stable/libfprint-Image-operations.html#img_std)
+    /// an image by normalizing its orientation, colors, etc. Only standardized images can be
+    /// binarized and have their minutiae detected.
+    pub fn standardize(self) -> StandardImage {
+        if !self.0.as_raw().is_null() {
+            let _lock = crate::context::lock();
+            unsafe { fprint_sys::fp_img_standardize(self.0.as_raw()) };
+        }
+
+        StandardImage(self.0)
+    }
+
+    pub fn into_image(self) -> Image {
+        self.0
+    }
+}
+
+impl std::ops::Deref for RawImage {
+    type Target = Image;
+
+    fn deref(&self) -> &Image {
+        &self.0
+    }
+}
+
+/// Image without device-specific orientation and colors, created by `RawImage::standardize()`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct StandardImage(Image);
+
+impl StandardImage {
+    /// Number of minutiae detected in the image, which can serve as a quality score of the scan.
+    /// Returns `None` if there is no image or detection failed.
+    pub fn get_minutiae_count(&self) -> Option<u32> {
+        self.minutiae().ok().map(|minutiae| minutiae.len() as u32)
+    }
+
+    /// Get a binarized form of the image. This is where the fingerprint image has been
+    /// "enhanced" and is a set of pure black ridges on a pure white background.
+    /// Internally, image processing happens on top of the binarized image.
     ///
-    /// You cannot binarize an image twice.
-    pub fn binarize(&self) -> crate::Result<Self> {
-        self.standardize();
-        let result = unsafe { fprint_sys::fp_img_binarize(self.0) };
+    /// The binarized image is independent of the original one and can outlive it.
+    pub fn binarize(&self) -> crate::Result<BinarizedImage> {
+        let result = if self.0.as_raw().is_null() {
+            std::ptr::null_mut()
+        } else {
+            let _lock = crate::context::lock();
+            unsafe { fprint_sys::fp_img_binarize(self.0.as_raw()) }
+        };
 
         if result.is_null() {
             Err(crate::FPrintError::NullPtr(crate::NullPtrContext::Binarize))
         } else {
-            Ok(Image::with_image(result))
+            Ok(BinarizedImage(Image(result)))
         }
     }
+
+    pub fn into_image(self) -> Image {
+        self.0
+    }
 }
 
-impl Default for Image {
-    fn default() -> Self {
-        Self::new()
+impl std::ops::Deref for StandardImage {
+    type Target = Image;
+
+    fn deref(&self) -> &Image {
+        &self.0
+    }
+}
+
+/// Black ridges on a white background, created by `StandardImage::binarize()`. It cannot
+/// be binarized again and has no minutiae.
+#[derive(Debug, PartialEq, Eq)]
+pub struct BinarizedImage(Image);
+
+impl std::ops::Deref for BinarizedImage {
+    type Target = Image;
+
+    fn deref(&self) -> &Image {
+        &self.0
     }
 }
 
@@ -381,13 +1217,14 @@ impl Default for Image {
 /// For more info on the semantics of interpreting these result codes and tracking
 /// enrollment process, see [Enrolling](https://fprint.freedesktop.org/libfprint-stable/libfprint-Devices-operations.html#enrolling)
 #[derive(Debug, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum EnrollResult {
-    Complete(PrintData, Image),
+    Complete(PrintData, RawImage),
     /// Enrollment failed due to incomprehensible data; this may occur when
     /// the user scans a different finger on each enroll stage.
     Fail,
     /// Enroll stage passed; more stages are need to complete the process.
-    Pass(Image),
+    Pass(RawImage),
     /// The enrollment scan did not succeed due to poor scan quality or
     /// other general user scanning problem.
     Retry,
@@ -419,10 +1256,10 @@ impl Display for EnrollResult {
     }
 }
 
-impl TryFrom<(u32, PrintData, Image)> for EnrollResult {
+impl TryFrom<(u32, PrintData, RawImage)> for EnrollResult {
     type Error = crate::FPrintError;
 
-    fn try_from((raw_value, data, image): (u32, PrintData, Image)) -> Result<Self, Self::Error> {
+    fn try_from((raw_value, data, image): (u32, PrintData, RawImage)) -> Result<Self, Self::Error> {
         match raw_value {
             1 => Ok(EnrollResult::Complete(data, image)),
             2 => Ok(EnrollResult::Fail),
@@ -437,7 +1274,9 @@ impl TryFrom<(u32, PrintData, Image)> for EnrollResult {
 }
 
 #[repr(u32)]
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum VerifyResult {
     /// The scan completed successfully, but the newly scanned fingerprint
     /// does not match the fingerprint being verified against.
@@ -490,10 +1329,70 @@ impl TryFrom<u32> for VerifyResult {
     }
 }
 
+/// Statistics of the enrollment performed by `Device::enroll()`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnrollReport {
+    /// Number of passed stages, including the final one.
+    pub stages: u32,
+    /// Number of retried scans by the stage they happened on.
+    pub retries_per_stage: Vec<u32>,
+    /// Number of times the enrollment failed and started over.
+    pub restarts: u32,
+    /// Time spent on the enrollment.
+    pub duration: Duration,
+    /// Number of minutiae in the last scan, if the device provides images.
+    pub quality: Option<u32>,
+}
+
+/// Enroll scan reported by `Device::enroll()`.
+#[derive(Debug)]
+pub struct EnrollAttempt {
+    /// Number of the stage the scan was made for, starting from 1.
+    pub stage: u32,
+    pub total_stages: u32,
+    pub result: EnrollResult,
+    /// Image of a retried or failed scan if the device is an imaging device. It is `None`
+    /// for `Pass` and `Complete` results, which hold the image themselves, use
+    /// `EnrollAttempt::get_image()` to get the image for any result.
+    pub image: Option<RawImage>,
+}
+
+impl EnrollAttempt {
+    /// Image of the scan if the device is an imaging device.
+    pub fn get_image(&self) -> Option<&RawImage> {
+        match &self.result {
+            EnrollResult::Complete(_, image) | EnrollResult::Pass(image) => {
+                Some(image).filter(|image| !image.as_raw().is_null())
+            }
+            _ => self.image.as_ref(),
+        }
+    }
+}
+
+/// Result of `Device::verify()` with statistics of the attempts.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VerifyOutcome {
+    /// Result of the last scan: `Match`, `NoMatch`, or a `Retry` result when no more
+    /// attempts were allowed.
+    pub decision: VerifyResult,
+    /// Number of scans performed.
+    pub attempts: u32,
+    /// Number of unsuccessful scans by their result.
+    pub retries_by_reason: HashMap<VerifyResult, u32>,
+    /// Time spent on all attempts.
+    pub duration: Duration,
+}
+
+/// Result of identification. `Matched` contains the offset of the matched print for
+/// `Device::identify_finger_image()` and the `GalleryMatch` for `Device::identify()`.
 #[repr(u32)]
 #[derive(Debug, Eq, PartialEq)]
-pub enum IdentifyResult {
-    Matched(usize),
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum IdentifyResult<T = usize> {
+    Matched(T),
     Error(VerifyResult),
 }
 
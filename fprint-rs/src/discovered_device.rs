@@ -1,5 +1,8 @@
-use crate::{Device, Driver, PrintData};
-use std::mem::{size_of, size_of_val};
+use crate::{context::Context, Device, DeviceOptions, Driver, PrintData};
+use std::{ops::Index, sync::Arc, thread, time::Duration};
+
+/// Interval between attempts of `DiscoveredDev::open_retry()`.
+pub const OPEN_RETRY_INTERVAL: Duration = Duration::from_millis(500);
 
 /// These functions allow you to scan the system for supported fingerprint scanning hardware.
 /// This is your starting point when integrating libfprint into your software.
@@ -8,18 +11,41 @@ use std::mem::{size_of, size_of_val};
 /// with `open()`. Note that discovered devices may no longer be available at the time when you
 /// want to open them, for example the user may have unplugged the device.
 #[derive(Debug, Clone)]
-pub struct DiscoveredDev(*mut fprint_sys::fp_dscv_dev);
+pub struct DiscoveredDev(*mut fprint_sys::fp_dscv_dev, Option<Arc<Context>>);
 
 impl DiscoveredDev {
-    pub fn new(inner: *mut fprint_sys::fp_dscv_dev) -> Self {
-        DiscoveredDev(inner)
+    /// Wraps a discovered device returned by libfprint.
+    ///
+    /// # Safety
+    ///
+    /// `inner` must be a valid discovered device, which outlives the `DiscoveredDev`.
+    pub unsafe fn new(inner: *mut fprint_sys::fp_dscv_dev) -> Self {
+        DiscoveredDev(inner, None)
+    }
+
+    /// Same as `DiscoveredDev::new()`.
+    ///
+    /// # Safety
+    ///
+    /// See `DiscoveredDev::new()`.
+    pub unsafe fn from_raw(raw: *mut fprint_sys::fp_dscv_dev) -> Self {
+        Self::new(raw)
+    }
+
+    /// Returns the raw discovered device for calling libfprint functions which are not wrapped.
+    pub fn as_raw(&self) -> *mut fprint_sys::fp_dscv_dev {
+        self.0
+    }
+
+    /// Consumes the wrapper and returns the raw discovered device. It is owned by the
+    /// `DiscoveredDevices` list.
+    pub fn into_raw(self) -> *mut fprint_sys::fp_dscv_dev {
+        self.0
     }
 
     /// Gets the `Driver` for a discovered device.
     pub fn get_driver(&self) -> Driver {
-        let driver = unsafe { fprint_sys::fp_dscv_dev_get_driver(self.0) };
-
-        Driver::new(driver)
+        unsafe { Driver::new(fprint_sys::fp_dscv_dev_get_driver(self.0)) }
     }
 
     /// Gets the devtype for a discovered device.
@@ -29,7 +55,7 @@ impl DiscoveredDev {
 
     /// Determines if a specific `PrintData` stored print appears to be compatible
     /// with a discovered device.
-    pub fn supports_print_data(&self, data: &mut PrintData) -> bool {
+    pub fn supports_print_data(&self, data: &PrintData) -> bool {
         let result = unsafe { fprint_sys::fp_dscv_dev_supports_print_data(self.0, data.0) };
 
         result == 1
@@ -37,64 +63,174 @@ impl DiscoveredDev {
 
     /// Opens and initialises a device. This is the function you call in order to convert
     /// a discovered device into an actual device handle that you can perform operations with.
-    pub fn open(&self) -> Device {
-        let device = unsafe { fprint_sys::fp_dev_open(self.0) };
+    ///
+    /// Returns `FPrintError::OpenFailed` if the device can not be opened, e.g. it was unplugged
+    /// after discovery.
+    pub fn open(&self) -> crate::Result<Device> {
+        self.open_with(DeviceOptions::default())
+    }
+
+    /// Same as `DiscoveredDev::open()`, with the policy of the device set by `options`.
+    pub fn open_with(&self, options: DeviceOptions) -> crate::Result<Device> {
+        let device = {
+            let _lock = crate::context::lock();
+            unsafe { fprint_sys::fp_dev_open(self.0) }
+        };
+        if device.is_null() {
+            return Err(crate::FPrintError::OpenFailed);
+        }
+
+        let device = unsafe { Device::with_options(device, options) };
+        Ok(device.in_context(self.1.clone()))
+    }
 
-        Device::new(device)
+    /// Same as `DiscoveredDev::open()`, but makes up to `attempts` attempts to open the device
+    /// with `OPEN_RETRY_INTERVAL` between them. Useful right after the device was plugged in,
+    /// when it may still be busy.
+    pub fn open_retry(&self, attempts: u32) -> crate::Result<Device> {
+        let mut attempt = 1;
+
+        loop {
+            match self.open() {
+                Err(crate::FPrintError::OpenFailed) if attempt < attempts => {
+                    attempt += 1;
+                    thread::sleep(OPEN_RETRY_INTERVAL);
+                }
+                result => return result,
+            }
+        }
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct DiscoveredDevices {
-    inner: *mut *mut fprint_sys::fp_dscv_dev,
-    current_item_number: isize,
+/// Filter of discovered devices, see `FPrint::discover_with()`. Implemented by closures
+/// taking `&DiscoveredDev` and by the typed filters `DriverName`, `DevType`
+/// and `CompatibleWith`.
+pub trait DeviceFilter {
+    fn matches(&self, device: &DiscoveredDev) -> bool;
 }
 
-impl Iterator for DiscoveredDevices {
-    type Item = DiscoveredDev;
+impl<F: Fn(&DiscoveredDev) -> bool> DeviceFilter for F {
+    fn matches(&self, device: &DiscoveredDev) -> bool {
+        self(device)
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let item = unsafe { self.inner.offset(self.current_item_number) };
-        let device: *mut fprint_sys::fp_dscv_dev = unsafe { item.read() };
-        if device.is_null() {
-            None
-        } else {
-            Some(DiscoveredDev::new(device))
-        }
+/// Matches devices handled by the driver with the name, e.g. "upekts".
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DriverName<'a>(pub &'a str);
+
+impl DeviceFilter for DriverName<'_> {
+    fn matches(&self, device: &DiscoveredDev) -> bool {
+        device.get_driver().get_name() == self.0
+    }
+}
+
+/// Matches devices with the devtype.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DevType(pub u32);
+
+impl DeviceFilter for DevType {
+    fn matches(&self, device: &DiscoveredDev) -> bool {
+        device.get_devtype() == self.0
+    }
+}
+
+/// Matches devices the print can be verified on.
+#[derive(Debug, Copy, Clone)]
+pub struct CompatibleWith<'a>(pub &'a PrintData);
+
+impl DeviceFilter for CompatibleWith<'_> {
+    fn matches(&self, device: &DiscoveredDev) -> bool {
+        device.supports_print_data(self.0)
     }
 }
 
+/// List of discovered devices. The NULL-terminated array returned by libfprint is walked
+/// once, when the list is created.
+#[derive(Debug)]
+pub struct DiscoveredDevices {
+    inner: *mut *mut fprint_sys::fp_dscv_dev,
+    devices: Vec<DiscoveredDev>,
+    _context: Option<Arc<Context>>,
+}
+
+// The list and its entries are plain data allocated by libfprint, they are moved together.
+unsafe impl Send for DiscoveredDevices {}
+
 impl DiscoveredDevices {
     pub fn new() -> Self {
-        let devices = std::ptr::null_mut();
-
-        Self::with_devices(devices)
+        unsafe { Self::with_devices(std::ptr::null_mut()) }
     }
 
-    pub fn with_devices(devices: *mut *mut fprint_sys::fp_dscv_dev) -> Self {
+    /// Wraps a NULL-terminated list of discovered devices returned by libfprint.
+    ///
+    /// # Safety
+    ///
+    /// `devices` must be NULL or a list returned by `fp_discover_devs()`, not owned by
+    /// anything else. It is freed when the `DiscoveredDevices` is dropped.
+    pub unsafe fn with_devices(devices: *mut *mut fprint_sys::fp_dscv_dev) -> Self {
+        let mut list = Vec::new();
+        if !devices.is_null() {
+            while !devices.add(list.len()).read().is_null() {
+                list.push(DiscoveredDev(devices.add(list.len()).read(), None));
+            }
+        }
+
         DiscoveredDevices {
             inner: devices,
-            current_item_number: 0,
+            devices: list,
+            _context: None,
         }
     }
 
-    pub fn get(&self, index: isize) -> Option<DiscoveredDev> {
-        if index as usize >= self.count() {
-            return None;
+    /// Keeps libfprint initialised while the list, its devices or devices opened from them
+    /// are alive.
+    pub(crate) fn in_context(mut self, context: &Arc<Context>) -> Self {
+        for device in &mut self.devices {
+            device.1 = Some(context.clone());
         }
+        self._context = Some(context.clone());
 
-        let item = unsafe { self.inner.offset(index) };
-        let device: *mut fprint_sys::fp_dscv_dev = unsafe { item.read() };
+        self
+    }
 
-        if device.is_null() {
-            None
-        } else {
-            Some(DiscoveredDev::new(device))
-        }
+    pub fn get(&self, index: usize) -> Option<&DiscoveredDev> {
+        self.devices.get(index)
     }
 
-    pub fn count(&self) -> usize {
-        size_of_val(&self.inner) / size_of::<*mut fprint_sys::fp_dscv_dev>()
+    /// Number of discovered devices.
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, DiscoveredDev> {
+        self.devices.iter()
+    }
+
+    /// Keeps only devices matched by the `filter`.
+    pub fn retain(&mut self, filter: impl DeviceFilter) {
+        self.devices.retain(|device| filter.matches(device));
+    }
+}
+
+impl Index<usize> for DiscoveredDevices {
+    type Output = DiscoveredDev;
+
+    fn index(&self, index: usize) -> &DiscoveredDev {
+        &self.devices[index]
+    }
+}
+
+impl<'a> IntoIterator for &'a DiscoveredDevices {
+    type Item = &'a DiscoveredDev;
+    type IntoIter = std::slice::Iter<'a, DiscoveredDev>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
     }
 }
 
@@ -9,10 +9,34 @@ use std::ffi::CStr;
 pub struct Driver(*mut fprint_sys::fp_driver);
 
 impl Driver {
-    pub fn new(driver: *mut fprint_sys::fp_driver) -> Self {
+    /// Wraps a driver handle returned by libfprint.
+    ///
+    /// # Safety
+    ///
+    /// `driver` must be a valid driver handle, which outlives the `Driver`.
+    pub unsafe fn new(driver: *mut fprint_sys::fp_driver) -> Self {
         Driver(driver)
     }
 
+    /// Same as `Driver::new()`.
+    ///
+    /// # Safety
+    ///
+    /// See `Driver::new()`.
+    pub unsafe fn from_raw(raw: *mut fprint_sys::fp_driver) -> Self {
+        Self::new(raw)
+    }
+
+    /// Returns the raw driver handle for calling libfprint functions which are not wrapped.
+    pub fn as_raw(&self) -> *mut fprint_sys::fp_driver {
+        self.0
+    }
+
+    /// Consumes the wrapper and returns the raw driver handle. It is owned by libfprint.
+    pub fn into_raw(self) -> *mut fprint_sys::fp_driver {
+        self.0
+    }
+
     /// Retrieves the name of the driver. For example: "upekts"
     pub fn get_name(&self) -> String {
         unsafe {
@@ -44,8 +68,86 @@ impl Driver {
     }
 }
 
+/// Immutable metadata of the driver and the device, read once when the device is opened,
+/// so it can be accessed without FFI calls.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DriverInfo {
+    /// Name of the driver. For example: "upekts"
+    pub name: String,
+    /// Descriptive name of the driver. For example: "UPEK TouchStrip"
+    pub full_name: String,
+    pub driver_id: u16,
+    /// Scan type, `None` if it is not known.
+    pub scan_type: Option<ScanType>,
+    /// Devtype of the device.
+    pub devtype: u32,
+    /// Number of enroll stages required to enroll a fingerprint with the device.
+    pub enroll_stages: i32,
+}
+
+/// Drivers of libfprint 0.x by their id, used for prints whose reader is not attached.
+const KNOWN_DRIVERS: &[(u16, &str, &str)] = &[
+    (1, "upekts", "UPEK TouchStrip"),
+    (2, "uru4000", "Digital Persona U.are.U 4000/4000B/4500"),
+    (3, "aes4000", "AuthenTec AES4000"),
+    (4, "aes2501", "AuthenTec AES2501"),
+    (5, "upektc", "UPEK TouchChip/Eikon Touch 300"),
+    (6, "aes1610", "AuthenTec AES1610"),
+    (8, "vcom5s", "Veridicom 5thSense"),
+    (9, "upeksonly", "UPEK TouchStrip Sensor-Only"),
+    (10, "vfs101", "Validity VFS101"),
+    (11, "vfs301", "Validity VFS301"),
+    (12, "aes2550", "AuthenTec AES2550/AES2810"),
+    (14, "aes1660", "AuthenTec AES1660"),
+    (15, "aes2660", "AuthenTec AES2660"),
+    (16, "aes3500", "AuthenTec AES3500"),
+    (17, "upektc_img", "Upek TouchChip Fingerprint Coprocessor"),
+    (18, "etes603", "EgisTec ES603"),
+    (19, "vfs5011", "Validity VFS5011"),
+    (20, "vfs0050", "Validity VFS0050"),
+    (21, "elan", "ElanTech Fingerprint Sensor"),
+];
+
+impl DriverInfo {
+    /// Looks up the driver by its id in the list of libfprint drivers, for prints whose
+    /// reader is not attached. Scan type and number of enroll stages are not known without
+    /// the device and are left empty. Returns `None` for unknown drivers.
+    pub fn from_driver_id(driver_id: u16, devtype: u32) -> Option<Self> {
+        let &(_, name, full_name) = KNOWN_DRIVERS.iter().find(|(id, _, _)| *id == driver_id)?;
+
+        Some(DriverInfo {
+            name: name.to_string(),
+            full_name: full_name.to_string(),
+            driver_id,
+            scan_type: None,
+            devtype,
+            enroll_stages: 0,
+        })
+    }
+
+    pub(crate) fn read(device: *mut fprint_sys::fp_dev) -> Self {
+        if device.is_null() {
+            return Self::default();
+        }
+
+        let driver = Driver(unsafe { fprint_sys::fp_dev_get_driver(device) });
+
+        DriverInfo {
+            name: driver.get_name(),
+            full_name: driver.get_full_name(),
+            driver_id: driver.get_driver_id(),
+            scan_type: driver.get_scan_type().ok(),
+            devtype: unsafe { fprint_sys::fp_dev_get_devtype(device) },
+            enroll_stages: unsafe { fprint_sys::fp_dev_get_nr_enroll_stages(device) as i32 },
+        }
+    }
+}
+
 /// Devices require either swiping or pressing the finger on the device. This is useful for front-ends.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum ScanType {
     /// the reader has a surface area that covers the whole finger
     Press,
@@ -4,6 +4,7 @@ use failure::Fail;
 
 // TODO: refactor it!
 #[derive(Debug, Fail)]
+#[non_exhaustive]
 pub enum FPrintError {
     #[fail(display = "Fail on init. FPrint error code: {}", _0)]
     InitError(i32),
@@ -11,6 +12,8 @@ pub enum FPrintError {
     NullPtr(NullPtrContext),
     #[fail(display = "Requested fingerprint not found (finger: {})", _0)]
     FingerprintNotFound(Finger),
+    #[fail(display = "Requested fingerprint not found (label: {})", _0)]
+    LabelNotFound(String),
     #[fail(display = "Obscure error conditions (e.g. corruption): {}", _0)]
     Obscure(i32),
     #[fail(display = "Failed removing fingerprint for finger `{}`", _0)]
@@ -35,6 +38,8 @@ pub enum FPrintError {
     IdentifyFailed(i32),
     #[fail(display = "Failed to save print data. Error code: {}", _0)]
     SavePrint(i32),
+    #[fail(display = "Failed to save image. Error code: {}", _0)]
+    SaveImage(i32),
     #[fail(display = "Can not convert stored print into unified representation")]
     ConvertationFailed,
     #[fail(display = "Can not convert from `{}`", _0)]
@@ -43,9 +48,94 @@ pub enum FPrintError {
     PathNotExists,
     #[fail(display = "Error not specified. Please, write issue")]
     NeedError,
+    #[fail(display = "I/O error: {}", _0)]
+    Io(#[fail(cause)] std::io::Error),
+    #[fail(display = "Unknown system user with uid {}", _0)]
+    UnknownUser(u32),
+    #[cfg(feature = "mqtt")]
+    #[fail(display = "Failed to publish event: {}", _0)]
+    PublishFailed(String),
+    #[cfg(feature = "attendance")]
+    #[fail(display = "Database error: {}", _0)]
+    Database(#[fail(cause)] rusqlite::Error),
+    #[cfg(feature = "image")]
+    #[fail(display = "Image error: {}", _0)]
+    Image(#[fail(cause)] image::ImageError),
+    #[cfg(feature = "websocket")]
+    #[fail(display = "WebSocket error: {}", _0)]
+    WebSocket(#[fail(cause)] tungstenite::Error),
+    #[cfg(feature = "websocket")]
+    #[fail(display = "WebSocket handshake failed: {}", _0)]
+    WebSocketHandshake(String),
+    #[cfg(feature = "serde_json")]
+    #[fail(display = "Serialization error: {}", _0)]
+    Serialization(#[fail(cause)] serde_json::Error),
+    #[fail(display = "Audit log chain is broken at record {}", _0)]
+    AuditChainBroken(u64),
+    #[fail(display = "Proof token is malformed or has invalid signature")]
+    InvalidProofToken,
+    #[fail(display = "Proof token is expired")]
+    ProofTokenExpired,
+    #[fail(display = "Key can not be used for signing")]
+    InvalidKey,
+    #[fail(display = "No supported device was attached in time")]
+    NoDevice,
+    #[fail(display = "Failed to open device, it may have been unplugged")]
+    OpenFailed,
+    #[fail(display = "libfprint is still used by devices or other handles")]
+    ContextInUse,
+    #[fail(display = "Invalid localization resource: {}", _0)]
+    Localization(String),
+    #[fail(display = "Operation was cancelled")]
+    Cancelled,
+    #[fail(display = "Operation timed out")]
+    TimedOut,
+    #[fail(display = "Event source already exists")]
+    EventSourceInUse,
+    #[fail(display = "Invalid template: {}", _0)]
+    InvalidTemplate(&'static str),
+    #[fail(display = "Print data is corrupted: {}", reason)]
+    CorruptTemplate { reason: &'static str },
+    #[fail(display = "Invalid gallery file: {}", _0)]
+    InvalidGalleryFile(&'static str),
+}
+
+impl From<std::io::Error> for FPrintError {
+    fn from(e: std::io::Error) -> Self {
+        FPrintError::Io(e)
+    }
+}
+
+#[cfg(feature = "attendance")]
+impl From<rusqlite::Error> for FPrintError {
+    fn from(e: rusqlite::Error) -> Self {
+        FPrintError::Database(e)
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<image::ImageError> for FPrintError {
+    fn from(e: image::ImageError) -> Self {
+        FPrintError::Image(e)
+    }
+}
+
+#[cfg(feature = "websocket")]
+impl From<tungstenite::Error> for FPrintError {
+    fn from(e: tungstenite::Error) -> Self {
+        FPrintError::WebSocket(e)
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl From<serde_json::Error> for FPrintError {
+    fn from(e: serde_json::Error) -> Self {
+        FPrintError::Serialization(e)
+    }
 }
 
 #[derive(Debug, Fail)]
+#[non_exhaustive]
 pub enum NullPtrContext {
     #[fail(display = "on discovering devices")]
     Discovering,
@@ -55,9 +145,20 @@ pub enum NullPtrContext {
     Binarize,
     #[fail(display = "on create discovering device")]
     CreateDiscoveringDevice,
+    #[fail(display = "on opening device")]
+    OpenDevice,
+    #[fail(display = "on adding print to gallery")]
+    GalleryPrint,
+    #[fail(display = "on detecting minutiae")]
+    Minutiae,
+    #[fail(display = "on saving image")]
+    SaveImage,
+    #[fail(display = "on accessing print without data")]
+    EmptyPrint,
 }
 
 #[derive(Debug, Fail)]
+#[non_exhaustive]
 pub enum NotSupportContext {
     #[fail(
         display = "either the unconditional flag was set but the device does not support this, or that the device does not support imaging"
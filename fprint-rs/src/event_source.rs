@@ -0,0 +1,169 @@
+use crate::FPrint;
+use std::{
+    os::{
+        raw::{c_int, c_short},
+        unix::io::RawFd,
+    },
+    slice,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, MutexGuard, PoisonError,
+    },
+    time::Duration,
+};
+
+/// Whether an `EventSource` exists, libfprint has a single set of pollfd notifiers.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+/// Changes reported by libfprint since the last `EventSource::take_changes()`.
+static CHANGES: Mutex<Vec<PollFdChange>> = Mutex::new(Vec::new());
+
+/// File descriptor libfprint waits on.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PollFd {
+    pub fd: RawFd,
+    /// Events to poll for, as in `poll(2)` (`POLLIN`, `POLLOUT`).
+    pub events: i16,
+}
+
+/// Change of the set of file descriptors libfprint waits on.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PollFdChange {
+    Added(PollFd),
+    Removed(RawFd),
+}
+
+/// Source of libfprint events for applications driving libfprint from their own event loop
+/// (epoll, mio, calloop...) instead of the blocking calls of the crate:
+///
+/// ```ignore
+/// let source = EventSource::new(&fprint)?;
+/// for pollfd in source.get_pollfds()? {
+///     poller.add(pollfd.fd, pollfd.events)?;
+/// }
+///
+/// loop {
+///     poller.wait(source.get_next_timeout())?;
+///     source.dispatch()?;
+///     for change in source.take_changes() {
+///         match change {
+///             PollFdChange::Added(pollfd) => poller.add(pollfd.fd, pollfd.events)?,
+///             PollFdChange::Removed(fd) => poller.remove(fd)?,
+///         }
+///     }
+/// }
+/// ```
+///
+/// Only one event source may exist at a time.
+#[derive(Debug)]
+pub struct EventSource {
+    _fprint: FPrint,
+}
+
+impl EventSource {
+    /// Starts tracking file descriptors libfprint waits on.
+    pub fn new(fprint: &FPrint) -> crate::Result<Self> {
+        if ACTIVE.swap(true, Ordering::SeqCst) {
+            return Err(crate::FPrintError::EventSourceInUse);
+        }
+
+        changes().clear();
+        let _lock = crate::context::lock();
+        unsafe { fprint_sys::fp_set_pollfd_notifiers(Some(pollfd_added), Some(pollfd_removed)) };
+
+        Ok(EventSource {
+            _fprint: fprint.clone(),
+        })
+    }
+
+    /// File descriptors libfprint currently waits on.
+    pub fn get_pollfds(&self) -> crate::Result<Vec<PollFd>> {
+        let mut pollfds: *mut fprint_sys::fp_pollfd = std::ptr::null_mut();
+        let len = {
+            let _lock = crate::context::lock();
+            unsafe { fprint_sys::fp_get_pollfds(&mut pollfds) }
+        };
+        if len < 0 {
+            return Err(crate::FPrintError::Other(len as i32));
+        }
+        if pollfds.is_null() {
+            return Ok(Vec::new());
+        }
+
+        let result = unsafe { slice::from_raw_parts(pollfds, len as usize) }
+            .iter()
+            .map(|pollfd| PollFd {
+                fd: pollfd.fd,
+                events: pollfd.events,
+            })
+            .collect();
+        unsafe { libc::free(pollfds as *mut libc::c_void) };
+
+        Ok(result)
+    }
+
+    /// Time until libfprint has to handle events even if no file descriptor becomes ready,
+    /// `None` if it has no pending timeouts.
+    pub fn get_next_timeout(&self) -> Option<Duration> {
+        let mut timeout = fprint_sys::timeval {
+            tv_sec: 0,
+            tv_usec: 0,
+        };
+
+        let _lock = crate::context::lock();
+        if unsafe { fprint_sys::fp_get_next_timeout(&mut timeout) } == 0 {
+            None
+        } else {
+            Some(
+                Duration::from_secs(timeout.tv_sec.max(0) as u64)
+                    + Duration::from_micros(timeout.tv_usec.max(0) as u64),
+            )
+        }
+    }
+
+    /// Handles pending events without blocking. Call it when a file descriptor is ready or
+    /// the timeout has expired.
+    pub fn dispatch(&self) -> crate::Result<()> {
+        self.handle_events_timeout(Duration::from_secs(0))
+    }
+
+    /// Waits for events for at most `timeout` and handles them.
+    pub fn handle_events_timeout(&self, timeout: Duration) -> crate::Result<()> {
+        let mut timeout = fprint_sys::timeval {
+            tv_sec: timeout.as_secs() as _,
+            tv_usec: timeout.subsec_micros() as _,
+        };
+
+        let _lock = crate::context::lock();
+        match unsafe { fprint_sys::fp_handle_events_timeout(&mut timeout) } {
+            result if result < 0 => Err(crate::FPrintError::Other(result)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns changes of the file descriptors since the previous call.
+    pub fn take_changes(&self) -> Vec<PollFdChange> {
+        std::mem::take(&mut *changes())
+    }
+}
+
+impl Drop for EventSource {
+    fn drop(&mut self) {
+        let _lock = crate::context::lock();
+        unsafe { fprint_sys::fp_set_pollfd_notifiers(None, None) };
+        changes().clear();
+        ACTIVE.store(false, Ordering::SeqCst);
+    }
+}
+
+fn changes() -> MutexGuard<'static, Vec<PollFdChange>> {
+    CHANGES.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+unsafe extern "C" fn pollfd_added(fd: c_int, events: c_short) {
+    changes().push(PollFdChange::Added(PollFd { fd, events }));
+}
+
+unsafe extern "C" fn pollfd_removed(fd: c_int) {
+    changes().push(PollFdChange::Removed(fd));
+}
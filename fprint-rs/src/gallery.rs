@@ -0,0 +1,200 @@
+use crate::PrintData;
+use std::{collections::HashMap, ptr};
+
+/// Prints to identify fingers against, see `Device::identify()`. Every print is stored
+/// with a key, e.g. id of its owner, which is returned when the print matches:
+///
+/// ```ignore
+/// let mut gallery = Gallery::new();
+/// gallery.insert("alice", alice_print)?;
+/// gallery.insert("bob", bob_print)?;
+///
+/// if let IdentifyResult::Matched(matched) = device.identify(&gallery)? {
+///     println!("Hello, {}!", matched.key);
+/// }
+/// ```
+///
+/// Prints are indexed by driver id and devtype, so identification only goes through prints
+/// compatible with the device. The gallery owns its prints and keeps NULL-terminated arrays
+/// of them passed to libfprint, so identification does not load or allocate anything.
+#[derive(Debug)]
+pub struct Gallery<K = ()> {
+    prints: Vec<PrintData>,
+    keys: Vec<K>,
+    buckets: HashMap<(u16, u32), Bucket>,
+}
+
+/// Prints of the gallery enrolled with the same driver and devtype.
+#[derive(Debug)]
+pub(crate) struct Bucket {
+    /// Indices of the prints in the gallery.
+    indices: Vec<usize>,
+    /// Pointers of the prints followed by NULL.
+    raw: Vec<*mut fprint_sys::fp_print_data>,
+}
+
+// Pointers of the arrays point to the prints owned by the gallery. libfprint only reads
+// the arrays, and calls into it are serialized by the global lock.
+unsafe impl<K: Send> Send for Gallery<K> {}
+unsafe impl<K: Sync> Sync for Gallery<K> {}
+
+/// Print of the gallery matched by `Device::identify()`.
+#[derive(Debug)]
+pub struct GalleryMatch<'a, K> {
+    /// Index of the print in the gallery.
+    pub index: usize,
+    pub key: &'a K,
+    pub print: &'a PrintData,
+}
+
+impl<K> Gallery<K> {
+    pub fn new() -> Self {
+        Gallery {
+            prints: Vec::new(),
+            keys: Vec::new(),
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Adds the print with its key and returns its index. Prints without data are rejected.
+    pub fn insert(&mut self, key: K, print: PrintData) -> crate::Result<usize> {
+        if print.as_raw().is_null() {
+            return Err(crate::FPrintError::NullPtr(
+                crate::NullPtrContext::GalleryPrint,
+            ));
+        }
+
+        self.index(self.prints.len(), &print);
+        self.prints.push(print);
+        self.keys.push(key);
+
+        Ok(self.prints.len() - 1)
+    }
+
+    /// Loads the serialized print and adds it, see `Gallery::insert()`. The bytes can be kept
+    /// in a `SecureBuffer` of the `secure-memory` feature. Loaded prints are copied into memory
+    /// allocated by libfprint, which is not locked.
+    pub fn insert_bytes(&mut self, key: K, bytes: impl AsRef<[u8]>) -> crate::Result<usize> {
+        self.insert(key, PrintData::from_bytes(bytes)?)
+    }
+
+    /// Removes the print at `index`, shifting following prints to the left.
+    pub fn remove(&mut self, index: usize) -> Option<(K, PrintData)> {
+        if index >= self.prints.len() {
+            return None;
+        }
+
+        let removed = (self.keys.remove(index), self.prints.remove(index));
+        self.reindex();
+
+        Some(removed)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&PrintData> {
+        self.prints.get(index)
+    }
+
+    pub fn get_key(&self, index: usize) -> Option<&K> {
+        self.keys.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.prints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prints.is_empty()
+    }
+
+    /// Keeps only prints for which `filter` returns `true`, e.g. prints compatible with
+    /// the device:
+    ///
+    /// ```ignore
+    /// gallery.retain(|_, print| print.is_compatible_with_device(&discovered));
+    /// ```
+    ///
+    /// Indices of the following prints are shifted.
+    pub fn retain(&mut self, mut filter: impl FnMut(&K, &PrintData) -> bool) {
+        let (keys, prints) = self
+            .keys
+            .drain(..)
+            .zip(self.prints.drain(..))
+            .filter(|(key, print)| filter(key, print))
+            .unzip();
+        self.keys = keys;
+        self.prints = prints;
+        self.reindex();
+    }
+
+    /// Iterates over keys and prints in the order of their indices.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &PrintData)> {
+        self.keys.iter().zip(&self.prints)
+    }
+
+    /// Resolves the offset reported by libfprint.
+    pub(crate) fn get_match(&self, index: usize) -> Option<GalleryMatch<'_, K>> {
+        Some(GalleryMatch {
+            index,
+            key: self.keys.get(index)?,
+            print: self.prints.get(index)?,
+        })
+    }
+
+    /// Prints enrolled with the driver and the devtype.
+    pub(crate) fn get_bucket(&self, driver_id: u16, devtype: u32) -> Option<&Bucket> {
+        self.buckets.get(&(driver_id, devtype))
+    }
+
+    /// Adds the print at `index` to the bucket of its driver and devtype.
+    fn index(&mut self, index: usize, print: &PrintData) {
+        let bucket = self
+            .buckets
+            .entry((print.get_driver_id(), print.get_devtype()))
+            .or_insert_with(|| Bucket {
+                indices: Vec::new(),
+                raw: vec![ptr::null_mut()],
+            });
+        bucket.indices.push(index);
+        bucket.raw.insert(bucket.indices.len() - 1, print.as_raw());
+    }
+
+    /// Rebuilds buckets after indices of the prints are changed.
+    fn reindex(&mut self) {
+        let prints = std::mem::take(&mut self.prints);
+        self.buckets.clear();
+        for (index, print) in prints.iter().enumerate() {
+            self.index(index, print);
+        }
+        self.prints = prints;
+    }
+}
+
+impl Bucket {
+    /// Indices of the prints in the gallery, in the order of the array.
+    pub(crate) fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// NULL-terminated array of the prints for libfprint. libfprint only reads it.
+    pub(crate) fn as_raw(&self) -> *mut *mut fprint_sys::fp_print_data {
+        self.raw.as_ptr() as *mut _
+    }
+}
+
+impl Gallery<()> {
+    /// Adds the print without a key and returns its index.
+    pub fn push(&mut self, print: PrintData) -> crate::Result<usize> {
+        self.insert((), print)
+    }
+
+    /// Loads the serialized print and adds it without a key.
+    pub fn push_bytes(&mut self, bytes: impl AsRef<[u8]>) -> crate::Result<usize> {
+        self.insert_bytes((), bytes)
+    }
+}
+
+impl<K> Default for Gallery<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{EnrollResult, VerifyResult};
+use std::fmt::{self, Display, Formatter};
+
+/// Prompt to show the user during enrollment and verification. `Display` gives the English
+/// text, localized texts are provided by the `i18n` feature.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum UserGuidance {
+    /// Ask the user to put the finger on the sensor.
+    ScanFinger,
+    EnrollComplete,
+    EnrollStagePassed,
+    /// Enrollment failed and starts over.
+    EnrollFailed,
+    Match,
+    NoMatch,
+    /// Scan failed due to poor quality, it should be repeated.
+    ScanAgain,
+    SwipeTooShort,
+    CenterFinger,
+    RemoveFinger,
+}
+
+impl UserGuidance {
+    /// Id of the message in the localization catalog.
+    pub fn id(self) -> &'static str {
+        match self {
+            UserGuidance::ScanFinger => "scan-finger",
+            UserGuidance::EnrollComplete => "enroll-complete",
+            UserGuidance::EnrollStagePassed => "enroll-stage-passed",
+            UserGuidance::EnrollFailed => "enroll-failed",
+            UserGuidance::Match => "match",
+            UserGuidance::NoMatch => "no-match",
+            UserGuidance::ScanAgain => "scan-again",
+            UserGuidance::SwipeTooShort => "swipe-too-short",
+            UserGuidance::CenterFinger => "center-finger",
+            UserGuidance::RemoveFinger => "remove-finger",
+        }
+    }
+}
+
+impl Display for UserGuidance {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let text = match self {
+            UserGuidance::ScanFinger => "Scan your finger now.",
+            UserGuidance::EnrollComplete => "Enroll complete!",
+            UserGuidance::EnrollStagePassed => "Enroll stage passed. Scan your finger again.",
+            UserGuidance::EnrollFailed => "Enroll failed, starting over. Scan your finger again.",
+            UserGuidance::Match => "MATCH!",
+            UserGuidance::NoMatch => "NO MATCH!",
+            UserGuidance::ScanAgain => "Didn't quite catch that. Please try again.",
+            UserGuidance::SwipeTooShort => "Your swipe was too short, please try again.",
+            UserGuidance::CenterFinger => {
+                "Didn't catch that, please center your finger on the sensor and try again."
+            }
+            UserGuidance::RemoveFinger => {
+                "Scan failed, please remove your finger and then try again."
+            }
+        };
+
+        write!(f, "{}", text)
+    }
+}
+
+impl From<&EnrollResult> for UserGuidance {
+    fn from(result: &EnrollResult) -> Self {
+        match result {
+            EnrollResult::Complete(_, _) => UserGuidance::EnrollComplete,
+            EnrollResult::Pass(_) => UserGuidance::EnrollStagePassed,
+            EnrollResult::Fail => UserGuidance::EnrollFailed,
+            EnrollResult::Retry => UserGuidance::ScanAgain,
+            EnrollResult::RetryTooShort => UserGuidance::SwipeTooShort,
+            EnrollResult::RetryCenterFinger => UserGuidance::CenterFinger,
+            EnrollResult::RetryRemoveFinger => UserGuidance::RemoveFinger,
+        }
+    }
+}
+
+impl From<VerifyResult> for UserGuidance {
+    fn from(result: VerifyResult) -> Self {
+        match result {
+            VerifyResult::Match => UserGuidance::Match,
+            VerifyResult::NoMatch => UserGuidance::NoMatch,
+            VerifyResult::Retry => UserGuidance::ScanAgain,
+            VerifyResult::RetryTooShort => UserGuidance::SwipeTooShort,
+            VerifyResult::RetryCenterFinger => UserGuidance::CenterFinger,
+            VerifyResult::RetryRemoveFinger => UserGuidance::RemoveFinger,
+        }
+    }
+}
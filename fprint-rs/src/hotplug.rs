@@ -0,0 +1,160 @@
+//! Notifications about fingerprint readers being plugged in or unplugged.
+//!
+//! `HotplugMonitor` listens to udev events of USB devices. When one comes, supported devices
+//! are discovered again and compared with the previous discovery, so only fingerprint readers
+//! are reported. libfprint is not thread-safe, so the monitor is driven by the thread using
+//! the `FPrint`:
+//!
+//! ```ignore
+//! let mut monitor = HotplugMonitor::new(&fprint)?;
+//! for event in &mut monitor {
+//!     match event? {
+//!         HotplugEvent::DeviceAdded(device) => println!("{} plugged in", device.driver),
+//!         HotplugEvent::DeviceRemoved(device) => println!("{} unplugged", device.driver),
+//!     }
+//! }
+//! ```
+
+use crate::FPrint;
+use std::{collections::VecDeque, io, os::unix::io::AsRawFd, time::Duration};
+use udev::{EventType, MonitorBuilder, MonitorSocket};
+
+/// Fingerprint reader reported by `HotplugMonitor`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct HotplugDevice {
+    /// Name of the driver of the device.
+    pub driver: String,
+    pub driver_id: u16,
+    /// Devtype of the device.
+    pub devtype: u32,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum HotplugEvent {
+    DeviceAdded(HotplugDevice),
+    DeviceRemoved(HotplugDevice),
+}
+
+/// Watcher of fingerprint readers being plugged in or unplugged.
+pub struct HotplugMonitor {
+    fprint: FPrint,
+    socket: MonitorSocket,
+    devices: Vec<HotplugDevice>,
+    pending: VecDeque<HotplugEvent>,
+}
+
+impl HotplugMonitor {
+    /// Starts listening to udev events. Devices present at this moment are not reported.
+    pub fn new(fprint: &FPrint) -> crate::Result<Self> {
+        let socket = MonitorBuilder::new()?
+            .match_subsystem_devtype("usb", "usb_device")?
+            .listen()?;
+
+        let mut monitor = HotplugMonitor {
+            fprint: fprint.clone(),
+            socket,
+            devices: Vec::new(),
+            pending: VecDeque::new(),
+        };
+        monitor.devices = monitor.discover();
+
+        Ok(monitor)
+    }
+
+    /// Currently plugged fingerprint readers.
+    pub fn get_devices(&self) -> &[HotplugDevice] {
+        &self.devices
+    }
+
+    /// Waits for the next event for at most `timeout`, forever if it is `None`.
+    /// Returns `None` on timeout.
+    pub fn wait(&mut self, timeout: Option<Duration>) -> crate::Result<Option<HotplugEvent>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(Some(event));
+            }
+
+            if !self.poll(timeout)? {
+                return Ok(None);
+            }
+
+            let mut changed = false;
+            for event in self.socket.by_ref() {
+                changed |= matches!(event.event_type(), EventType::Add | EventType::Remove);
+            }
+            if changed {
+                self.rediscover();
+            }
+        }
+    }
+
+    /// Waits until the udev socket is readable. Returns `false` on timeout.
+    fn poll(&self, timeout: Option<Duration>) -> crate::Result<bool> {
+        let mut fd = libc::pollfd {
+            fd: self.socket.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout.map_or(-1, |timeout| {
+            timeout.as_millis().min(i32::MAX as u128) as i32
+        });
+
+        loop {
+            match unsafe { libc::poll(&mut fd, 1, timeout) } {
+                -1 => {
+                    let error = io::Error::last_os_error();
+                    if error.kind() != io::ErrorKind::Interrupted {
+                        return Err(error.into());
+                    }
+                }
+                0 => return Ok(false),
+                _ => return Ok(true),
+            }
+        }
+    }
+
+    /// Discovers devices and queues events for the difference with the previous discovery.
+    fn rediscover(&mut self) {
+        let devices = self.discover();
+
+        for device in &self.devices {
+            if !devices.contains(device) {
+                self.pending
+                    .push_back(HotplugEvent::DeviceRemoved(device.clone()));
+            }
+        }
+        for device in &devices {
+            if !self.devices.contains(device) {
+                self.pending
+                    .push_back(HotplugEvent::DeviceAdded(device.clone()));
+            }
+        }
+
+        self.devices = devices;
+    }
+
+    fn discover(&self) -> Vec<HotplugDevice> {
+        self.fprint
+            .discover()
+            .iter()
+            .map(|device| {
+                let driver = device.get_driver();
+                HotplugDevice {
+                    driver: driver.get_name(),
+                    driver_id: driver.get_driver_id(),
+                    devtype: device.get_devtype(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Iterator for HotplugMonitor {
+    type Item = crate::Result<HotplugEvent>;
+
+    /// Blocks until the next event.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.wait(None).transpose()
+    }
+}
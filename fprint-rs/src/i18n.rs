@@ -0,0 +1,75 @@
+//! Localization of user prompts with [Fluent](https://projectfluent.org).
+//!
+//! English, German and Russian catalogs are bundled, messages are keyed by
+//! `UserGuidance::id()`. Other locales or own wording can be plugged in with
+//! `Localizer::with_resource()`:
+//!
+//! ```ignore
+//! let localizer = Localizer::new("fr".parse()?).with_resource(include_str!("fr.ftl"))?;
+//! println!("{}", localizer.localize(UserGuidance::CenterFinger));
+//! ```
+
+use crate::UserGuidance;
+use fluent::{concurrent::FluentBundle, FluentResource};
+pub use unic_langid::LanguageIdentifier;
+
+/// Bundled catalogs by language. The first one is the fallback for missing messages.
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../i18n/en.ftl")),
+    ("de", include_str!("../i18n/de.ftl")),
+    ("ru", include_str!("../i18n/ru.ftl")),
+];
+
+/// Translator of `UserGuidance` into the language of the locale.
+pub struct Localizer {
+    bundle: FluentBundle<FluentResource>,
+}
+
+impl Localizer {
+    /// Creates localizer with the bundled catalog for the language of `locale`. Messages
+    /// missing in it are taken from the English catalog.
+    pub fn new(locale: LanguageIdentifier) -> Self {
+        let language = locale.language;
+        let mut localizer = Localizer {
+            bundle: FluentBundle::new_concurrent(vec![locale]),
+        };
+        localizer.bundle.set_use_isolating(false);
+
+        for (index, (catalog, source)) in CATALOGS.iter().enumerate() {
+            if index == 0 || language.as_str() == *catalog {
+                let resource = FluentResource::try_new(source.to_string())
+                    .unwrap_or_else(|(resource, _)| resource);
+                localizer.bundle.add_resource_overriding(resource);
+            }
+        }
+
+        localizer
+    }
+
+    /// Adds messages from Fluent `source`, replacing already known messages with the same id.
+    pub fn with_resource(mut self, source: &str) -> crate::Result<Self> {
+        let resource = FluentResource::try_new(source.to_string())
+            .map_err(|(_, errors)| crate::FPrintError::Localization(format!("{:?}", errors)))?;
+        self.bundle.add_resource_overriding(resource);
+
+        Ok(self)
+    }
+
+    /// Localized text of the prompt. Falls back to the English text if the message is unknown.
+    pub fn localize(&self, guidance: UserGuidance) -> String {
+        let pattern = self
+            .bundle
+            .get_message(guidance.id())
+            .and_then(|message| message.value());
+
+        match pattern {
+            Some(pattern) => {
+                let mut errors = Vec::new();
+                self.bundle
+                    .format_pattern(pattern, None, &mut errors)
+                    .into_owned()
+            }
+            None => guidance.to_string(),
+        }
+    }
+}
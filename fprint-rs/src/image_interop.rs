@@ -0,0 +1,28 @@
+use crate::Image;
+use image::{GrayImage, ImageFormat};
+use std::path::Path;
+
+impl Image {
+    /// Copies greyscale data of the image into a `GrayImage`, so captures can be processed
+    /// with the `image` crate.
+    pub fn to_gray_image(&self) -> GrayImage {
+        let mut pixels = Vec::new();
+        let (width, height) = self.copy_data_into(&mut pixels);
+
+        GrayImage::from_raw(width.max(0) as u32, height.max(0) as u32, pixels)
+            .expect("copied data has width * height pixels")
+    }
+
+    /// Saves the image to a file in the `format`, e.g. `ImageFormat::Png`.
+    pub fn save_as(&self, path: impl AsRef<Path>, format: ImageFormat) -> crate::Result<()> {
+        self.to_gray_image().save_with_format(path, format)?;
+
+        Ok(())
+    }
+}
+
+impl From<&Image> for GrayImage {
+    fn from(image: &Image) -> Self {
+        image.to_gray_image()
+    }
+}
@@ -0,0 +1,368 @@
+use std::{fs::File, io, ops::Range, path::Path};
+
+/// Number of pixels processed together, wide enough for the compiler to use SIMD registers
+/// on both x86 and ARM.
+const LANES: usize = 16;
+
+/// Side of the square averaged by `anonymize()` and assessed by `assess_quality()`. It is
+/// larger than the ridge spacing of 500 dpi scans, so ridges and minutiae do not survive
+/// averaging, and every cell with a finger holds both ridges and valleys.
+const CELL: usize = 12;
+
+/// Number of grey levels left by `anonymize()`.
+const LEVELS: u32 = 4;
+
+/// Standard deviation of grey levels above which a cell is considered to contain ridges,
+/// the sensor background is nearly uniform.
+const RIDGE_DEVIATION: f32 = 12.0;
+/// Share of ridge pixels below which the scan is considered dry.
+const DRY_RIDGES: f32 = 0.35;
+/// Share of ridge pixels above which the scan is considered wet.
+const WET_RIDGES: f32 = 0.65;
+/// Minimal coverage and contrast of scans accepted by `QualityReport::is_acceptable()`.
+const MIN_COVERAGE: f32 = 0.5;
+const MIN_CONTRAST: f32 = 0.15;
+
+/// Stretches contrast of greyscale pixels in place, so the darkest pixel becomes 0 and
+/// the brightest 255. Images with a single color are left unchanged.
+///
+/// Unlike `RawImage::standardize()` this works on any buffer, for example one filled by
+/// `Image::copy_data_into()`, and is cheap enough to run on every captured frame.
+pub fn normalize(pixels: &mut [u8]) {
+    let (min, max) = min_max(pixels);
+    if max <= min {
+        return;
+    }
+
+    let (min, range) = (u32::from(min), u32::from(max - min));
+    let mut table = [0u8; 256];
+    for (value, out) in table.iter_mut().enumerate().skip(min as usize) {
+        *out = (((value as u32 - min) * 255 + range / 2) / range).min(255) as u8;
+    }
+
+    for pixel in pixels {
+        *pixel = table[usize::from(*pixel)];
+    }
+}
+
+/// Reduces greyscale pixels of a `width` wide image to coarse cells with a few grey levels
+/// in place. The result still shows the contact area and pressure of the finger, but not
+/// the ridges, so it is not usable as a biometric.
+pub fn anonymize(pixels: &mut [u8], width: usize) {
+    if width == 0 {
+        return;
+    }
+
+    let height = pixels.len() / width;
+    for (rows, columns) in cells(width, height) {
+        let mut sum = 0;
+        for row in rows.clone() {
+            let line = &pixels[row * width..][columns.clone()];
+            sum += line.iter().map(|&pixel| u32::from(pixel)).sum::<u32>();
+        }
+
+        let mean = sum / (rows.len() * columns.len()) as u32;
+        let step = 255 / (LEVELS - 1);
+        let value = ((mean + step / 2) / step * step) as u8;
+        for row in rows {
+            for pixel in &mut pixels[row * width..][columns.clone()] {
+                *pixel = value;
+            }
+        }
+    }
+}
+
+/// Quality of a scan computed by `assess_quality()`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QualityReport {
+    /// Share of the image covered by the finger, from 0.0 to 1.0.
+    pub coverage: f32,
+    /// Local contrast of ridges in the finger area, from 0.0 to 1.0.
+    pub contrast: f32,
+    /// Share of ridge pixels in the finger area, about a half in good scans.
+    pub ridge_ratio: f32,
+    /// Ridges are thin and broken, the finger is probably too dry.
+    pub dry: bool,
+    /// Ridges merge together, the finger is probably wet or pressed too hard.
+    pub wet: bool,
+}
+
+impl QualityReport {
+    /// Whether the scan is good enough to be enrolled: the finger covers most of the image,
+    /// ridges are clearly visible and the finger is neither dry nor wet.
+    pub fn is_acceptable(&self) -> bool {
+        self.coverage >= MIN_COVERAGE && self.contrast >= MIN_CONTRAST && !self.dry && !self.wet
+    }
+}
+
+/// Assesses quality of greyscale pixels of a `width` wide image with dark ridges, as in
+/// standardized images. Cells of the image with enough variation of grey levels are
+/// considered to be covered by the finger, pixels darker than the middle of their range
+/// are considered to be ridges.
+pub fn assess_quality(pixels: &[u8], width: usize) -> QualityReport {
+    if width == 0 {
+        return QualityReport::default();
+    }
+
+    let height = pixels.len() / width;
+    let mut total = 0;
+    let mut finger = Vec::new();
+    let mut deviations = 0.0;
+    for (rows, columns) in cells(width, height) {
+        let (mut sum, mut squares) = (0u64, 0u64);
+        for row in rows.clone() {
+            for &pixel in &pixels[row * width..][columns.clone()] {
+                sum += u64::from(pixel);
+                squares += u64::from(pixel) * u64::from(pixel);
+            }
+        }
+
+        let len = (rows.len() * columns.len()) as f32;
+        let mean = sum as f32 / len;
+        let deviation = (squares as f32 / len - mean * mean).max(0.0).sqrt();
+
+        total += 1;
+        if deviation >= RIDGE_DEVIATION {
+            deviations += deviation;
+            finger.push((rows, columns));
+        }
+    }
+
+    if finger.is_empty() {
+        return QualityReport::default();
+    }
+
+    let (mut min, mut max) = (u8::MAX, u8::MIN);
+    for (rows, columns) in &finger {
+        for row in rows.clone() {
+            let (line_min, line_max) = min_max(&pixels[row * width..][columns.clone()]);
+            min = min.min(line_min);
+            max = max.max(line_max);
+        }
+    }
+
+    let middle = ((u16::from(min) + u16::from(max)) / 2) as u8;
+    let (mut ridges, mut area) = (0, 0);
+    for (rows, columns) in &finger {
+        for row in rows.clone() {
+            let line = &pixels[row * width..][columns.clone()];
+            ridges += line.iter().filter(|&&pixel| pixel < middle).count();
+            area += line.len();
+        }
+    }
+
+    let ridge_ratio = ridges as f32 / area as f32;
+    QualityReport {
+        coverage: finger.len() as f32 / total as f32,
+        // Deviation of a cell is at most 127.5, when half of its pixels are black and half white.
+        contrast: (deviations / finger.len() as f32 / 127.5).min(1.0),
+        ridge_ratio,
+        dry: ridge_ratio < DRY_RIDGES,
+        wet: ridge_ratio > WET_RIDGES,
+    }
+}
+
+/// Greyscale image reduced by `Image::anonymized()`, safe to include in logs and bug reports.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AnonymizedImage {
+    pub width: i32,
+    pub height: i32,
+    pub pixels: Vec<u8>,
+}
+
+impl AnonymizedImage {
+    /// Saves the image to a file in [PGM format](http://netpbm.sourceforge.net/doc/pgm.html).
+    pub fn save_to_file(&self, path: impl AsRef<Path>) -> crate::Result<()> {
+        write_pgm(File::create(path)?, self.width, self.height, &self.pixels)
+    }
+}
+
+/// Writes greyscale pixels in PGM format, shared by `Image` and `AnonymizedImage`.
+pub(crate) fn write_pgm(
+    mut writer: impl io::Write,
+    width: i32,
+    height: i32,
+    pixels: &[u8],
+) -> crate::Result<()> {
+    writeln!(writer, "P5 {} {} 255", width, height)?;
+    writer.write_all(pixels)?;
+
+    Ok(())
+}
+
+/// Finds the darkest and the brightest pixel. Pixels are compared in independent lanes,
+/// which lets the loop be vectorized.
+fn min_max(pixels: &[u8]) -> (u8, u8) {
+    let mut min = [u8::MAX; LANES];
+    let mut max = [u8::MIN; LANES];
+
+    let chunks = pixels.chunks_exact(LANES);
+    let rest = chunks.remainder();
+    for chunk in chunks {
+        for lane in 0..LANES {
+            min[lane] = min[lane].min(chunk[lane]);
+            max[lane] = max[lane].max(chunk[lane]);
+        }
+    }
+
+    let min = min.iter().chain(rest).copied().min().unwrap_or(u8::MAX);
+    let max = max.iter().chain(rest).copied().max().unwrap_or(u8::MIN);
+
+    (min, max)
+}
+
+/// Splits a `width` by `height` image into `CELL` sized squares, squares at the right and
+/// bottom edges may be smaller.
+fn cells(width: usize, height: usize) -> impl Iterator<Item = (Range<usize>, Range<usize>)> {
+    (0..height).step_by(CELL).flat_map(move |top| {
+        (0..width).step_by(CELL).map(move |left| {
+            (
+                top..(top + CELL).min(height),
+                left..(left + CELL).min(width),
+            )
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Side of test scans, 4 by 4 cells.
+    const SIDE: usize = CELL * 4;
+
+    /// Pixels with a pattern, so every lane of `min_max()` sees different values.
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 37 % 200 + 20) as u8).collect()
+    }
+
+    /// `SIDE` by `SIDE` scan with vertical ridges, `ridge` columns of every 6 are dark.
+    /// Columns from `finger_width` are left blank.
+    fn ridges(ridge: usize, finger_width: usize) -> Vec<u8> {
+        (0..SIDE * SIDE)
+            .map(|i| match i % SIDE {
+                x if x >= finger_width => 230,
+                x if x % 6 < ridge => 30,
+                _ => 220,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn min_max_matches_iterators() {
+        for &len in &[0, 1, LANES - 1, LANES, LANES * 3 + 5, 1000] {
+            let pixels = pattern(len);
+            let expected = (
+                pixels.iter().copied().min().unwrap_or(u8::MAX),
+                pixels.iter().copied().max().unwrap_or(u8::MIN),
+            );
+            assert_eq!(min_max(&pixels), expected, "{} pixels", len);
+        }
+    }
+
+    #[test]
+    fn contrast_is_stretched() {
+        let mut pixels = vec![10, 20, 30, 30, 10];
+        normalize(&mut pixels);
+        assert_eq!(pixels, vec![0, 128, 255, 255, 0]);
+
+        let mut pixels = pattern(LANES * 3 + 5);
+        normalize(&mut pixels);
+        assert_eq!(min_max(&pixels), (0, 255));
+    }
+
+    #[test]
+    fn single_color_is_not_normalized() {
+        let mut pixels = vec![42; LANES + 1];
+        normalize(&mut pixels);
+        assert_eq!(pixels, vec![42; LANES + 1]);
+
+        let mut empty = Vec::new();
+        normalize(&mut empty);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn good_scan_is_acceptable() {
+        let report = assess_quality(&ridges(3, SIDE), SIDE);
+
+        assert_eq!(report.coverage, 1.0);
+        assert_eq!(report.ridge_ratio, 0.5);
+        assert!((report.contrast - 95.0 / 127.5).abs() < 0.01);
+        assert!(!report.dry && !report.wet);
+        assert!(report.is_acceptable());
+    }
+
+    #[test]
+    fn coverage_counts_cells_with_ridges() {
+        let report = assess_quality(&ridges(3, SIDE / 2), SIDE);
+        assert_eq!(report.coverage, 0.5);
+        assert!(report.is_acceptable());
+
+        let report = assess_quality(&ridges(3, CELL), SIDE);
+        assert_eq!(report.coverage, 0.25);
+        assert!(!report.is_acceptable());
+    }
+
+    #[test]
+    fn dry_and_wet_scans_are_detected() {
+        let dry = assess_quality(&ridges(1, SIDE), SIDE);
+        assert!(dry.dry && !dry.wet && !dry.is_acceptable());
+
+        let wet = assess_quality(&ridges(5, SIDE), SIDE);
+        assert!(wet.wet && !wet.dry && !wet.is_acceptable());
+    }
+
+    #[test]
+    fn blank_scan_has_no_quality() {
+        assert_eq!(
+            assess_quality(&[230; SIDE * SIDE], SIDE),
+            QualityReport::default()
+        );
+        assert_eq!(assess_quality(&pattern(100), 0), QualityReport::default());
+        assert_eq!(assess_quality(&[], SIDE), QualityReport::default());
+        assert!(!QualityReport::default().is_acceptable());
+    }
+
+    #[test]
+    fn anonymized_scan_has_no_ridges() {
+        let mut pixels = ridges(3, SIDE / 2);
+        anonymize(&mut pixels, SIDE);
+
+        // Ridge cells average to 125, blank ones to 230, rounded to 4 levels.
+        assert!(pixels
+            .chunks(SIDE)
+            .all(|row| row[..SIDE / 2].iter().all(|&pixel| pixel == 85)
+                && row[SIDE / 2..].iter().all(|&pixel| pixel == 255)));
+        assert_eq!(assess_quality(&pixels, SIDE), QualityReport::default());
+    }
+
+    #[test]
+    fn partial_cells_are_anonymized() {
+        let width = CELL + 5;
+        let mut pixels = pattern(width * (CELL + 3));
+        anonymize(&mut pixels, width);
+
+        assert!(pixels.iter().all(|pixel| [0, 85, 170, 255].contains(pixel)));
+        for (rows, columns) in cells(width, CELL + 3) {
+            let first = pixels[rows.start * width + columns.start];
+            for row in rows {
+                assert!(pixels[row * width..][columns.clone()]
+                    .iter()
+                    .all(|&pixel| pixel == first));
+            }
+        }
+
+        let mut unchanged = pattern(10);
+        anonymize(&mut unchanged, 0);
+        assert_eq!(unchanged, pattern(10));
+    }
+
+    #[test]
+    fn pgm_is_written() {
+        let mut pgm = Vec::new();
+        write_pgm(&mut pgm, 2, 3, &[0, 1, 2, 253, 254, 255]).unwrap();
+        assert_eq!(pgm, b"P5 2 3 255\n\x00\x01\x02\xfd\xfe\xff");
+    }
+}
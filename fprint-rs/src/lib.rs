@@ -1,18 +1,85 @@
 #![warn(clippy::all)]
 
+#[cfg(feature = "async")]
+pub mod asynchronous;
+#[cfg(feature = "attendance")]
+pub mod attendance;
+#[cfg(feature = "audit")]
+pub mod audit;
+mod cancel;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod context;
 mod device;
 mod discovered_device;
 mod driver;
 mod errors;
+mod event_source;
 mod finger;
+mod gallery;
+mod guidance;
+#[cfg(feature = "hotplug")]
+pub mod hotplug;
+#[cfg(feature = "i18n")]
+pub mod i18n;
+#[cfg(feature = "image")]
+mod image_interop;
+mod image_ops;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+mod minutia;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "offload")]
+pub mod offload;
+mod options;
 mod print_data;
+mod print_store;
+#[cfg(feature = "proof")]
+pub mod proof;
+#[cfg(feature = "python")]
+mod python;
+mod scan;
+#[cfg(feature = "secure-memory")]
+mod secure_buffer;
+mod session;
+mod template;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+#[cfg(feature = "users")]
+mod uid_store;
+mod user_store;
+mod util;
+#[cfg(feature = "websocket")]
+pub mod websocket;
 
-pub use crate::{device::*, discovered_device::*, driver::*, errors::*, finger::*, print_data::*};
+use crate::context::Context;
+#[cfg(feature = "secure-memory")]
+pub use crate::secure_buffer::*;
+#[cfg(feature = "users")]
+pub use crate::uid_store::*;
+pub use crate::{
+    cancel::*, device::*, discovered_device::*, driver::*, errors::*, event_source::*, finger::*,
+    gallery::*, guidance::*, image_ops::*, minutia::*, options::*, print_data::*, print_store::*,
+    session::*, template::*, user_store::*, util::*,
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 pub type Result<T> = std::result::Result<T, FPrintError>;
 
+/// Interval between discovery attempts of `FPrint::wait_for_device()`.
+const DISCOVERY_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Handle of initialised libfprint. Clones and other `FPrint`s of the process share the
+/// initialisation. Discovered and opened devices keep libfprint initialised too, so it is
+/// deinitialised only when the last of them is dropped.
 #[derive(Debug, Clone)]
-pub struct FPrint;
+pub struct FPrint(Arc<Context>);
 
 impl FPrint {
     /// Initialise libfprint.
@@ -32,28 +99,63 @@ impl FPrint {
     /// # LIBUSB_DEBUG=4 G_MESSAGES_DEBUG=all my-libfprint-application
     /// ```
     pub fn new() -> crate::Result<FPrint> {
-        let res = unsafe { fprint_sys::fp_init() } as i32;
-
-        if res == 0 {
-            Ok(FPrint)
-        } else {
-            Err(crate::FPrintError::InitError(res))
-        }
+        Context::acquire().map(|context| FPrint(Arc::new(context)))
     }
 
     /// Scans the system and returns a list of discovered devices. This is your entry point
     /// into finding a fingerprint reader to operate.
     pub fn discover(&self) -> DiscoveredDevices {
-        let devices_list = unsafe { fprint_sys::fp_discover_devs() };
+        let _lock = context::lock();
+        let devices = unsafe { DiscoveredDevices::with_devices(fprint_sys::fp_discover_devs()) };
+        devices.in_context(&self.0)
+    }
 
-        DiscoveredDevices::with_devices(devices_list)
+    /// Same as `FPrint::discover()`, but only devices matched by `filter` are returned:
+    ///
+    /// ```ignore
+    /// let devices = fprint.discover_with(CompatibleWith(&print));
+    /// let devices = fprint.discover_with(|device: &DiscoveredDev| device.get_devtype() != 0);
+    /// ```
+    pub fn discover_with(&self, filter: impl DeviceFilter) -> DiscoveredDevices {
+        let mut devices = self.discover();
+        devices.retain(filter);
+
+        devices
     }
-}
 
-impl Drop for FPrint {
-    fn drop(&mut self) {
-        unsafe {
-            fprint_sys::fp_exit();
+    /// Blocks until at least one supported device is discovered and returns the discovered
+    /// devices. Useful when the application starts before the USB devices are enumerated.
+    /// Returns `FPrintError::NoDevice` if nothing was attached within `timeout`.
+    pub fn wait_for_device(&self, timeout: Duration) -> crate::Result<DiscoveredDevices> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let devices = self.discover();
+            if !devices.is_empty() {
+                return Ok(devices);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(crate::FPrintError::NoDevice);
+            }
+
+            std::thread::sleep(DISCOVERY_INTERVAL.min(deadline - now));
         }
     }
+
+    /// Removes all prints saved with `PrintData::save_to_disk()` for the device type, so they
+    /// can be removed without opening the device. Returns number of removed prints.
+    pub fn wipe_prints_for(&self, driver_id: u16, devtype: u32) -> crate::Result<usize> {
+        print_store::delete_saved_prints(driver_id, devtype)
+    }
+
+    /// Deinitialises libfprint at a chosen point instead of when `FPrint` is dropped.
+    /// Fails with `FPrintError::ContextInUse` if clones of the handle, discovered or opened
+    /// devices are still alive. libfprint stays initialised while other `FPrint`s are alive.
+    pub fn shutdown(self) -> crate::Result<()> {
+        Arc::try_unwrap(self.0)
+            .map(drop)
+            .map_err(|_| crate::FPrintError::ContextInUse)
+    }
 }
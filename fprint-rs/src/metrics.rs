@@ -0,0 +1,212 @@
+//! Device metrics and Prometheus exporter.
+//!
+//! Every enrollment stage, verification and identification performed by a `Device` is counted
+//! per device (driver name and devtype). Counters can be read with `snapshot()` or scraped by
+//! Prometheus from the endpoint started with `serve()`.
+
+use crate::{Device, EnrollResult, IdentifyResult, VerifyResult};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Mutex, MutexGuard},
+};
+
+static REGISTRY: Mutex<BTreeMap<DeviceLabels, DeviceMetrics>> = Mutex::new(BTreeMap::new());
+
+/// Labels identifying a device in metrics.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct DeviceLabels {
+    pub driver: String,
+    pub devtype: u32,
+}
+
+/// Counters keyed by result label (or operation name for errors).
+pub type Counters = BTreeMap<&'static str, u64>;
+
+/// Counters of a single device.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceMetrics {
+    pub enroll_stages: Counters,
+    pub verifications: Counters,
+    pub identifications: Counters,
+    pub errors: Counters,
+}
+
+/// Returns a copy of counters of all devices used so far.
+pub fn snapshot() -> Vec<(DeviceLabels, DeviceMetrics)> {
+    registry()
+        .iter()
+        .map(|(labels, metrics)| (labels.clone(), metrics.clone()))
+        .collect()
+}
+
+/// Renders all counters in the Prometheus text exposition format.
+pub fn render() -> String {
+    let registry = registry();
+    let mut out = String::new();
+
+    write_family(
+        &mut out,
+        &registry,
+        ("fprint_enroll_stages_total", "result"),
+        "Enrollment stages performed, by result.",
+        |m| &m.enroll_stages,
+    );
+    write_family(
+        &mut out,
+        &registry,
+        ("fprint_verifications_total", "result"),
+        "Verification scans performed, by result.",
+        |m| &m.verifications,
+    );
+    write_family(
+        &mut out,
+        &registry,
+        ("fprint_identifications_total", "result"),
+        "Identification scans performed, by result.",
+        |m| &m.identifications,
+    );
+    write_family(
+        &mut out,
+        &registry,
+        ("fprint_errors_total", "operation"),
+        "Operations failed with an error, by operation.",
+        |m| &m.errors,
+    );
+
+    out
+}
+
+fn write_family(
+    out: &mut String,
+    registry: &BTreeMap<DeviceLabels, DeviceMetrics>,
+    (name, label): (&str, &str),
+    help: &str,
+    counters: fn(&DeviceMetrics) -> &Counters,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    for (device, metrics) in registry.iter() {
+        for (value, count) in counters(metrics) {
+            let _ = writeln!(
+                out,
+                "{}{{driver=\"{}\",devtype=\"{}\",{}=\"{}\"}} {}",
+                name,
+                escape(&device.driver),
+                device.devtype,
+                label,
+                value,
+                count
+            );
+        }
+    }
+}
+
+/// Serves `render()` output on `GET /metrics` for every connection accepted by the `listener`.
+/// This function blocks, so it is usually started in a dedicated thread.
+pub fn serve(listener: TcpListener) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let _ = respond(stream?);
+    }
+
+    Ok(())
+}
+
+fn respond(stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Skip request headers.
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let mut stream = reader.into_inner();
+    if request_line.starts_with("GET /metrics ") {
+        let body = render();
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        stream
+            .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+    }
+}
+
+fn registry() -> MutexGuard<'static, BTreeMap<DeviceLabels, DeviceMetrics>> {
+    // Counters stay consistent even if a thread panicked while holding the lock.
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn update(device: &Device, f: impl FnOnce(&mut DeviceMetrics)) {
+    let labels = DeviceLabels {
+        driver: device.get_driver_info().name.clone(),
+        devtype: device.get_dev_type(),
+    };
+
+    f(registry().entry(labels).or_default());
+}
+
+fn increment(counters: &mut Counters, key: &'static str) {
+    *counters.entry(key).or_insert(0) += 1;
+}
+
+fn verify_label(result: &VerifyResult) -> &'static str {
+    match result {
+        VerifyResult::NoMatch => "no_match",
+        VerifyResult::Match => "match",
+        VerifyResult::Retry => "retry",
+        VerifyResult::RetryTooShort => "retry_too_short",
+        VerifyResult::RetryCenterFinger => "retry_center_finger",
+        VerifyResult::RetryRemoveFinger => "retry_remove_finger",
+    }
+}
+
+pub(crate) fn record_enroll(device: &Device, result: &crate::Result<EnrollResult>) {
+    update(device, |metrics| match result {
+        Ok(result) => {
+            let label = match result {
+                EnrollResult::Complete(_, _) => "complete",
+                EnrollResult::Fail => "fail",
+                EnrollResult::Pass(_) => "pass",
+                EnrollResult::Retry => "retry",
+                EnrollResult::RetryTooShort => "retry_too_short",
+                EnrollResult::RetryCenterFinger => "retry_center_finger",
+                EnrollResult::RetryRemoveFinger => "retry_remove_finger",
+            };
+            increment(&mut metrics.enroll_stages, label);
+        }
+        Err(_) => increment(&mut metrics.errors, "enroll"),
+    });
+}
+
+pub(crate) fn record_verify(device: &Device, result: &crate::Result<VerifyResult>) {
+    update(device, |metrics| match result {
+        Ok(result) => increment(&mut metrics.verifications, verify_label(result)),
+        Err(_) => increment(&mut metrics.errors, "verify"),
+    });
+}
+
+pub(crate) fn record_identify(device: &Device, result: &crate::Result<IdentifyResult>) {
+    update(device, |metrics| match result {
+        Ok(IdentifyResult::Matched(_)) => increment(&mut metrics.identifications, "match"),
+        Ok(IdentifyResult::Error(result)) => {
+            increment(&mut metrics.identifications, verify_label(result))
+        }
+        Err(_) => increment(&mut metrics.errors, "identify"),
+    });
+}
@@ -0,0 +1,93 @@
+use crate::StandardImage;
+use std::os::raw::c_int;
+
+/// Angle between neighbouring minutia directions, NBIS uses 32 directions for the full turn.
+const DIRECTION_STEP: f32 = 11.25;
+/// Value of `RawMinutia::kind` for bifurcations, other values are ridge endings.
+const BIFURCATION: c_int = 0;
+
+/// Layout of `struct fp_minutia`, which is opaque in the public libfprint header. It matches
+/// `struct minutia` of NBIS that libfprint uses for detection.
+#[repr(C)]
+#[allow(dead_code)]
+struct RawMinutia {
+    x: c_int,
+    y: c_int,
+    ex: c_int,
+    ey: c_int,
+    direction: c_int,
+    reliability: f64,
+    kind: c_int,
+    appearing: c_int,
+    feature_id: c_int,
+    nbrs: *mut c_int,
+    ridge_counts: *mut c_int,
+    num_nbrs: c_int,
+}
+
+/// Type of the ridge feature.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MinutiaKind {
+    /// Ridge ends.
+    Ending,
+    /// Ridge splits into two.
+    Bifurcation,
+}
+
+/// Minutia detected in a standardized image.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Minutia {
+    /// Column of the minutia in pixels.
+    pub x: u32,
+    /// Row of the minutia in pixels.
+    pub y: u32,
+    /// Direction of the ridge in degrees, 0 points up and angles grow clockwise.
+    pub angle: f32,
+    /// Reliability of the detection from 0.0 to 1.0.
+    pub quality: f32,
+    pub kind: MinutiaKind,
+}
+
+impl StandardImage {
+    /// Detects minutiae of the image, e.g. to draw them over the scan or to analyse
+    /// templates. Detection is done once, later calls return the same minutiae.
+    ///
+    /// Minutiae are copied out of the image, so they can be kept after dropping it.
+    pub fn minutiae(&self) -> crate::Result<Vec<Minutia>> {
+        let image = self.as_raw();
+        let mut count = 0;
+        let list = if image.is_null() {
+            std::ptr::null_mut()
+        } else {
+            let _lock = crate::context::lock();
+            unsafe { fprint_sys::fp_img_get_minutiae(image, &mut count) }
+        };
+        if list.is_null() {
+            return Err(crate::FPrintError::NullPtr(crate::NullPtrContext::Minutiae));
+        }
+
+        // The list is owned by the image and lives until the image is freed.
+        let list = unsafe { std::slice::from_raw_parts(list, count.max(0) as usize) };
+        let minutiae = list
+            .iter()
+            .filter(|minutia| !minutia.is_null())
+            .map(|&minutia| {
+                let minutia = unsafe { &*(minutia as *const RawMinutia) };
+                Minutia {
+                    x: minutia.x.max(0) as u32,
+                    y: minutia.y.max(0) as u32,
+                    angle: minutia.direction as f32 * DIRECTION_STEP,
+                    quality: minutia.reliability as f32,
+                    kind: match minutia.kind {
+                        BIFURCATION => MinutiaKind::Bifurcation,
+                        _ => MinutiaKind::Ending,
+                    },
+                }
+            })
+            .collect();
+
+        Ok(minutiae)
+    }
+}
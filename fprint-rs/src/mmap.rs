@@ -0,0 +1,382 @@
+//! Compact gallery files, memory-mapped instead of read at startup.
+//!
+//! A `GalleryFile` holds keys and serialized prints of a large gallery. Opening it only maps
+//! the file and checks its index, prints are borrowed from the mapping when needed, so
+//! galleries of tens of thousands prints are available immediately:
+//!
+//! ```ignore
+//! GalleryFile::write("gallery.fpg", rows.iter().map(|row| (&row.user, &row.print)))?;
+//!
+//! let file = GalleryFile::open("gallery.fpg")?;
+//! let result = device.identify_finger_image(&file.prints(0..file.len()))?;
+//! if let IdentifyResult::Matched(index) = result {
+//!     println!("Hello, {}!", file.get_key(index).unwrap());
+//! }
+//! ```
+//!
+//! File is `FPGF`, the format version, 3 reserved bytes, number of prints (`u32`) and offset
+//! of the index (`u64`), followed by the entries and the index. Every entry is the key and
+//! the print, the index holds offset of the entry (`u64`), length of the key (`u32`) and
+//! length of the print (`u32`) for every entry. Integers are little-endian.
+
+use crate::{Gallery, PrintData};
+use memmap2::Mmap;
+use std::{
+    convert::TryFrom,
+    fs::{self, File},
+    io::{BufWriter, Seek, SeekFrom, Write},
+    ops::Range,
+    path::Path,
+    str,
+};
+
+/// Leading bytes of gallery files.
+const MAGIC: &[u8; 4] = b"FPGF";
+/// Version of gallery files written by `GalleryFile::write()`.
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 4 + 1 + 3 + 4 + 8;
+const INDEX_ENTRY_LEN: usize = 8 + 4 + 4;
+
+/// Memory-mapped gallery file.
+#[derive(Debug)]
+pub struct GalleryFile {
+    map: Mmap,
+    len: usize,
+    index: usize,
+}
+
+impl GalleryFile {
+    /// Writes keys and serialized prints into a new gallery file, replacing the file
+    /// atomically. Returns number of written prints.
+    pub fn write<K, D, I>(path: impl AsRef<Path>, entries: I) -> crate::Result<usize>
+    where
+        K: AsRef<str>,
+        D: AsRef<[u8]>,
+        I: IntoIterator<Item = (K, D)>,
+    {
+        let path = path.as_ref();
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+
+        let written = write_entries(Path::new(&temp), entries);
+        match written {
+            Ok(_) => fs::rename(&temp, path)?,
+            Err(_) => {
+                let _ = fs::remove_file(&temp);
+            }
+        }
+
+        written
+    }
+
+    /// Same as `GalleryFile::write()`, but prints are serialized from `PrintData`.
+    pub fn write_prints<'a, K, I>(path: impl AsRef<Path>, entries: I) -> crate::Result<usize>
+    where
+        K: AsRef<str>,
+        I: IntoIterator<Item = (K, &'a PrintData)>,
+    {
+        let entries = entries
+            .into_iter()
+            .map(|(key, print)| Ok((key, print.as_bytes()?)))
+            .collect::<crate::Result<Vec<_>>>()?;
+
+        Self::write(path, entries)
+    }
+
+    /// Maps the gallery file and checks its index. Prints are not loaded. Files of later
+    /// format versions are rejected.
+    pub fn open(path: impl AsRef<Path>) -> crate::Result<Self> {
+        let file = File::open(path)?;
+        // Gallery files are replaced by renaming, the mapped file is not modified.
+        let map = unsafe { Mmap::map(&file)? };
+
+        if map.len() < HEADER_LEN || &map[..4] != MAGIC {
+            return Err(invalid("not a gallery file"));
+        }
+        if map[4] == 0 || map[4] > VERSION {
+            return Err(invalid("unknown gallery file version"));
+        }
+
+        let len = read_u32(&map, 8) as usize;
+        let index = read_u64(&map, 12) as usize;
+        len.checked_mul(INDEX_ENTRY_LEN)
+            .and_then(|size| size.checked_add(index))
+            .filter(|&end| end <= map.len())
+            .ok_or_else(|| invalid("index is out of the file"))?;
+
+        let file = GalleryFile { map, len, index };
+        for entry in 0..len {
+            let (offset, key_len, print_len) = file.index_entry(entry);
+            let end = offset
+                .checked_add(key_len)
+                .and_then(|end| end.checked_add(print_len))
+                .filter(|&end| offset >= HEADER_LEN && end <= file.index)
+                .ok_or_else(|| invalid("entry is out of the file"))?;
+
+            str::from_utf8(&file.map[offset..end - print_len])
+                .map_err(|_| invalid("key is not UTF-8"))?;
+        }
+
+        Ok(file)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns key and serialized print of the entry, borrowed from the mapping.
+    pub fn get(&self, index: usize) -> Option<(&str, &[u8])> {
+        if index >= self.len {
+            return None;
+        }
+
+        let (offset, key_len, print_len) = self.index_entry(index);
+        let key = &self.map[offset..offset + key_len];
+        let print = &self.map[offset + key_len..offset + key_len + print_len];
+
+        // Keys are checked when the file is opened.
+        Some((str::from_utf8(key).ok()?, print))
+    }
+
+    pub fn get_key(&self, index: usize) -> Option<&str> {
+        self.get(index).map(|(key, _)| key)
+    }
+
+    /// Serialized prints of the entries in `range`, e.g. to pass a part of the gallery to
+    /// `Device::identify_finger_image()` without loading it. Indices out of the file are
+    /// skipped.
+    pub fn prints(&self, range: Range<usize>) -> Vec<&[u8]> {
+        range
+            .filter_map(|index| self.get(index).map(|(_, print)| print))
+            .collect()
+    }
+
+    /// Iterates over keys and serialized prints in the order of their indices.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        (0..self.len).filter_map(move |index| self.get(index))
+    }
+
+    /// Loads entries in `range` into a gallery. Gallery indices start from 0, add
+    /// `range.start` to get indices in the file.
+    pub fn load(&self, range: Range<usize>) -> crate::Result<Gallery<String>> {
+        let mut gallery = Gallery::new();
+        for index in range {
+            if let Some((key, print)) = self.get(index) {
+                gallery.insert_bytes(key.to_string(), print)?;
+            }
+        }
+
+        Ok(gallery)
+    }
+
+    /// Loads all entries into a gallery.
+    pub fn to_gallery(&self) -> crate::Result<Gallery<String>> {
+        self.load(0..self.len)
+    }
+
+    fn index_entry(&self, index: usize) -> (usize, usize, usize) {
+        let entry = self.index + index * INDEX_ENTRY_LEN;
+
+        (
+            read_u64(&self.map, entry) as usize,
+            read_u32(&self.map, entry + 8) as usize,
+            read_u32(&self.map, entry + 12) as usize,
+        )
+    }
+}
+
+fn write_entries<K, D, I>(path: &Path, entries: I) -> crate::Result<usize>
+where
+    K: AsRef<str>,
+    D: AsRef<[u8]>,
+    I: IntoIterator<Item = (K, D)>,
+{
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&[0; HEADER_LEN])?;
+
+    let mut offset = HEADER_LEN as u64;
+    let mut index = Vec::new();
+    for (key, print) in entries {
+        let (key, print) = (key.as_ref().as_bytes(), print.as_ref());
+        writer.write_all(key)?;
+        writer.write_all(print)?;
+
+        let too_large = |_| invalid("entry is too large");
+        index.extend_from_slice(&offset.to_le_bytes());
+        index.extend_from_slice(&u32::try_from(key.len()).map_err(too_large)?.to_le_bytes());
+        index.extend_from_slice(&u32::try_from(print.len()).map_err(too_large)?.to_le_bytes());
+        offset += (key.len() + print.len()) as u64;
+    }
+    writer.write_all(&index)?;
+
+    let len =
+        u32::try_from(index.len() / INDEX_ENTRY_LEN).map_err(|_| invalid("too many entries"))?;
+    writer.seek(SeekFrom::Start(0))?;
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[VERSION, 0, 0, 0])?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(&offset.to_le_bytes())?;
+
+    let file = writer.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+
+    Ok(len as usize)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut value = [0; 4];
+    value.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(value)
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut value = [0; 8];
+    value.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(value)
+}
+
+fn invalid(reason: &'static str) -> crate::FPrintError {
+    crate::FPrintError::InvalidGalleryFile(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Gallery file in the temporary directory, removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let name = format!("fprint-rs-{}-{}.fpg", std::process::id(), name);
+            TempFile(std::env::temp_dir().join(name))
+        }
+
+        fn write(name: &str, bytes: &[u8]) -> Self {
+            let file = Self::new(name);
+            fs::write(&file.0, bytes).unwrap();
+            file
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn is_invalid(result: crate::Result<GalleryFile>, reason: &str) -> bool {
+        matches!(result, Err(crate::FPrintError::InvalidGalleryFile(r)) if r == reason)
+    }
+
+    fn written(name: &str) -> (TempFile, Vec<u8>) {
+        let file = TempFile::new(name);
+        let entries = vec![("alice", &b"print 1"[..]), ("bob", b""), ("", b"print 3")];
+        assert_eq!(GalleryFile::write(&file.0, entries).unwrap(), 3);
+
+        let bytes = fs::read(&file.0).unwrap();
+        (file, bytes)
+    }
+
+    #[test]
+    fn written_file_is_opened() {
+        let (file, _) = written("round-trip");
+        let gallery = GalleryFile::open(&file.0).unwrap();
+
+        assert_eq!(gallery.len(), 3);
+        assert_eq!(gallery.get(0), Some(("alice", &b"print 1"[..])));
+        assert_eq!(gallery.get(1), Some(("bob", &b""[..])));
+        assert_eq!(gallery.get(2), Some(("", &b"print 3"[..])));
+        assert_eq!(gallery.get(3), None);
+        assert_eq!(gallery.prints(1..5), vec![&b""[..], b"print 3"]);
+        assert_eq!(gallery.iter().count(), 3);
+    }
+
+    #[test]
+    fn empty_gallery_is_written() {
+        let file = TempFile::new("empty");
+        let entries: Vec<(&str, &[u8])> = Vec::new();
+        assert_eq!(GalleryFile::write(&file.0, entries).unwrap(), 0);
+
+        let gallery = GalleryFile::open(&file.0).unwrap();
+        assert!(gallery.is_empty());
+        assert_eq!(gallery.get(0), None);
+    }
+
+    #[test]
+    fn invalid_headers_are_rejected() {
+        let (_, bytes) = written("headers");
+
+        let short = TempFile::write("short", &bytes[..HEADER_LEN - 1]);
+        assert!(is_invalid(
+            GalleryFile::open(&short.0),
+            "not a gallery file"
+        ));
+
+        let mut magic = bytes.clone();
+        magic[0] = b'X';
+        let magic = TempFile::write("magic", &magic);
+        assert!(is_invalid(
+            GalleryFile::open(&magic.0),
+            "not a gallery file"
+        ));
+
+        for &version in &[0, VERSION + 1] {
+            let mut newer = bytes.clone();
+            newer[4] = version;
+            let newer = TempFile::write("version", &newer);
+            assert!(is_invalid(
+                GalleryFile::open(&newer.0),
+                "unknown gallery file version"
+            ));
+        }
+    }
+
+    #[test]
+    fn invalid_indices_are_rejected() {
+        let (_, bytes) = written("indices");
+        let index = read_u64(&bytes, 12) as usize;
+
+        let mut count = bytes.clone();
+        count[8..12].copy_from_slice(&4u32.to_le_bytes());
+        let count = TempFile::write("count", &count);
+        assert!(is_invalid(
+            GalleryFile::open(&count.0),
+            "index is out of the file"
+        ));
+
+        let mut offset = bytes.clone();
+        offset[12..20].copy_from_slice(&u64::MAX.to_le_bytes());
+        let offset = TempFile::write("offset", &offset);
+        assert!(is_invalid(
+            GalleryFile::open(&offset.0),
+            "index is out of the file"
+        ));
+
+        let mut entry = bytes.clone();
+        entry[index + 8..index + 12].copy_from_slice(&100u32.to_le_bytes());
+        let entry = TempFile::write("entry", &entry);
+        assert!(is_invalid(
+            GalleryFile::open(&entry.0),
+            "entry is out of the file"
+        ));
+
+        let mut header = bytes.clone();
+        header[index..index + 8].copy_from_slice(&0u64.to_le_bytes());
+        let header = TempFile::write("header", &header);
+        assert!(is_invalid(
+            GalleryFile::open(&header.0),
+            "entry is out of the file"
+        ));
+
+        let mut key = bytes;
+        key[HEADER_LEN] = 0xff;
+        let key = TempFile::write("key", &key);
+        assert!(is_invalid(GalleryFile::open(&key.0), "key is not UTF-8"));
+    }
+}
@@ -0,0 +1,117 @@
+//! Publishing of identification events to an MQTT broker.
+//!
+//! Attendance and home automation systems usually integrate through a broker, so every
+//! identification attempt can be published as JSON message and consumed by any subscriber.
+
+use crate::{Device, IdentifyResult, VerifyResult};
+use rumqttc::{Client, Connection};
+use serde::Serialize;
+use std::{
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+pub use rumqttc::{MqttOptions, QoS};
+
+/// Outcome of an identification attempt.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum IdentificationOutcome {
+    /// Scanned finger was found in the gallery.
+    Matched,
+    /// Scanned finger was not found in the gallery.
+    NoMatch,
+    /// Scan did not succeed and should be retried.
+    Retry,
+}
+
+impl From<&IdentifyResult> for IdentificationOutcome {
+    fn from(result: &IdentifyResult) -> Self {
+        match result {
+            IdentifyResult::Matched(_) => IdentificationOutcome::Matched,
+            IdentifyResult::Error(VerifyResult::NoMatch) => IdentificationOutcome::NoMatch,
+            IdentifyResult::Error(VerifyResult::Match) => IdentificationOutcome::Matched,
+            IdentifyResult::Error(_) => IdentificationOutcome::Retry,
+        }
+    }
+}
+
+/// Identification event published to the broker.
+#[derive(Debug, Clone, Serialize)]
+pub struct IdentificationEvent {
+    /// Key of the identified user, if any.
+    pub user: Option<String>,
+    /// Name of the driver of the device used for identification.
+    pub device: String,
+    /// Devtype of the device used for identification.
+    pub devtype: u32,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub outcome: IdentificationOutcome,
+}
+
+impl IdentificationEvent {
+    /// Creates event for the identification `result` received from the `device`.
+    /// `user` is the key of the user owning the matched print.
+    pub fn new(device: &Device, result: &IdentifyResult, user: Option<String>) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        IdentificationEvent {
+            user,
+            device: device.get_driver_info().name.clone(),
+            devtype: device.get_dev_type(),
+            timestamp,
+            outcome: result.into(),
+        }
+    }
+}
+
+/// Publisher of identification events. Connection to the broker is driven by a background
+/// thread, which reconnects automatically when the connection is lost.
+pub struct MqttPublisher {
+    client: Client,
+    topic: String,
+    qos: QoS,
+}
+
+impl MqttPublisher {
+    /// Connects to the broker and publishes events into `topic` with `QoS::AtLeastOnce`.
+    pub fn connect(options: MqttOptions, topic: impl Into<String>) -> Self {
+        let (client, connection) = Client::new(options, 16);
+        thread::spawn(move || drive(connection));
+
+        MqttPublisher {
+            client,
+            topic: topic.into(),
+            qos: QoS::AtLeastOnce,
+        }
+    }
+
+    /// Sets quality of service used for published events.
+    pub fn with_qos(mut self, qos: QoS) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    /// Queues the event for publishing.
+    pub fn publish(&self, event: &IdentificationEvent) -> crate::Result<()> {
+        let payload = serde_json::to_vec(event)?;
+
+        self.client
+            .publish(self.topic.as_str(), self.qos, false, payload)
+            .map_err(|e| crate::FPrintError::PublishFailed(e.to_string()))
+    }
+}
+
+fn drive(mut connection: Connection) {
+    // Iteration stops when the publisher is dropped.
+    for notification in connection.iter() {
+        if notification.is_err() {
+            thread::sleep(Duration::from_secs(1));
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Futures on top of blocking operations, for async code on any executor.
+//!
+//! `BlockingDevice` runs operations of a `Device` on threads provided by a `Spawner`, so
+//! they can be awaited without stalling the executor. Adapters for the blocking thread pools
+//! of the common executors are available behind features of the same names: `TokioSpawner`
+//! (`tokio`), `AsyncStdSpawner` (`async-std`) and `SmolSpawner` (`smol`). `ThreadSpawner`
+//! starts a thread per operation and works everywhere:
+//!
+//! ```ignore
+//! let device = BlockingDevice::<SmolSpawner>::new(devices.get(0).unwrap().open()?);
+//! let outcome = device.verify(&template).await?;
+//! ```
+//!
+//! Operations of the device are serialized, an operation started while another one runs
+//! waits for it to finish. Calls into libfprint of different devices are serialized too,
+//! by the process-wide lock taken by `Device`.
+
+use crate::{Device, EnrollProgress, EnrollSession, IdentifyResult, PrintData, RawImage};
+use crate::{SyncDevice, VerifyOutcome};
+use async_channel::Receiver;
+use futures_core::Stream;
+use std::{
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    task::{Context, Poll},
+    thread,
+};
+
+/// Blocking task passed to a `Spawner`.
+pub type BlockingTask = Box<dyn FnOnce() + Send>;
+
+/// Runs blocking tasks outside of the async executor.
+pub trait Spawner {
+    /// Starts the task. Its result is delivered by the task itself, so the spawner does not
+    /// need to track it.
+    fn spawn_blocking(&self, task: BlockingTask);
+}
+
+/// Spawner starting a new thread for every task.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ThreadSpawner;
+
+impl Spawner for ThreadSpawner {
+    fn spawn_blocking(&self, task: BlockingTask) {
+        thread::spawn(task);
+    }
+}
+
+/// Spawner using `tokio::task::spawn_blocking()`. Tasks must be spawned from within
+/// a tokio runtime.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct TokioSpawner;
+
+#[cfg(feature = "tokio")]
+impl Spawner for TokioSpawner {
+    fn spawn_blocking(&self, task: BlockingTask) {
+        tokio::task::spawn_blocking(task);
+    }
+}
+
+/// Spawner using `async_std::task::spawn_blocking()`.
+#[cfg(feature = "async-std")]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct AsyncStdSpawner;
+
+#[cfg(feature = "async-std")]
+impl Spawner for AsyncStdSpawner {
+    fn spawn_blocking(&self, task: BlockingTask) {
+        async_std::task::spawn_blocking(task);
+    }
+}
+
+/// Spawner using `smol::unblock()`.
+#[cfg(feature = "smol")]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SmolSpawner;
+
+#[cfg(feature = "smol")]
+impl Spawner for SmolSpawner {
+    fn spawn_blocking(&self, task: BlockingTask) {
+        smol::unblock(task).detach();
+    }
+}
+
+/// `Device` with operations returning futures and streams.
+#[derive(Debug, Clone)]
+pub struct BlockingDevice<S = ThreadSpawner> {
+    device: SyncDevice,
+    spawner: S,
+}
+
+impl<S: Spawner> BlockingDevice<S> {
+    pub fn new(device: Device) -> Self
+    where
+        S: Default,
+    {
+        Self::with_spawner(device, S::default())
+    }
+
+    pub fn with_spawner(device: Device, spawner: S) -> Self {
+        BlockingDevice {
+            device: SyncDevice::new(device),
+            spawner,
+        }
+    }
+
+    pub fn get_device(&self) -> &SyncDevice {
+        &self.device
+    }
+
+    /// Runs `operation` on a thread of the spawner. Panics of the operation are resumed
+    /// in the awaiting task.
+    pub async fn run<T, F>(&self, operation: F) -> T
+    where
+        T: Send + 'static,
+        F: FnOnce(&Device) -> T + Send + 'static,
+    {
+        let (sender, receiver) = async_channel::bounded(1);
+        let device = self.device.clone();
+
+        self.spawner.spawn_blocking(Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| operation(&device.lock())));
+            let _ = sender.send_blocking(result);
+        }));
+
+        match receiver.recv().await {
+            Ok(Ok(value)) => value,
+            Ok(Err(payload)) => panic::resume_unwind(payload),
+            Err(_) => panic!("blocking task was dropped without running"),
+        }
+    }
+
+    /// Captures an image, see `Device::capture_image()`.
+    pub async fn capture(&self, unconditional: bool) -> crate::Result<RawImage> {
+        self.run(move |device| device.capture_image(unconditional))
+            .await
+    }
+
+    /// Verifies a finger against the serialized print, retrying scans according to
+    /// the retry policy of the device, see `Device::verify()`.
+    pub async fn verify(&self, template: &[u8]) -> crate::Result<VerifyOutcome> {
+        let template = template.to_vec();
+
+        self.run(move |device| {
+            let mut print = PrintData::from_bytes(&template)?;
+            device.verify(&mut print)
+        })
+        .await
+    }
+
+    /// Scans a finger and looks it up in the serialized prints,
+    /// see `Device::identify_finger_image()`.
+    pub async fn identify<T: AsRef<[u8]>>(&self, gallery: &[T]) -> crate::Result<IdentifyResult> {
+        let gallery: Vec<_> = gallery.iter().map(|data| data.as_ref().to_vec()).collect();
+
+        self.run(move |device| device.identify_finger_image(&gallery))
+            .await
+    }
+
+    /// Enrolls a finger, reporting progress of every stage. The stream ends after
+    /// `EnrollProgress::Completed` or an error. Dropping the stream stops the enrollment
+    /// after the running stage.
+    pub fn enroll(&self) -> EnrollStream {
+        let (sender, receiver) = async_channel::bounded(1);
+        let device = self.device.clone();
+
+        self.spawner.spawn_blocking(Box::new(move || {
+            let device = device.lock();
+            let mut session = EnrollSession::new(&device);
+
+            loop {
+                let progress = session.next_stage();
+                let done = matches!(progress, Ok(EnrollProgress::Completed(_)) | Err(_));
+
+                if sender.send_blocking(progress).is_err() || done {
+                    break;
+                }
+            }
+        }));
+
+        EnrollStream(Box::pin(receiver))
+    }
+}
+
+impl<S: Spawner + Default> From<Device> for BlockingDevice<S> {
+    fn from(device: Device) -> Self {
+        Self::new(device)
+    }
+}
+
+/// Progress of the enrollment started by `BlockingDevice::enroll()`.
+pub struct EnrollStream(Pin<Box<Receiver<crate::Result<EnrollProgress>>>>);
+
+impl Stream for EnrollStream {
+    type Item = crate::Result<EnrollProgress>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.0.as_mut().poll_next(cx)
+    }
+}
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+/// How scans finished with one of `Retry` results are repeated.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RetryPolicy {
+    /// Maximum number of scans, `None` to retry until the result is known.
+    pub max_attempts: Option<u32>,
+    /// Delay before repeating a scan.
+    pub delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Checks whether one more scan is allowed after `attempts` scans.
+    pub fn allows(&self, attempts: u32) -> bool {
+        match self.max_attempts {
+            Some(max) => attempts < max,
+            None => true,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: None,
+            delay: Duration::from_millis(0),
+        }
+    }
+}
+
+/// Policy of the device, applied when it is opened with `DiscoveredDev::open_with()`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DeviceOptions {
+    retry_policy: RetryPolicy,
+    unconditional_capture: bool,
+    metrics: bool,
+}
+
+impl DeviceOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how `Device::verify()` repeats unsuccessful scans.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Sets whether `Device::capture()` captures images without waiting for a finger.
+    pub fn unconditional_capture(mut self, unconditional: bool) -> Self {
+        self.unconditional_capture = unconditional;
+        self
+    }
+
+    /// Sets whether operations of the device are counted by the `metrics` module.
+    pub fn metrics(mut self, enabled: bool) -> Self {
+        self.metrics = enabled;
+        self
+    }
+
+    pub fn get_retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+    }
+
+    pub fn is_unconditional_capture(&self) -> bool {
+        self.unconditional_capture
+    }
+
+    pub fn is_metrics_enabled(&self) -> bool {
+        self.metrics
+    }
+}
+
+impl Default for DeviceOptions {
+    fn default() -> Self {
+        DeviceOptions {
+            retry_policy: RetryPolicy::default(),
+            unconditional_capture: false,
+            metrics: true,
+        }
+    }
+}
@@ -1,21 +1,154 @@
 use crate::finger::Finger;
-use std::os::raw::c_uchar;
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    io::{self, Read, Write},
+    ops::Deref,
+    os::raw::c_uchar,
+    path::Path,
+    slice,
+};
+use zeroize::{Zeroize, Zeroizing};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Size of the header of print data serialized by libfprint.
+const PRINT_HEADER_LEN: usize = 3 + 2 + 4 + 1;
+/// Limit of prints read by `PrintData::read_from()`, so corrupted lengths do not exhaust
+/// memory.
+const MAX_PRINT_LEN: usize = 1 << 20;
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Serialized print data. Data returned by libfprint is borrowed without copying, use
+/// `to_vec()` to get an owned copy. The buffer is wiped when dropped, so biometric data
+/// does not linger in freed memory.
+pub struct TemplateBytes(Buffer);
+
+enum Buffer {
+    Owned(Zeroizing<Vec<u8>>),
+    /// Buffer allocated by libfprint, owned by the wrapper.
+    Foreign(*mut u8, usize),
+}
+
+// Foreign buffer is not shared with libfprint, it is owned exclusively by the wrapper.
+unsafe impl Send for TemplateBytes {}
+unsafe impl Sync for TemplateBytes {}
+
+impl TemplateBytes {
+    /// Takes the buffer out of the wrapper. The caller becomes responsible for wiping it.
+    pub fn into_vec(mut self) -> Vec<u8> {
+        match &mut self.0 {
+            Buffer::Owned(data) => std::mem::take(&mut **data),
+            Buffer::Foreign(_, _) => self.to_vec(),
+        }
+    }
+}
+
+impl Default for TemplateBytes {
+    fn default() -> Self {
+        TemplateBytes::from(Vec::new())
+    }
+}
+
+impl Clone for TemplateBytes {
+    fn clone(&self) -> Self {
+        TemplateBytes::from(self.to_vec())
+    }
+}
+
+impl From<Vec<u8>> for TemplateBytes {
+    fn from(data: Vec<u8>) -> Self {
+        TemplateBytes(Buffer::Owned(Zeroizing::new(data)))
+    }
+}
+
+impl Drop for TemplateBytes {
+    fn drop(&mut self) {
+        if let Buffer::Foreign(ptr, len) = self.0 {
+            unsafe {
+                slice::from_raw_parts_mut(ptr, len).zeroize();
+                libc::free(ptr as *mut libc::c_void);
+            }
+        }
+    }
+}
+
+impl PartialEq for TemplateBytes {
+    fn eq(&self, other: &Self) -> bool {
+        crate::constant_time_eq(self, other)
+    }
+}
+
+impl Eq for TemplateBytes {}
+
+impl Deref for TemplateBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.0 {
+            Buffer::Owned(data) => data,
+            Buffer::Foreign(ptr, len) => unsafe { slice::from_raw_parts(*ptr, *len) },
+        }
+    }
+}
+
+impl AsRef<[u8]> for TemplateBytes {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl fmt::Debug for TemplateBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TemplateBytes({} bytes)", self.len())
+    }
+}
+
+/// Prints are compared and hashed by their serialized data, so equal templates loaded
+/// separately are equal. Prints without data are equal to each other, other prints which
+/// can not be serialized are only equal to themselves.
 pub struct PrintData(pub(crate) *mut fprint_sys::fp_print_data);
 
+// Print data is a plain buffer without references to libfprint state, and every wrapper
+// owns its own buffer: clones are deep copies.
+unsafe impl Send for PrintData {}
+
 impl PrintData {
     /// Created PrintData without data
     pub fn new() -> Self {
-        let data = std::ptr::null_mut();
-
-        Self::with_data(data)
+        PrintData(std::ptr::null_mut())
     }
 
-    pub fn with_data(data: *mut fprint_sys::fp_print_data) -> Self {
+    /// Wraps print data allocated by libfprint.
+    ///
+    /// # Safety
+    ///
+    /// `data` must be NULL or valid print data not owned by anything else. It is freed when
+    /// the `PrintData` is dropped.
+    pub unsafe fn with_data(data: *mut fprint_sys::fp_print_data) -> Self {
         PrintData(data)
     }
 
+    /// Same as `PrintData::with_data()`.
+    ///
+    /// # Safety
+    ///
+    /// See `PrintData::with_data()`.
+    pub unsafe fn from_raw(raw: *mut fprint_sys::fp_print_data) -> Self {
+        Self::with_data(raw)
+    }
+
+    /// Returns the raw print data for calling libfprint functions which are not wrapped.
+    /// It stays owned by the wrapper.
+    pub fn as_raw(&self) -> *mut fprint_sys::fp_print_data {
+        self.0
+    }
+
+    /// Consumes the wrapper and returns the raw print data, the caller becomes responsible
+    /// for freeing it.
+    pub fn into_raw(mut self) -> *mut fprint_sys::fp_print_data {
+        std::mem::replace(&mut self.0, std::ptr::null_mut())
+    }
+
     /// Saves a stored print to disk, assigned to a specific finger. Even though you are limited
     /// to storing only the 10 human fingers, this is a per-device-type limit.
     /// For example, you can store the users right index finger from a DigitalPersona scanner,
@@ -26,6 +159,10 @@ impl PrintData {
     /// finger and device type. The print is saved in a hidden directory beneath the current
     /// user's home directory.
     pub fn save_to_disk(&self, finger: Finger) -> crate::Result<()> {
+        if self.0.is_null() {
+            return Err(empty());
+        }
+
         let result = unsafe { fprint_sys::fp_print_data_save(self.0, finger as u32) };
 
         if result == 0 {
@@ -35,73 +172,497 @@ impl PrintData {
         }
     }
 
+    /// Same as `PrintData::save_to_disk()`, but the print is saved under `base` instead of
+    /// the directory in the user's home, using the same layout:
+    /// `<base>/<driver id>/<devtype>/<finger>`. Useful for daemons running as system users.
+    pub fn save_to_dir(&self, base: impl AsRef<Path>, finger: Finger) -> crate::Result<()> {
+        let dir = base.as_ref().join(crate::print_store::device_dir(
+            self.get_driver_id(),
+            self.get_devtype(),
+        ));
+
+        let name = crate::print_store::finger_file(finger);
+
+        crate::print_store::write_print(&dir, &name, self, crate::Durability::Synced)
+    }
+
     /// Convert a stored print into a unified representation inside a data buffer.
     /// You can then store this data buffer in any way that suits you, and load it back at
-    /// some later time using `PrintData::from_data()` (or `PrintData::try_from(Location)`).
-    pub fn get_data(&self) -> crate::Result<&[u8]> {
+    /// some later time using `PrintData::from_data()`.
+    pub fn get_data(&self) -> crate::Result<TemplateBytes> {
         self.as_bytes()
     }
 
-    pub fn as_bytes(&self) -> crate::Result<&[u8]> {
+    /// Same as `PrintData::get_data()`.
+    pub fn as_bytes(&self) -> crate::Result<TemplateBytes> {
+        if self.0.is_null() {
+            return Err(empty());
+        }
+
         let mut buf: *mut c_uchar = std::ptr::null_mut();
         let length = unsafe { fprint_sys::fp_print_data_get_data(self.0, &mut buf) };
 
         if length == 0 {
             Err(crate::FPrintError::ConvertationFailed)
         } else {
-            let data = unsafe { std::slice::from_raw_parts(buf, length) };
-
-            Ok(data)
+            Ok(TemplateBytes(Buffer::Foreign(buf, length)))
         }
     }
 
+    /// Cheap digest of the serialized print (64-bit FNV-1a), stable between runs and
+    /// versions of the crate, e.g. for deduplication or logging which template was used.
+    /// It is not a cryptographic hash.
+    pub fn digest(&self) -> crate::Result<u64> {
+        let digest = self.as_bytes()?.iter().fold(FNV_OFFSET, |digest, &byte| {
+            (digest ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+        });
+
+        Ok(digest)
+    }
+
     /// Load a stored print from a data buffer. The contents of said buffer must be the untouched
     /// contents of a buffer previously supplied to you by the `PrintData::get_data()`.
     pub fn from_data(data: &[u8]) -> crate::Result<Self> {
         Self::from_bytes(data)
     }
 
+    /// Same as `PrintData::from_data()`. Data not looking like a print of libfprint is
+    /// rejected with `FPrintError::CorruptTemplate` before it is passed to libfprint.
     pub fn from_bytes(bytes: impl AsRef<[u8]>) -> crate::Result<Self> {
-        Self::from_bytes_raw(bytes).map(Self::with_data)
+        Self::from_bytes_raw(bytes).map(PrintData)
     }
 
     pub(crate) fn from_bytes_raw(
         bytes: impl AsRef<[u8]>,
     ) -> crate::Result<*mut fprint_sys::fp_print_data> {
         let bytes = bytes.as_ref();
+        check_print(bytes)?;
+
         let len = bytes.len();
         let value = bytes.as_ptr() as *mut c_uchar;
         let print = unsafe { fprint_sys::fp_print_data_from_data(value, len) };
 
         if print.is_null() {
-            // TODO: refactor it!
-            Err(crate::FPrintError::NeedError)
+            Err(corrupt("rejected by libfprint"))
         } else {
             Ok(print)
         }
     }
 
+    /// Writes the serialized print prefixed with its length (`u32`, little-endian), so
+    /// several prints can be written to one stream and read back with `PrintData::read_from()`.
+    /// The data of libfprint is written directly, without copying.
+    pub fn write_to(&self, mut writer: impl Write) -> crate::Result<()> {
+        let data = self.as_bytes()?;
+        writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        writer.write_all(&data)?;
+
+        Ok(())
+    }
+
+    /// Reads a print written by `PrintData::write_to()`. Returns `None` if the stream ends
+    /// before the print.
+    pub fn read_from(mut reader: impl Read) -> crate::Result<Option<Self>> {
+        let mut len = [0; 4];
+        match reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+
+        let len = u32::from_le_bytes(len) as usize;
+        if len > MAX_PRINT_LEN {
+            return Err(corrupt("data is too long"));
+        }
+
+        let mut data = Zeroizing::new(Vec::with_capacity(len));
+        reader.take(len as u64).read_to_end(&mut data)?;
+        if data.len() != len {
+            return Err(corrupt("data is truncated"));
+        }
+
+        Self::from_bytes(&*data).map(Some)
+    }
+
+    /// Same as `PrintData::as_bytes()`, but a CRC-32 of the data is appended, so accidental
+    /// corruption in storage is detected by `PrintData::from_checked_bytes()`.
+    /// The checksum does not protect against tampering.
+    pub fn to_checked_bytes(&self) -> crate::Result<TemplateBytes> {
+        let mut bytes = append_checksum(&self.as_bytes()?);
+
+        Ok(TemplateBytes::from(std::mem::take(&mut *bytes)))
+    }
+
+    /// Loads a print serialized by `PrintData::to_checked_bytes()`, verifying its checksum.
+    pub fn from_checked_bytes(bytes: impl AsRef<[u8]>) -> crate::Result<Self> {
+        Self::from_bytes(strip_checksum(bytes.as_ref())?)
+    }
+
     /// Gets the driver ID for a stored print. The driver ID indicates which driver the print
     /// originally came from. The print is only usable with a device controlled by that driver.
+    /// Returns 0 for a print without data.
     pub fn get_driver_id(&self) -> u16 {
+        if self.0.is_null() {
+            return 0;
+        }
+
         unsafe { fprint_sys::fp_print_data_get_driver_id(self.0) }
     }
 
+    /// Resolves the driver the print was enrolled with, so it can be displayed even when
+    /// the reader is not attached. Returns `None` if the driver is unknown.
+    pub fn driver_info(&self) -> Option<crate::DriverInfo> {
+        crate::DriverInfo::from_driver_id(self.get_driver_id(), self.get_devtype())
+    }
+
+    /// Describes the print for logs, without its data. Returns `None` for a print without
+    /// data.
+    pub fn summary(&self) -> Option<PrintSummary> {
+        if self.0.is_null() {
+            return None;
+        }
+
+        let driver_id = self.get_driver_id();
+        let devtype = self.get_devtype();
+
+        Some(PrintSummary {
+            driver_id,
+            driver: crate::DriverInfo::from_driver_id(driver_id, devtype).map(|info| info.name),
+            devtype,
+            len: self.as_bytes().map(|data| data.len()).unwrap_or_default(),
+        })
+    }
+
+    /// Checks whether the print was enrolled with a device of the driver. Devtypes are not
+    /// known for drivers, use `PrintData::is_compatible_with_device()` to compare them too.
+    pub fn is_compatible_with(&self, driver: &crate::Driver) -> bool {
+        self.get_driver_id() == driver.get_driver_id()
+    }
+
+    /// Checks whether the print was enrolled with the driver and the devtype of the device,
+    /// e.g. to filter a gallery before identification, where incompatible prints never match.
+    pub fn is_compatible_with_device(&self, device: &crate::DiscoveredDev) -> bool {
+        self.is_compatible_with(&device.get_driver()) && self.get_devtype() == device.get_devtype()
+    }
+
     /// Gets the devtype for a stored print. The [devtype](https://fprint.freedesktop.org/libfprint-stable/advanced-topics.html#device-types)
     /// represents which type of device under the parent driver is compatible with the print.
+    /// Returns 0 for a print without data.
     pub fn get_devtype(&self) -> u32 {
+        if self.0.is_null() {
+            return 0;
+        }
+
         unsafe { fprint_sys::fp_print_data_get_devtype(self.0) }
     }
 }
 
+/// Checks the header of libfprint print data: the `FP1` or `FP2` prefix, driver id (`u16`),
+/// devtype (`u32`) and data type (`u8`).
+fn check_print(bytes: &[u8]) -> crate::Result<()> {
+    if bytes.len() < PRINT_HEADER_LEN {
+        return Err(corrupt("data is too short"));
+    }
+
+    match &bytes[..3] {
+        b"FP1" | b"FP2" => Ok(()),
+        _ => Err(corrupt("unknown format")),
+    }
+}
+
+/// Copies the data with its CRC-32 (`u32`, little-endian) appended.
+fn append_checksum(data: &[u8]) -> Zeroizing<Vec<u8>> {
+    let mut bytes = Zeroizing::new(Vec::with_capacity(data.len() + 4));
+    bytes.extend_from_slice(data);
+    bytes.extend_from_slice(&crc32(data).to_le_bytes());
+
+    bytes
+}
+
+/// Verifies the CRC-32 appended by `append_checksum()` and returns the data without it.
+fn strip_checksum(bytes: &[u8]) -> crate::Result<&[u8]> {
+    if bytes.len() < 4 {
+        return Err(corrupt("data is too short"));
+    }
+
+    let (data, checksum) = bytes.split_at(bytes.len() - 4);
+    let mut expected = [0; 4];
+    expected.copy_from_slice(checksum);
+    if crc32(data) != u32::from_le_bytes(expected) {
+        return Err(corrupt("checksum mismatch"));
+    }
+
+    Ok(data)
+}
+
+fn empty() -> crate::FPrintError {
+    crate::FPrintError::NullPtr(crate::NullPtrContext::EmptyPrint)
+}
+
+fn corrupt(reason: &'static str) -> crate::FPrintError {
+    crate::FPrintError::CorruptTemplate { reason }
+}
+
+/// CRC-32 (IEEE 802.3).
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+
+    !crc
+}
+
 impl Default for PrintData {
     fn default() -> Self {
         PrintData::new()
     }
 }
 
+/// Clones are deep copies made through the serialized data, so every clone owns its own
+/// print. Prints which can not be serialized are cloned as prints without data.
+impl Clone for PrintData {
+    fn clone(&self) -> Self {
+        if self.0.is_null() {
+            return PrintData::new();
+        }
+
+        self.as_bytes()
+            .and_then(PrintData::from_bytes)
+            .unwrap_or_default()
+    }
+}
+
+/// Description of a print returned by `PrintData::summary()`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PrintSummary {
+    pub driver_id: u16,
+    /// Name of the driver, `None` if the driver is unknown.
+    pub driver: Option<String>,
+    pub devtype: u32,
+    /// Length of the serialized print.
+    pub len: usize,
+}
+
+impl fmt::Display for PrintSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.driver {
+            Some(driver) => write!(f, "{} print", driver)?,
+            None => write!(f, "print of driver {:#06x}", self.driver_id)?,
+        }
+
+        write!(f, " (devtype {:#010x}, {} bytes)", self.devtype, self.len)
+    }
+}
+
+impl fmt::Debug for PrintData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.summary() {
+            Some(summary) => f
+                .debug_struct("PrintData")
+                .field("driver_id", &summary.driver_id)
+                .field("driver", &summary.driver)
+                .field("devtype", &summary.devtype)
+                .field("len", &summary.len)
+                .finish(),
+            None => f.write_str("PrintData(empty)"),
+        }
+    }
+}
+
+impl fmt::Display for PrintData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.summary() {
+            Some(summary) => summary.fmt(f),
+            None => f.write_str("empty print"),
+        }
+    }
+}
+
+impl PartialEq for PrintData {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.0.is_null(), other.0.is_null()) {
+            (true, true) => return true,
+            (false, false) => {}
+            _ => return false,
+        }
+
+        match (self.as_bytes(), other.as_bytes()) {
+            (Ok(data), Ok(other)) => data == other,
+            (Err(_), Err(_)) => self.0 == other.0,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for PrintData {}
+
+impl Hash for PrintData {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        if self.0.is_null() {
+            state.write_u8(0);
+            return;
+        }
+
+        // Prints which can not be serialized all hash the same, they are rare.
+        if let Ok(data) = self.as_bytes() {
+            state.write(&data);
+        }
+    }
+}
+
 impl Drop for PrintData {
     fn drop(&mut self) {
         unsafe { fprint_sys::fp_print_data_free(self.0) }
     }
 }
+
+/// Print is serialized as the bytes of `PrintData::as_bytes()`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for PrintData {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes = self.as_bytes().map_err(serde::ser::Error::custom)?;
+        serializer.serialize_bytes(&bytes)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PrintData {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BytesVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for BytesVisitor {
+            type Value = PrintData;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("serialized print data")
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<PrintData, E> {
+                PrintData::from_bytes(bytes).map_err(E::custom)
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<PrintData, A::Error> {
+                let mut bytes = Zeroizing::new(Vec::with_capacity(seq.size_hint().unwrap_or(0)));
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+
+                self.visit_bytes(&bytes)
+            }
+        }
+
+        deserializer.deserialize_bytes(BytesVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Print data with a valid header of the `FP2` format.
+    fn print() -> Vec<u8> {
+        let mut data = b"FP2".to_vec();
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.push(0);
+        data.extend_from_slice(b"minutiae");
+        data
+    }
+
+    fn is_corrupt<T>(result: crate::Result<T>, expected: &str) -> bool {
+        matches!(result, Err(crate::FPrintError::CorruptTemplate { reason }) if reason == expected)
+    }
+
+    #[test]
+    fn crc32_matches_ieee() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414f_a339
+        );
+    }
+
+    #[test]
+    fn checksum_round_trip() {
+        let data = print();
+        let bytes = append_checksum(&data);
+
+        assert_eq!(bytes.len(), data.len() + 4);
+        assert_eq!(strip_checksum(&bytes).unwrap(), &data[..]);
+        assert_eq!(strip_checksum(&append_checksum(b"")).unwrap(), b"");
+    }
+
+    #[test]
+    fn corrupted_checksums_are_rejected() {
+        let bytes = append_checksum(&print());
+        for i in 0..bytes.len() {
+            let mut corrupted = bytes.to_vec();
+            corrupted[i] ^= 0x10;
+            assert!(is_corrupt(strip_checksum(&corrupted), "checksum mismatch"));
+        }
+
+        assert!(is_corrupt(
+            strip_checksum(&bytes[..bytes.len() - 1]),
+            "checksum mismatch"
+        ));
+        assert!(is_corrupt(strip_checksum(&bytes[..3]), "data is too short"));
+    }
+
+    #[test]
+    fn print_headers_are_checked() {
+        let data = print();
+        assert!(check_print(&data).is_ok());
+        assert!(check_print(&data[..PRINT_HEADER_LEN]).is_ok());
+
+        let mut legacy = data.clone();
+        legacy[2] = b'1';
+        assert!(check_print(&legacy).is_ok());
+
+        assert!(is_corrupt(
+            check_print(&data[..PRINT_HEADER_LEN - 1]),
+            "data is too short"
+        ));
+        assert!(is_corrupt(check_print(b""), "data is too short"));
+        let mut unknown = data;
+        unknown[2] = b'3';
+        assert!(is_corrupt(check_print(&unknown), "unknown format"));
+    }
+
+    #[test]
+    fn empty_print_has_no_data() {
+        let print = PrintData::default();
+
+        assert!(matches!(
+            print.as_bytes(),
+            Err(crate::FPrintError::NullPtr(
+                crate::NullPtrContext::EmptyPrint
+            ))
+        ));
+        assert!(print.save_to_disk(Finger::RightIndex).is_err());
+        assert_eq!((print.get_driver_id(), print.get_devtype()), (0, 0));
+        assert!(print.summary().is_none());
+    }
+
+    #[test]
+    fn empty_prints_are_equal() {
+        use std::collections::hash_map::DefaultHasher;
+
+        let hash = |print: &PrintData| {
+            let mut hasher = DefaultHasher::new();
+            print.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        assert_eq!(PrintData::default(), PrintData::default());
+        assert_eq!(PrintData::default(), PrintData::default().clone());
+        assert_eq!(hash(&PrintData::default()), hash(&PrintData::new()));
+    }
+}
@@ -0,0 +1,1561 @@
+use crate::{
+    Consent, Device, Finger, Gallery, IdentifyResult, PrintData, Template, TemplateBytes,
+    VerifyOutcome, VerifyResult,
+};
+use std::{
+    borrow::Borrow,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fs::{self, DirBuilder, File, OpenOptions},
+    io::{self, Read, Write},
+    os::unix::fs::{DirBuilderExt, OpenOptionsExt},
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+use zeroize::Zeroizing;
+
+/// Directory where fprintd keeps enrolled prints.
+pub const FPRINTD_STORAGE_DIR: &str = "/var/lib/fprint";
+
+/// Leading bytes of bundles written by `PrintStore::export()`.
+const BUNDLE_MAGIC: &[u8; 4] = b"FPBN";
+/// Version of bundles written by `PrintStore::export()`.
+const BUNDLE_VERSION: u8 = 1;
+/// Limit of a single print in a bundle, so corrupted lengths do not exhaust memory.
+const MAX_BUNDLE_ENTRY: usize = 1 << 20;
+/// Subdirectory of the device type directory with labeled prints.
+pub(crate) const LABELS_DIR: &str = "labels";
+/// Extension appended to the name of a print file to get the file with its consent.
+const CONSENT_EXTENSION: &str = "consent";
+
+/// Decision of `PrintStore::authenticate()`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum AuthDecision {
+    /// Scanned finger matched the print of the finger.
+    Accepted(Finger),
+    /// Scanned finger did not match any print of the user.
+    Rejected,
+    /// User has no prints for the device type.
+    NotEnrolled,
+    /// Scans did not succeed within the attempts allowed by the retry policy.
+    Inconclusive(VerifyResult),
+}
+
+/// Storage of enrolled prints. Prints are saved under an application defined key, e.g.
+/// a user name, and a finger, separately for every device type. Applications can swap
+/// storage without touching the device code:
+///
+/// ```ignore
+/// fn enroll(store: &impl PrintStore, user: &str, device: &Device) -> fprint_rs::Result<()> {
+///     let (print, _) = device.enroll(|_| {})?;
+///     store.save(user, Finger::RightIndex, &print)
+/// }
+/// ```
+pub trait PrintStore {
+    /// Saves the print, overwriting prints previously saved for the same key, finger and
+    /// device type.
+    fn save(&self, key: &str, finger: Finger, data: &PrintData) -> crate::Result<()> {
+        self.save_with_consent(key, finger, data, &Consent::default())
+    }
+
+    /// Same as `PrintStore::save()`, keeping the `consent` with the print. It is returned
+    /// with the print by `PrintStore::entries()` and included in exports.
+    fn save_with_consent(
+        &self,
+        key: &str,
+        finger: Finger,
+        data: &PrintData,
+        consent: &Consent,
+    ) -> crate::Result<()>;
+
+    /// Saves the print as another template of the finger, keeping prints saved before,
+    /// e.g. to keep prints enrolled in several sessions.
+    fn add(&self, key: &str, finger: Finger, data: &PrintData) -> crate::Result<()> {
+        self.add_with_consent(key, finger, data, &Consent::default())
+    }
+
+    /// Same as `PrintStore::add()`, keeping the `consent` with the print.
+    fn add_with_consent(
+        &self,
+        key: &str,
+        finger: Finger,
+        data: &PrintData,
+        consent: &Consent,
+    ) -> crate::Result<()>;
+
+    /// Loads the print saved for the key and finger with the device type of the `device`.
+    /// If the finger has several templates, the first saved one is returned.
+    /// Returns `FPrintError::FingerprintNotFound` if there is no such print.
+    fn load(&self, key: &str, device: &Device, finger: Finger) -> crate::Result<PrintData>;
+
+    /// Loads all templates saved for the key and finger with the device type of the `device`,
+    /// in the order they were saved. Returns `FPrintError::FingerprintNotFound` if there is
+    /// no such print.
+    fn load_all(&self, key: &str, device: &Device, finger: Finger)
+        -> crate::Result<Vec<PrintData>>;
+
+    /// Removes all templates saved for the key and finger with the device type of the `device`.
+    /// Returns `FPrintError::FingerprintNotFound` if there is no such print.
+    fn delete(&self, key: &str, device: &Device, finger: Finger) -> crate::Result<()>;
+
+    /// Lists fingers saved for the key with the device type of the `device`.
+    fn list(&self, key: &str, device: &Device) -> crate::Result<Vec<Finger>>;
+
+    /// Saves the print under the `label` (e.g. `"badge-backup"`) instead of a finger,
+    /// overwriting a print previously saved for the same key, label and device type.
+    fn save_label(&self, key: &str, label: &str, data: &PrintData) -> crate::Result<()> {
+        self.save_label_with_consent(key, label, data, &Consent::default())
+    }
+
+    /// Same as `PrintStore::save_label()`, keeping the `consent` with the print.
+    fn save_label_with_consent(
+        &self,
+        key: &str,
+        label: &str,
+        data: &PrintData,
+        consent: &Consent,
+    ) -> crate::Result<()>;
+
+    /// Loads the print saved for the key under the `label` with the device type of the `device`.
+    /// Returns `FPrintError::LabelNotFound` if there is no such print.
+    fn load_label(&self, key: &str, device: &Device, label: &str) -> crate::Result<PrintData>;
+
+    /// Removes the print saved for the key under the `label` with the device type of the
+    /// `device`. Returns `FPrintError::LabelNotFound` if there is no such print.
+    fn delete_label(&self, key: &str, device: &Device, label: &str) -> crate::Result<()>;
+
+    /// Lists labels saved for the key with the device type of the `device`, sorted.
+    fn labels(&self, key: &str, device: &Device) -> crate::Result<Vec<String>>;
+
+    /// Lists keys with at least one saved print, regardless of the device type, sorted.
+    fn keys(&self) -> crate::Result<Vec<String>>;
+
+    /// Removes all prints saved for the key, of all fingers, labels and device types, so that
+    /// print data does not remain in the storage, e.g. for erasure requests. Returns number
+    /// of removed prints.
+    fn secure_delete(&self, key: &str) -> crate::Result<usize>;
+
+    /// Passes all saved prints, of all keys and device types, to `f` one by one, without
+    /// loading them all in memory. Labeled prints are passed after prints of fingers.
+    /// Stops at the first error.
+    fn for_each_entry(
+        &self,
+        f: &mut dyn FnMut(StoredPrint) -> crate::Result<()>,
+    ) -> crate::Result<()>;
+
+    /// Loads all saved prints, of all keys and device types, including labeled ones.
+    fn entries(&self) -> crate::Result<Vec<StoredPrint>> {
+        let mut entries = Vec::new();
+        self.for_each_entry(&mut |entry| {
+            entries.push(entry);
+            Ok(())
+        })?;
+
+        Ok(entries)
+    }
+
+    /// Verifies the finger against all templates saved for it, see `Device::verify_any()`.
+    fn verify(&self, key: &str, device: &Device, finger: Finger) -> crate::Result<VerifyOutcome> {
+        device.verify_any(&mut self.load_all(key, device, finger)?)
+    }
+
+    /// Authenticates the user with a new scan: all templates saved for the key with the device
+    /// type of the `device` are loaded and the scanned finger is identified among them. When
+    /// there is a single template or the device does not support identification, the finger
+    /// is verified against the templates one by one until one of them matches. Unsuccessful
+    /// scans are repeated as allowed by the retry policy of the device.
+    fn authenticate(&self, key: &str, device: &Device) -> crate::Result<AuthDecision> {
+        let mut prints = Vec::new();
+        for finger in self.list(key, device)? {
+            for data in self.load_all(key, device, finger)? {
+                prints.push((finger, data));
+            }
+        }
+
+        authenticate_prints(device, prints)
+    }
+
+    /// Same as `PrintStore::authenticate()`, but every decision, including failed scans,
+    /// is appended to the audit `log`. If the event can not be logged, the error is returned
+    /// instead of the decision.
+    #[cfg(feature = "audit")]
+    fn authenticate_logged(
+        &self,
+        key: &str,
+        device: &Device,
+        log: &mut crate::audit::AuditLog,
+    ) -> crate::Result<AuthDecision> {
+        let result = self.authenticate(key, device);
+        log.append(crate::audit::AuditEvent::authenticate(device, &result).with_user(key))?;
+
+        result
+    }
+
+    /// Writes all saved prints, including labeled ones, into a single bundle, for backups
+    /// or moving enrollments to another machine with the same reader model. Returns number
+    /// of exported prints.
+    ///
+    /// Bundle is `FPBN`, the format version and prints serialized as `Template`s with keys
+    /// as user names, each prefixed with its length (`u32`, little-endian). Labeled prints
+    /// are written as labeled templates.
+    fn export<W: Write>(&self, mut writer: W) -> crate::Result<usize>
+    where
+        Self: Sized,
+    {
+        let mut exported = 0;
+
+        write_bundle_header(&mut writer)?;
+        self.for_each_entry(&mut |entry| {
+            let template = match entry.slot {
+                PrintSlot::Finger(finger) => Template::new(entry.data, finger),
+                PrintSlot::Label(label) => Template::labeled(entry.data, label),
+            };
+            let bytes = template
+                .with_username(entry.key)
+                .with_consent(entry.consent)
+                .to_bytes()?;
+            write_bundle_entry(&mut writer, &bytes)?;
+            exported += 1;
+
+            Ok(())
+        })?;
+        writer.flush()?;
+
+        Ok(exported)
+    }
+
+    /// Saves all prints of a bundle written by `PrintStore::export()` with their consents,
+    /// overwriting prints saved for the same keys, fingers or labels and device types.
+    /// Several templates of a finger are all kept. Bundles of later format versions are rejected. Returns number
+    /// of imported prints.
+    fn import<R: Read>(&self, mut reader: R) -> crate::Result<usize>
+    where
+        Self: Sized,
+    {
+        read_bundle_header(&mut reader)?;
+
+        let mut imported = 0;
+        let mut saved = HashSet::new();
+        while let Some(bytes) = read_bundle_entry(&mut reader)? {
+            let template = Template::from_bytes(&bytes)?;
+            let key = template
+                .get_username()
+                .ok_or(crate::FPrintError::InvalidTemplate(
+                    "bundle entry has no key",
+                ))?
+                .to_string();
+            let (print, consent) = (template.get_print(), template.get_consent());
+            match (template.get_label(), template.get_finger()) {
+                (Some(label), _) => self.save_label_with_consent(&key, label, print, consent)?,
+                (None, Some(finger)) => {
+                    let slot = (key, print.get_driver_id(), print.get_devtype(), finger);
+                    if saved.contains(&slot) {
+                        self.add_with_consent(&slot.0, finger, print, consent)?;
+                    } else {
+                        self.save_with_consent(&slot.0, finger, print, consent)?;
+                        saved.insert(slot);
+                    }
+                }
+                (None, None) => return Err(crate::FPrintError::InvalidTemplate("unknown finger")),
+            }
+            imported += 1;
+        }
+
+        Ok(imported)
+    }
+}
+
+/// Print loaded by `PrintStore::entries()` and `PrintStore::for_each_entry()`.
+#[derive(Debug)]
+pub struct StoredPrint {
+    pub key: String,
+    pub slot: PrintSlot,
+    pub data: PrintData,
+    pub consent: Consent,
+}
+
+/// What a stored print is saved under.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum PrintSlot {
+    /// Print saved with `PrintStore::save()` or `PrintStore::add()`.
+    Finger(Finger),
+    /// Print saved with `PrintStore::save_label()`.
+    Label(String),
+}
+
+/// Print storage in files, using the directory layout and file format of libfprint and
+/// fprintd: prints are saved under `<base>/<key>/<driver id>/<devtype>/<finger>`. With
+/// `FPRINTD_STORAGE_DIR` as the base (`DiskStore::fprintd()`) and user names as keys prints
+/// are shared with fprintd. Templates added to a finger with `PrintStore::add()` are saved
+/// next to the first one as `<finger>.<n>`, labeled prints are saved in the `labels`
+/// subdirectory, fprintd ignores both. Consents are saved next to print files, with
+/// the `.consent` extension appended.
+///
+/// Deleted files are overwritten before unlinking.
+#[derive(Debug, Clone)]
+pub struct DiskStore {
+    base: PathBuf,
+    durability: Durability,
+}
+
+/// How print files are written. Files are always replaced atomically, so they never contain
+/// a partially written print.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum Durability {
+    /// Data is left to the page cache, saved prints may be lost on power failure.
+    Atomic,
+    /// Files and directories are synced to disk before saving returns.
+    #[default]
+    Synced,
+}
+
+impl DiskStore {
+    pub fn at(base: impl Into<PathBuf>) -> Self {
+        DiskStore {
+            base: base.into(),
+            durability: Durability::default(),
+        }
+    }
+
+    /// Sets how print files are written, `Durability::Synced` by default.
+    pub fn with_durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Store shared with fprintd, keyed by user names. Usually only root can access it.
+    pub fn fprintd() -> Self {
+        Self::at(FPRINTD_STORAGE_DIR)
+    }
+
+    fn key_dir(&self, key: &str) -> crate::Result<PathBuf> {
+        let valid = !key.is_empty() && key != "." && key != ".." && !key.contains('/');
+        if !valid {
+            return Err(
+                io::Error::new(io::ErrorKind::InvalidInput, "Invalid print store key").into(),
+            );
+        }
+
+        Ok(self.base.join(key))
+    }
+
+    fn device_dir(&self, key: &str, driver_id: u16, devtype: u32) -> crate::Result<PathBuf> {
+        Ok(self.key_dir(key)?.join(device_dir(driver_id, devtype)))
+    }
+
+    fn device_dir_of(&self, key: &str, device: &Device) -> crate::Result<PathBuf> {
+        self.device_dir(
+            key,
+            device.get_driver_info().driver_id,
+            device.get_dev_type(),
+        )
+    }
+}
+
+impl PrintStore for DiskStore {
+    fn save_with_consent(
+        &self,
+        key: &str,
+        finger: Finger,
+        data: &PrintData,
+        consent: &Consent,
+    ) -> crate::Result<()> {
+        let dir = self.device_dir(key, data.get_driver_id(), data.get_devtype())?;
+
+        write_print(&dir, &finger_file(finger), data, self.durability)?;
+        write_consent(&dir, &finger_file(finger), consent, self.durability)?;
+        for path in added_templates(&dir, finger)? {
+            remove_print(&path, finger)?;
+        }
+
+        Ok(())
+    }
+
+    fn add_with_consent(
+        &self,
+        key: &str,
+        finger: Finger,
+        data: &PrintData,
+        consent: &Consent,
+    ) -> crate::Result<()> {
+        let dir = self.device_dir(key, data.get_driver_id(), data.get_devtype())?;
+        let name = if dir.join(finger_file(finger)).exists() {
+            added_template_file(&dir, finger)?
+        } else {
+            finger_file(finger)
+        };
+
+        write_print(&dir, &name, data, self.durability)?;
+        write_consent(&dir, &name, consent, self.durability)
+    }
+
+    fn load(&self, key: &str, device: &Device, finger: Finger) -> crate::Result<PrintData> {
+        let dir = self.device_dir_of(key, device)?;
+
+        read_print(&dir.join(finger_file(finger)), finger)
+    }
+
+    fn load_all(
+        &self,
+        key: &str,
+        device: &Device,
+        finger: Finger,
+    ) -> crate::Result<Vec<PrintData>> {
+        load_templates(&self.device_dir_of(key, device)?, finger)
+    }
+
+    fn delete(&self, key: &str, device: &Device, finger: Finger) -> crate::Result<()> {
+        let dir = self.device_dir_of(key, device)?;
+
+        remove_print(&dir.join(finger_file(finger)), finger)?;
+        for path in added_templates(&dir, finger)? {
+            remove_print(&path, finger)?;
+        }
+
+        Ok(())
+    }
+
+    fn list(&self, key: &str, device: &Device) -> crate::Result<Vec<Finger>> {
+        read_fingers(&self.device_dir_of(key, device)?)
+    }
+
+    fn save_label_with_consent(
+        &self,
+        key: &str,
+        label: &str,
+        data: &PrintData,
+        consent: &Consent,
+    ) -> crate::Result<()> {
+        let dir = self
+            .device_dir(key, data.get_driver_id(), data.get_devtype())?
+            .join(LABELS_DIR);
+        let name = label_file(label)?;
+
+        write_print(&dir, &name, data, self.durability)?;
+        write_consent(&dir, &name, consent, self.durability)
+    }
+
+    fn load_label(&self, key: &str, device: &Device, label: &str) -> crate::Result<PrintData> {
+        let dir = self.device_dir_of(key, device)?.join(LABELS_DIR);
+
+        read_label(&dir.join(label_file(label)?), label)
+    }
+
+    fn delete_label(&self, key: &str, device: &Device, label: &str) -> crate::Result<()> {
+        let dir = self.device_dir_of(key, device)?.join(LABELS_DIR);
+
+        remove_label(&dir.join(label_file(label)?), label)
+    }
+
+    fn labels(&self, key: &str, device: &Device) -> crate::Result<Vec<String>> {
+        read_labels(&self.device_dir_of(key, device)?.join(LABELS_DIR))
+    }
+
+    fn keys(&self) -> crate::Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.base) {
+            Ok(entries) => entries,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut keys = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let key = match entry.file_name().into_string() {
+                Ok(key) => key,
+                Err(_) => continue,
+            };
+
+            if has_prints(&entry.path(), 2)? {
+                keys.push(key);
+            }
+        }
+        keys.sort();
+
+        Ok(keys)
+    }
+
+    fn secure_delete(&self, key: &str) -> crate::Result<usize> {
+        let dir = self.key_dir(key)?;
+        if !dir.is_dir() {
+            return Ok(0);
+        }
+
+        let removed = wipe_dir(&dir)?;
+        fs::remove_dir_all(&dir)?;
+
+        Ok(removed)
+    }
+
+    fn for_each_entry(
+        &self,
+        f: &mut dyn FnMut(StoredPrint) -> crate::Result<()>,
+    ) -> crate::Result<()> {
+        for key in self.keys()? {
+            for driver in fs::read_dir(self.base.join(&key))? {
+                let driver = driver?.path();
+                if !driver.is_dir() {
+                    continue;
+                }
+
+                for devtype in fs::read_dir(&driver)? {
+                    let dir = devtype?.path();
+                    if !dir.is_dir() {
+                        continue;
+                    }
+
+                    for finger in read_fingers(&dir)? {
+                        for path in template_files(&dir, finger)? {
+                            f(StoredPrint {
+                                key: key.clone(),
+                                slot: PrintSlot::Finger(finger),
+                                data: read_print(&path, finger)?,
+                                consent: read_consent(&path)?,
+                            })?;
+                        }
+                    }
+
+                    let labels = dir.join(LABELS_DIR);
+                    for label in read_labels(&labels)? {
+                        let path = labels.join(label_file(&label)?);
+                        f(StoredPrint {
+                            key: key.clone(),
+                            data: read_label(&path, &label)?,
+                            consent: read_consent(&path)?,
+                            slot: PrintSlot::Label(label),
+                        })?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Templates saved for a key, finger and device type in `MemoryStore`.
+type Slots = HashMap<(String, u16, u32, Finger), Vec<SavedPrint>>;
+/// Prints saved for a key, label and device type in `MemoryStore`.
+type Labels = HashMap<(String, u16, u32, String), SavedPrint>;
+
+/// Serialized print kept by `MemoryStore`, locked in RAM with the `secure-memory` feature.
+#[cfg(feature = "secure-memory")]
+type PrintBytes = crate::SecureBuffer;
+#[cfg(not(feature = "secure-memory"))]
+type PrintBytes = TemplateBytes;
+
+/// Serialized print saved in `MemoryStore` with its consent.
+#[derive(Debug)]
+struct SavedPrint {
+    bytes: PrintBytes,
+    consent: Consent,
+}
+
+impl SavedPrint {
+    fn new(data: &PrintData, consent: &Consent) -> crate::Result<Self> {
+        let bytes = data.as_bytes()?;
+        #[cfg(feature = "secure-memory")]
+        let bytes = crate::SecureBuffer::new(&bytes)?;
+        #[cfg(not(feature = "secure-memory"))]
+        let bytes = TemplateBytes::from(bytes.to_vec());
+
+        Ok(SavedPrint {
+            bytes,
+            consent: consent.clone(),
+        })
+    }
+
+    fn load(&self) -> crate::Result<PrintData> {
+        PrintData::from_bytes(&self.bytes)
+    }
+}
+
+/// Print storage in memory, e.g. for tests. Prints are kept serialized and wiped when
+/// removed. With the `secure-memory` feature they are kept in `SecureBuffer`s.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    prints: Mutex<Slots>,
+    labels: Mutex<Labels>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn prints(&self) -> MutexGuard<'_, Slots> {
+        self.prints.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn labeled(&self) -> MutexGuard<'_, Labels> {
+        self.labels.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl PrintStore for MemoryStore {
+    fn save_with_consent(
+        &self,
+        key: &str,
+        finger: Finger,
+        data: &PrintData,
+        consent: &Consent,
+    ) -> crate::Result<()> {
+        let print = SavedPrint::new(data, consent)?;
+        self.prints()
+            .insert(data_slot(key, finger, data), vec![print]);
+
+        Ok(())
+    }
+
+    fn add_with_consent(
+        &self,
+        key: &str,
+        finger: Finger,
+        data: &PrintData,
+        consent: &Consent,
+    ) -> crate::Result<()> {
+        let print = SavedPrint::new(data, consent)?;
+        self.prints()
+            .entry(data_slot(key, finger, data))
+            .or_default()
+            .push(print);
+
+        Ok(())
+    }
+
+    fn load(&self, key: &str, device: &Device, finger: Finger) -> crate::Result<PrintData> {
+        match self.prints().get(&slot(key, device, finger)) {
+            Some(templates) if !templates.is_empty() => templates[0].load(),
+            _ => Err(crate::FPrintError::FingerprintNotFound(finger)),
+        }
+    }
+
+    fn load_all(
+        &self,
+        key: &str,
+        device: &Device,
+        finger: Finger,
+    ) -> crate::Result<Vec<PrintData>> {
+        match self.prints().get(&slot(key, device, finger)) {
+            Some(templates) if !templates.is_empty() => {
+                templates.iter().map(SavedPrint::load).collect()
+            }
+            _ => Err(crate::FPrintError::FingerprintNotFound(finger)),
+        }
+    }
+
+    fn delete(&self, key: &str, device: &Device, finger: Finger) -> crate::Result<()> {
+        match self.prints().remove(&slot(key, device, finger)) {
+            Some(_) => Ok(()),
+            None => Err(crate::FPrintError::FingerprintNotFound(finger)),
+        }
+    }
+
+    fn list(&self, key: &str, device: &Device) -> crate::Result<Vec<Finger>> {
+        let (driver_id, devtype) = (device.get_driver_info().driver_id, device.get_dev_type());
+        let mut fingers: Vec<_> = self
+            .prints()
+            .keys()
+            .filter(|slot| slot.0 == key && slot.1 == driver_id && slot.2 == devtype)
+            .map(|slot| slot.3)
+            .collect();
+        fingers.sort_by_key(|finger| *finger as u32);
+
+        Ok(fingers)
+    }
+
+    fn save_label_with_consent(
+        &self,
+        key: &str,
+        label: &str,
+        data: &PrintData,
+        consent: &Consent,
+    ) -> crate::Result<()> {
+        check_label(label)?;
+        let print = SavedPrint::new(data, consent)?;
+        let slot = (
+            key.to_string(),
+            data.get_driver_id(),
+            data.get_devtype(),
+            label.to_string(),
+        );
+        self.labeled().insert(slot, print);
+
+        Ok(())
+    }
+
+    fn load_label(&self, key: &str, device: &Device, label: &str) -> crate::Result<PrintData> {
+        match self.labeled().get(&label_slot(key, device, label)) {
+            Some(print) => print.load(),
+            None => Err(crate::FPrintError::LabelNotFound(label.to_string())),
+        }
+    }
+
+    fn delete_label(&self, key: &str, device: &Device, label: &str) -> crate::Result<()> {
+        match self.labeled().remove(&label_slot(key, device, label)) {
+            Some(_) => Ok(()),
+            None => Err(crate::FPrintError::LabelNotFound(label.to_string())),
+        }
+    }
+
+    fn labels(&self, key: &str, device: &Device) -> crate::Result<Vec<String>> {
+        let (driver_id, devtype) = (device.get_driver_info().driver_id, device.get_dev_type());
+        let mut labels: Vec<_> = self
+            .labeled()
+            .keys()
+            .filter(|slot| slot.0 == key && slot.1 == driver_id && slot.2 == devtype)
+            .map(|slot| slot.3.clone())
+            .collect();
+        labels.sort();
+
+        Ok(labels)
+    }
+
+    fn keys(&self) -> crate::Result<Vec<String>> {
+        let mut keys: Vec<_> = self.prints().keys().map(|slot| slot.0.clone()).collect();
+        keys.extend(self.labeled().keys().map(|slot| slot.0.clone()));
+        keys.sort();
+        keys.dedup();
+
+        Ok(keys)
+    }
+
+    fn secure_delete(&self, key: &str) -> crate::Result<usize> {
+        // Removed prints are wiped when dropped.
+        let mut removed = 0;
+        self.prints().retain(|slot, templates| {
+            let keep = slot.0 != key;
+            if !keep {
+                removed += templates.len();
+            }
+            keep
+        });
+        self.labeled().retain(|slot, _| {
+            let keep = slot.0 != key;
+            if !keep {
+                removed += 1;
+            }
+            keep
+        });
+
+        Ok(removed)
+    }
+
+    fn for_each_entry(
+        &self,
+        f: &mut dyn FnMut(StoredPrint) -> crate::Result<()>,
+    ) -> crate::Result<()> {
+        // Prints are collected first, so `f` can use the store.
+        for entry in self.entries()? {
+            f(entry)?;
+        }
+
+        Ok(())
+    }
+
+    fn entries(&self) -> crate::Result<Vec<StoredPrint>> {
+        let mut entries = self
+            .prints()
+            .iter()
+            .flat_map(|(slot, templates)| templates.iter().map(move |print| (slot, print)))
+            .map(|(slot, print)| {
+                Ok(StoredPrint {
+                    key: slot.0.clone(),
+                    slot: PrintSlot::Finger(slot.3),
+                    data: print.load()?,
+                    consent: print.consent.clone(),
+                })
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+
+        for (slot, print) in self.labeled().iter() {
+            entries.push(StoredPrint {
+                key: slot.0.clone(),
+                slot: PrintSlot::Label(slot.3.clone()),
+                data: print.load()?,
+                consent: print.consent.clone(),
+            });
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Erases biometric data of the user for erasure requests: prints saved under the `key` are
+/// securely deleted from the store and prints of the key are removed from the `gallery`.
+/// Returns number of prints removed from the store.
+///
+/// Audit log records are kept, as they can not be changed without breaking the chain.
+/// They contain only the user key and no biometric data.
+pub fn forget_user<K: Borrow<str>>(
+    store: &impl PrintStore,
+    gallery: &mut Gallery<K>,
+    key: &str,
+) -> crate::Result<usize> {
+    gallery.retain(|user, _| user.borrow() != key);
+
+    store.secure_delete(key)
+}
+
+/// Identifies the scanned finger among the `prints`, or verifies it against them one by one
+/// if identification is not possible.
+pub(crate) fn authenticate_prints(
+    device: &Device,
+    prints: Vec<(Finger, PrintData)>,
+) -> crate::Result<AuthDecision> {
+    if prints.is_empty() {
+        return Ok(AuthDecision::NotEnrolled);
+    }
+
+    if prints.len() == 1 || !device.supports_identification() {
+        for (finger, mut print) in prints {
+            match device.verify(&mut print)?.decision {
+                VerifyResult::Match => return Ok(AuthDecision::Accepted(finger)),
+                VerifyResult::NoMatch => continue,
+                reason => return Ok(AuthDecision::Inconclusive(reason)),
+            }
+        }
+
+        return Ok(AuthDecision::Rejected);
+    }
+
+    let mut raw: Vec<_> = prints
+        .iter()
+        .map(|(_, print)| print.as_raw())
+        .chain(std::iter::once(std::ptr::null_mut()))
+        .collect();
+
+    let policy = device.get_options().get_retry_policy();
+    let mut attempts = 0;
+    loop {
+        let result = device.identify_prints(raw.as_mut_ptr(), prints.len())?;
+        attempts += 1;
+
+        match result {
+            IdentifyResult::Matched(offset) => {
+                return Ok(AuthDecision::Accepted(prints[offset].0));
+            }
+            IdentifyResult::Error(VerifyResult::NoMatch) => return Ok(AuthDecision::Rejected),
+            IdentifyResult::Error(reason) if !policy.allows(attempts) => {
+                return Ok(AuthDecision::Inconclusive(reason))
+            }
+            _ => std::thread::sleep(policy.delay),
+        }
+    }
+}
+
+/// Writes the magic and the current format version of a bundle.
+fn write_bundle_header(writer: &mut impl Write) -> crate::Result<()> {
+    writer.write_all(BUNDLE_MAGIC)?;
+    writer.write_all(&[BUNDLE_VERSION])?;
+
+    Ok(())
+}
+
+/// Writes a serialized template to a bundle, prefixed with its length.
+fn write_bundle_entry(writer: &mut impl Write, bytes: &[u8]) -> crate::Result<()> {
+    writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    writer.write_all(bytes)?;
+
+    Ok(())
+}
+
+/// Reads the magic and the format version of a bundle, rejecting versions newer than
+/// `BUNDLE_VERSION`.
+fn read_bundle_header(reader: &mut impl Read) -> crate::Result<()> {
+    let mut header = [0; 5];
+    reader.read_exact(&mut header)?;
+    if &header[..4] != BUNDLE_MAGIC {
+        return Err(crate::FPrintError::InvalidTemplate("not a print bundle"));
+    }
+    if header[4] == 0 || header[4] > BUNDLE_VERSION {
+        return Err(crate::FPrintError::InvalidTemplate(
+            "unknown bundle version",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Reads the next serialized template of a bundle, `None` at the end of the bundle.
+fn read_bundle_entry(reader: &mut impl Read) -> crate::Result<Option<Zeroizing<Vec<u8>>>> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_BUNDLE_ENTRY {
+        return Err(crate::FPrintError::InvalidTemplate(
+            "bundle entry is too large",
+        ));
+    }
+
+    let mut bytes = Zeroizing::new(Vec::with_capacity(len));
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(crate::FPrintError::InvalidTemplate("data is truncated"));
+    }
+
+    Ok(Some(bytes))
+}
+
+fn data_slot(key: &str, finger: Finger, data: &PrintData) -> (String, u16, u32, Finger) {
+    (
+        key.to_string(),
+        data.get_driver_id(),
+        data.get_devtype(),
+        finger,
+    )
+}
+
+fn slot(key: &str, device: &Device, finger: Finger) -> (String, u16, u32, Finger) {
+    (
+        key.to_string(),
+        device.get_driver_info().driver_id,
+        device.get_dev_type(),
+        finger,
+    )
+}
+
+fn label_slot(key: &str, device: &Device, label: &str) -> (String, u16, u32, String) {
+    (
+        key.to_string(),
+        device.get_driver_info().driver_id,
+        device.get_dev_type(),
+        label.to_string(),
+    )
+}
+
+pub(crate) fn device_dir(driver_id: u16, devtype: u32) -> PathBuf {
+    Path::new(&format!("{:04x}", driver_id)).join(format!("{:08x}", devtype))
+}
+
+pub(crate) fn finger_file(finger: Finger) -> String {
+    format!("{:x}", finger as u32)
+}
+
+/// Directory where libfprint keeps prints saved with `PrintData::save_to_disk()`.
+fn libfprint_storage_dir() -> crate::Result<PathBuf> {
+    std::env::var_os("HOME")
+        .map(|home| Path::new(&home).join(".fprint").join("prints"))
+        .ok_or(crate::FPrintError::PathNotExists)
+}
+
+/// Removes prints of the device type from the libfprint storage, overwriting them before
+/// unlinking. Returns number of removed prints.
+pub(crate) fn delete_saved_prints(driver_id: u16, devtype: u32) -> crate::Result<usize> {
+    let dir = libfprint_storage_dir()?.join(device_dir(driver_id, devtype));
+
+    let mut removed = 0;
+    for finger in read_fingers(&dir)? {
+        match remove_print(&dir.join(finger_file(finger)), finger) {
+            Ok(()) => removed += 1,
+            Err(crate::FPrintError::FingerprintNotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Writes the print into a temporary file which then replaces the print file, so the file
+/// always contains a whole print.
+pub(crate) fn write_print(
+    dir: &Path,
+    name: &str,
+    data: &PrintData,
+    durability: Durability,
+) -> crate::Result<()> {
+    write_file(dir, name, &data.as_bytes()?, durability)
+}
+
+/// Saves the consent next to the print file `name`. Empty consent removes the consent
+/// of a print previously saved there.
+fn write_consent(
+    dir: &Path,
+    name: &str,
+    consent: &Consent,
+    durability: Durability,
+) -> crate::Result<()> {
+    if *consent == Consent::default() {
+        return Ok(remove_consent(&dir.join(name))?);
+    }
+
+    let mut bytes = Vec::new();
+    consent.encode(&mut bytes)?;
+    write_file(
+        dir,
+        &format!("{}.{}", name, CONSENT_EXTENSION),
+        &bytes,
+        durability,
+    )
+}
+
+/// Writes the file through a temporary file, see `write_print()`.
+fn write_file(dir: &Path, name: &str, bytes: &[u8], durability: Durability) -> crate::Result<()> {
+    DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+
+    let temp = dir.join(format!(".{}.tmp", name));
+    let written = write_temp(&temp, bytes, durability).and_then(|()| {
+        fs::rename(&temp, dir.join(name))?;
+        Ok(())
+    });
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written?;
+
+    if durability == Durability::Synced {
+        File::open(dir)?.sync_all()?;
+    }
+
+    Ok(())
+}
+
+fn write_temp(path: &Path, bytes: &[u8], durability: Durability) -> crate::Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(bytes)?;
+
+    if durability == Durability::Synced {
+        file.sync_all()?;
+    }
+
+    Ok(())
+}
+
+/// Reads the print file of the finger.
+pub(crate) fn read_print(path: &Path, finger: Finger) -> crate::Result<PrintData> {
+    match fs::read(path) {
+        Ok(bytes) => PrintData::from_bytes(TemplateBytes::from(bytes)),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            Err(crate::FPrintError::FingerprintNotFound(finger))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Lists files of templates added to the finger with `PrintStore::add()`, in the order they
+/// were added.
+fn added_templates(dir: &Path, finger: Finger) -> crate::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let prefix = format!("{}.", finger_file(finger));
+    let mut templates = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let number = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.strip_prefix(&prefix))
+            .and_then(|number| number.parse::<u32>().ok());
+
+        if let Some(number) = number {
+            templates.push((number, entry.path()));
+        }
+    }
+    templates.sort();
+
+    Ok(templates.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Lists the print file of the finger and files of the templates added to it.
+fn template_files(dir: &Path, finger: Finger) -> crate::Result<Vec<PathBuf>> {
+    let mut files = vec![dir.join(finger_file(finger))];
+    files.extend(added_templates(dir, finger)?);
+
+    Ok(files)
+}
+
+/// Name of the file for a template added to the finger which is not taken yet.
+fn added_template_file(dir: &Path, finger: Finger) -> crate::Result<String> {
+    let mut number = added_templates(dir, finger)?.len() + 1;
+    loop {
+        let name = format!("{}.{}", finger_file(finger), number);
+        if !dir.join(&name).exists() {
+            return Ok(name);
+        }
+        number += 1;
+    }
+}
+
+/// Reads the print file of the finger and the templates added to it.
+fn load_templates(dir: &Path, finger: Finger) -> crate::Result<Vec<PrintData>> {
+    template_files(dir, finger)?
+        .iter()
+        .map(|path| read_print(path, finger))
+        .collect()
+}
+
+/// Reads the consent saved with the print file, empty if there is none.
+fn read_consent(path: &Path) -> crate::Result<Consent> {
+    match fs::read(consent_file(path)) {
+        Ok(bytes) => Consent::from_bytes(&bytes),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Consent::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Overwrites and removes the consent saved with the print file, if there is one.
+fn remove_consent(path: &Path) -> io::Result<()> {
+    let path = consent_file(path);
+    match wipe_file(&path).and_then(|()| fs::remove_file(&path)) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+fn consent_file(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(CONSENT_EXTENSION);
+
+    PathBuf::from(name)
+}
+
+/// Overwrites and removes the print file of the finger and its consent.
+pub(crate) fn remove_print(path: &Path, finger: Finger) -> crate::Result<()> {
+    let removed = wipe_file(path)
+        .and_then(|()| fs::remove_file(path))
+        .and_then(|()| remove_consent(path));
+    match removed {
+        Ok(()) => Ok(()),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            Err(crate::FPrintError::FingerprintNotFound(finger))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Reads the labeled print saved with `write_print()`.
+pub(crate) fn read_label(path: &Path, label: &str) -> crate::Result<PrintData> {
+    match fs::read(path) {
+        Ok(bytes) => PrintData::from_bytes(TemplateBytes::from(bytes)),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            Err(crate::FPrintError::LabelNotFound(label.to_string()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Overwrites and removes the labeled print file and its consent.
+pub(crate) fn remove_label(path: &Path, label: &str) -> crate::Result<()> {
+    let removed = wipe_file(path)
+        .and_then(|()| fs::remove_file(path))
+        .and_then(|()| remove_consent(path));
+    match removed {
+        Ok(()) => Ok(()),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            Err(crate::FPrintError::LabelNotFound(label.to_string()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Lists labels of the print files in the labels directory, sorted.
+pub(crate) fn read_labels(dir: &Path) -> crate::Result<Vec<String>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut labels = Vec::new();
+    for entry in entries {
+        let name = entry?.file_name();
+        // Labels are encoded without dots. Hidden files are temporary files of interrupted
+        // writes, files with extensions hold consents.
+        let name = name.to_str().filter(|name| !name.contains('.'));
+        if let Some(label) = name.and_then(parse_label_file) {
+            labels.push(label);
+        }
+    }
+    labels.sort();
+
+    Ok(labels)
+}
+
+fn check_label(label: &str) -> crate::Result<()> {
+    if label.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Label is empty").into());
+    }
+
+    Ok(())
+}
+
+/// File name of the labeled print. Bytes other than ASCII letters, digits, `-` and `_` are
+/// percent-encoded, so any non-empty label maps to a single file name.
+pub(crate) fn label_file(label: &str) -> crate::Result<String> {
+    check_label(label)?;
+
+    let mut name = String::with_capacity(label.len());
+    for byte in label.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+            name.push(byte as char);
+        } else {
+            name.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    Ok(name)
+}
+
+fn parse_label_file(name: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut rest = name.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let code = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(code, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+/// Lists fingers of the print files in the device type directory.
+pub(crate) fn read_fingers(dir: &Path) -> crate::Result<Vec<Finger>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut fingers = Vec::new();
+    for entry in entries {
+        let name = entry?.file_name();
+        let finger = name
+            .to_str()
+            .and_then(|name| u32::from_str_radix(name, 16).ok())
+            .and_then(|value| Finger::try_from(value).ok());
+
+        if let Some(finger) = finger {
+            fingers.push(finger);
+        }
+    }
+
+    Ok(fingers)
+}
+
+/// Checks whether there is any file at least `depth` directories below `dir`.
+pub(crate) fn has_prints(dir: &Path, depth: usize) -> io::Result<bool> {
+    if !dir.is_dir() {
+        return Ok(false);
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let found = if depth == 0 {
+            path.is_file() || has_prints(&path, 0)?
+        } else {
+            has_prints(&path, depth - 1)?
+        };
+
+        if found {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Overwrites all files below `dir` and returns number of print files among them.
+fn wipe_dir(dir: &Path) -> io::Result<usize> {
+    let mut wiped = 0;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            wiped += wipe_dir(&path)?;
+        } else {
+            wipe_file(&path)?;
+            if path.extension() != Some(CONSENT_EXTENSION.as_ref()) {
+                wiped += 1;
+            }
+        }
+    }
+
+    Ok(wiped)
+}
+
+/// Overwrites the file with zeros and syncs it to disk.
+pub(crate) fn wipe_file(path: &Path) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let len = file.metadata()?.len();
+    io::copy(&mut io::repeat(0).take(len), &mut file)?;
+
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle(version: u8) -> Vec<u8> {
+        let mut bundle = BUNDLE_MAGIC.to_vec();
+        bundle.push(version);
+        bundle
+    }
+
+    fn is_invalid<T>(result: crate::Result<T>, reason: &str) -> bool {
+        matches!(result, Err(crate::FPrintError::InvalidTemplate(r)) if r == reason)
+    }
+
+    /// Print of the `FP2` format with the devtype and the minutiae `data`.
+    fn print(devtype: u32, data: &[u8]) -> PrintData {
+        let mut bytes = b"FP2".to_vec();
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&devtype.to_le_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(data);
+        PrintData::from_bytes(bytes).unwrap()
+    }
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let name = format!("fprint-rs-{}-{}", std::process::id(), name);
+            let dir = TempDir(std::env::temp_dir().join(name));
+            let _ = fs::remove_dir_all(&dir.0);
+            dir
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Entries of the store in a comparable form, sorted by key and print data.
+    fn contents(store: &impl PrintStore) -> Vec<(String, PrintSlot, Vec<u8>, Consent)> {
+        let mut contents: Vec<_> = store
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|entry| {
+                (
+                    entry.key,
+                    entry.slot,
+                    entry.data.as_bytes().unwrap().to_vec(),
+                    entry.consent,
+                )
+            })
+            .collect();
+        // Print data differs between entries of the tests.
+        contents.sort_by(|a, b| (&a.0, &a.2).cmp(&(&b.0, &b.2)));
+        contents
+    }
+
+    fn consent(operator_id: &str) -> Consent {
+        Consent {
+            policy_version: Some("v2".to_string()),
+            timestamp: Some(1_700_000_000),
+            operator_id: Some(operator_id.to_string()),
+        }
+    }
+
+    fn fill(store: &impl PrintStore) {
+        store
+            .save("alice", Finger::RightIndex, &print(2, b"first"))
+            .unwrap();
+        store
+            .add_with_consent(
+                "alice",
+                Finger::RightIndex,
+                &print(2, b"second"),
+                &consent("kiosk"),
+            )
+            .unwrap();
+        store
+            .save("alice", Finger::LeftThumb, &print(3, b"thumb"))
+            .unwrap();
+        store
+            .save_label_with_consent("alice", "badge", &print(2, b"badge"), &consent("desk"))
+            .unwrap();
+        store
+            .save("bob", Finger::RightIndex, &print(2, b"bob"))
+            .unwrap();
+    }
+
+    #[test]
+    fn labels_map_to_file_names() {
+        for &label in &["badge-backup", "user42/right-index", "..", "метка", "a%b"] {
+            let name = label_file(label).unwrap();
+            assert!(!name.contains('/') && !name.starts_with('.'));
+            assert_eq!(parse_label_file(&name).as_deref(), Some(label));
+        }
+        assert_eq!(label_file("badge-backup").unwrap(), "badge-backup");
+        assert_eq!(label_file("user42/right").unwrap(), "user42%2Fright");
+        assert!(label_file("").is_err());
+        assert_eq!(parse_label_file("%2"), None);
+    }
+
+    #[test]
+    fn bundle_header_is_checked() {
+        assert!(read_bundle_header(&mut &bundle(BUNDLE_VERSION)[..]).is_ok());
+        assert!(is_invalid(
+            read_bundle_header(&mut &b"FPTM\x01"[..]),
+            "not a print bundle"
+        ));
+        for &version in &[0, BUNDLE_VERSION + 1] {
+            assert!(is_invalid(
+                read_bundle_header(&mut &bundle(version)[..]),
+                "unknown bundle version"
+            ));
+        }
+    }
+
+    #[test]
+    fn bundle_entries_are_read() {
+        let mut data = Vec::new();
+        for entry in &[&b"first"[..], b"", b"third"] {
+            data.extend_from_slice(&(entry.len() as u32).to_le_bytes());
+            data.extend_from_slice(entry);
+        }
+
+        let mut reader = &data[..];
+        assert_eq!(
+            &**read_bundle_entry(&mut reader).unwrap().unwrap(),
+            b"first"
+        );
+        assert_eq!(&**read_bundle_entry(&mut reader).unwrap().unwrap(), b"");
+        assert_eq!(
+            &**read_bundle_entry(&mut reader).unwrap().unwrap(),
+            b"third"
+        );
+        assert!(read_bundle_entry(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_bundle_entries_are_rejected() {
+        let too_large = (MAX_BUNDLE_ENTRY as u32 + 1).to_le_bytes();
+        assert!(is_invalid(
+            read_bundle_entry(&mut &too_large[..]),
+            "bundle entry is too large"
+        ));
+
+        let mut truncated = 8u32.to_le_bytes().to_vec();
+        truncated.extend_from_slice(b"FPTM");
+        assert!(is_invalid(
+            read_bundle_entry(&mut &truncated[..]),
+            "data is truncated"
+        ));
+    }
+
+    #[test]
+    fn written_bundle_is_read() {
+        let entries = [&b"first"[..], b"", &[0xff; 300][..]];
+        let mut data = Vec::new();
+        write_bundle_header(&mut data).unwrap();
+        for entry in &entries {
+            write_bundle_entry(&mut data, entry).unwrap();
+        }
+
+        let mut reader = &data[..];
+        read_bundle_header(&mut reader).unwrap();
+        for entry in &entries {
+            assert_eq!(&**read_bundle_entry(&mut reader).unwrap().unwrap(), *entry);
+        }
+        assert!(read_bundle_entry(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn exported_prints_are_imported() {
+        let dir = TempDir::new("export");
+        let disk = DiskStore::at(&dir.0).with_durability(Durability::Atomic);
+        let memory = MemoryStore::new();
+        fill(&memory);
+
+        let mut bundle = Vec::new();
+        assert_eq!(memory.export(&mut bundle).unwrap(), 5);
+        assert_eq!(disk.import(&bundle[..]).unwrap(), 5);
+        assert_eq!(contents(&disk), contents(&memory));
+        assert!(contents(&disk).contains(&(
+            "alice".to_string(),
+            PrintSlot::Label("badge".to_string()),
+            print(2, b"badge").as_bytes().unwrap().to_vec(),
+            consent("desk")
+        )));
+
+        let mut bundle = Vec::new();
+        assert_eq!(disk.export(&mut bundle).unwrap(), 5);
+        let imported = MemoryStore::new();
+        assert_eq!(imported.import(&bundle[..]).unwrap(), 5);
+        assert_eq!(contents(&imported), contents(&memory));
+    }
+
+    #[test]
+    fn consent_is_replaced_with_the_print() {
+        let dir = TempDir::new("consent");
+        let store = DiskStore::at(&dir.0).with_durability(Durability::Atomic);
+        let consent_file = dir.0.join("alice/0001/00000002/7.consent");
+
+        store
+            .save_with_consent(
+                "alice",
+                Finger::RightIndex,
+                &print(2, b"a"),
+                &consent("kiosk"),
+            )
+            .unwrap();
+        assert!(consent_file.exists());
+        assert_eq!(store.entries().unwrap()[0].consent, consent("kiosk"));
+
+        store
+            .save("alice", Finger::RightIndex, &print(2, b"b"))
+            .unwrap();
+        assert!(!consent_file.exists());
+        assert_eq!(store.entries().unwrap()[0].consent, Consent::default());
+    }
+
+    #[test]
+    fn secure_delete_removes_prints_of_the_key() {
+        let dir = TempDir::new("secure-delete");
+        let disk = DiskStore::at(&dir.0).with_durability(Durability::Atomic);
+        let memory = MemoryStore::new();
+        fill(&disk);
+        fill(&memory);
+
+        assert_eq!(disk.secure_delete("alice").unwrap(), 4);
+        assert_eq!(memory.secure_delete("alice").unwrap(), 4);
+        assert!(!dir.0.join("alice").exists());
+        assert_eq!(disk.keys().unwrap(), ["bob"]);
+        assert_eq!(memory.keys().unwrap(), ["bob"]);
+        assert_eq!(disk.secure_delete("alice").unwrap(), 0);
+        assert_eq!(memory.secure_delete("alice").unwrap(), 0);
+        assert!(disk.secure_delete("..").is_err());
+    }
+
+    #[test]
+    fn forget_user_clears_the_gallery() {
+        let store = MemoryStore::new();
+        fill(&store);
+        let mut gallery = Gallery::new();
+        for &key in &["alice", "bob", "alice"] {
+            gallery
+                .insert(key.to_string(), print(2, key.as_bytes()))
+                .unwrap();
+        }
+
+        assert_eq!(forget_user(&store, &mut gallery, "alice").unwrap(), 4);
+        assert_eq!(gallery.len(), 1);
+        assert_eq!(store.keys().unwrap(), ["bob"]);
+    }
+}
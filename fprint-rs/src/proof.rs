@@ -0,0 +1,218 @@
+//! Short-lived proof tokens of successful verification or identification.
+//!
+//! After a match the verifying process issues a token signed with HMAC-SHA256, and passes
+//! it to other services instead of a bare "it matched" message. Services sharing the key
+//! validate the token and get the user and the device it was issued for.
+//!
+//! Token is `<user>.<device>.<timestamp>.<mac>`, where user and device are hex encoded.
+
+use crate::{
+    util::{hex, hmac_sha256},
+    Device, IdentifyResult, VerifyResult,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+/// Default lifetime of issued tokens.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(30);
+
+/// Validated token.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ProofToken {
+    /// Key of the verified user.
+    pub user: String,
+    /// Id of the device used for verification, `<driver>:<devtype>`.
+    pub device: String,
+    /// Seconds since the Unix epoch.
+    pub issued: u64,
+}
+
+/// Issuer and validator of proof tokens.
+pub struct ProofIssuer {
+    key: Zeroizing<Vec<u8>>,
+    ttl: Duration,
+}
+
+impl ProofIssuer {
+    pub fn new(key: &[u8]) -> Self {
+        ProofIssuer {
+            key: Zeroizing::new(key.to_vec()),
+            ttl: DEFAULT_TTL,
+        }
+    }
+
+    /// Sets lifetime of the tokens.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Issues token for the `user` if the verification `result` is a match.
+    pub fn issue(
+        &self,
+        device: &Device,
+        user: &str,
+        result: &VerifyResult,
+    ) -> crate::Result<Option<String>> {
+        match result {
+            VerifyResult::Match => self.sign(user, &device_id(device), now()).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Issues token for the `user` owning the matched print if the identification `result`
+    /// is a match.
+    pub fn issue_identified(
+        &self,
+        device: &Device,
+        user: &str,
+        result: &IdentifyResult,
+    ) -> crate::Result<Option<String>> {
+        match result {
+            IdentifyResult::Matched(_) => self.sign(user, &device_id(device), now()).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Checks signature and lifetime of the token.
+    pub fn validate(&self, token: &str) -> crate::Result<ProofToken> {
+        let invalid = || crate::FPrintError::InvalidProofToken;
+
+        let parts = token.split('.').collect::<Vec<_>>();
+        let (user, device, issued, mac) = match parts.as_slice() {
+            [user, device, issued, mac] => (*user, *device, *issued, *mac),
+            _ => return Err(invalid()),
+        };
+
+        let payload = format!("{}.{}.{}", user, device, issued);
+        if !crate::constant_time_eq(mac.as_bytes(), self.mac(&payload)?.as_bytes()) {
+            return Err(invalid());
+        }
+
+        let issued = issued.parse::<u64>().map_err(|_| invalid())?;
+        let age = now().saturating_sub(issued);
+        if age > self.ttl.as_secs() || issued > now() + self.ttl.as_secs() {
+            return Err(crate::FPrintError::ProofTokenExpired);
+        }
+
+        Ok(ProofToken {
+            user: unhex(user).ok_or_else(invalid)?,
+            device: unhex(device).ok_or_else(invalid)?,
+            issued,
+        })
+    }
+
+    fn sign(&self, user: &str, device: &str, issued: u64) -> crate::Result<String> {
+        let payload = format!(
+            "{}.{}.{}",
+            hex(user.as_bytes()),
+            hex(device.as_bytes()),
+            issued
+        );
+        let mac = self.mac(&payload)?;
+
+        Ok(format!("{}.{}", payload, mac))
+    }
+
+    fn mac(&self, payload: &str) -> crate::Result<String> {
+        hmac_sha256(&self.key, payload.as_bytes())
+    }
+}
+
+fn device_id(device: &Device) -> String {
+    format!(
+        "{}:{:08x}",
+        &device.get_driver_info().name,
+        device.get_dev_type()
+    )
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+fn unhex(data: &str) -> Option<String> {
+    let bytes = (0..data.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(data.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEVICE: &str = "upekts:00000001";
+
+    fn issuer() -> ProofIssuer {
+        ProofIssuer::new(b"proof key")
+    }
+
+    fn is_invalid(result: crate::Result<ProofToken>) -> bool {
+        matches!(result, Err(crate::FPrintError::InvalidProofToken))
+    }
+
+    fn is_expired(result: crate::Result<ProofToken>) -> bool {
+        matches!(result, Err(crate::FPrintError::ProofTokenExpired))
+    }
+
+    #[test]
+    fn signed_token_is_validated() {
+        let issued = now();
+        let token = issuer().sign("alice.smith", DEVICE, issued).unwrap();
+
+        assert_eq!(token.split('.').count(), 4);
+        assert_eq!(
+            issuer().validate(&token).unwrap(),
+            ProofToken {
+                user: "alice.smith".to_string(),
+                device: DEVICE.to_string(),
+                issued,
+            }
+        );
+    }
+
+    #[test]
+    fn forged_tokens_are_rejected() {
+        let token = issuer().sign("alice", DEVICE, now()).unwrap();
+        assert!(is_invalid(ProofIssuer::new(b"other key").validate(&token)));
+
+        let parts = token.split('.').collect::<Vec<_>>();
+        let other_user = format!("{}.{}.{}.{}", hex(b"bob"), parts[1], parts[2], parts[3]);
+        assert!(is_invalid(issuer().validate(&other_user)));
+        let later = format!("{}.{}.{}.{}", parts[0], parts[1], now() + 1, parts[3]);
+        assert!(is_invalid(issuer().validate(&later)));
+
+        assert!(is_invalid(issuer().validate("")));
+        assert!(is_invalid(issuer().validate(&parts[..3].join("."))));
+        assert!(is_invalid(issuer().validate(&format!("{}.", token))));
+    }
+
+    #[test]
+    fn expired_tokens_are_rejected() {
+        let ttl = DEFAULT_TTL.as_secs();
+        let old = issuer().sign("alice", DEVICE, now() - ttl - 5).unwrap();
+        assert!(is_expired(issuer().validate(&old)));
+        assert!(issuer()
+            .with_ttl(Duration::from_secs(ttl + 60))
+            .validate(&old)
+            .is_ok());
+
+        let future = issuer().sign("alice", DEVICE, now() + ttl + 5).unwrap();
+        assert!(is_expired(issuer().validate(&future)));
+    }
+
+    #[test]
+    fn hex_is_decoded() {
+        assert_eq!(unhex(&hex("метка".as_bytes())).as_deref(), Some("метка"));
+        assert_eq!(unhex("").as_deref(), Some(""));
+        assert_eq!(unhex("616"), None);
+        assert_eq!(unhex("zz"), None);
+        assert_eq!(unhex("ff"), None);
+    }
+}
@@ -0,0 +1,296 @@
+//! Python bindings.
+//!
+//! Exposes `Context`, `Device`, `EnrollSession` and `Gallery` classes as the `fprint` Python
+//! module. Build the extension with [maturin](https://github.com/PyO3/maturin):
+//!
+//! ```bash
+//! cd fprint-rs && maturin build --release
+//! ```
+//!
+//! ```python
+//! import fprint
+//!
+//! ctx = fprint.Context()
+//! device = ctx.open(0)
+//! template = device.enroll(lambda result: print(result))
+//! print(device.verify(template))
+//! ```
+//!
+//! The GIL is released while waiting for a finger, so other Python threads keep running
+//! during scans.
+
+use crate::{
+    DiscoveredDevices, EnrollResult, FPrint, FPrintError, Gallery, IdentifyResult, PrintData,
+    SyncDevice, TemplateBytes, VerifyResult,
+};
+use pyo3::{exceptions::PyRuntimeError, prelude::*, types::PyBytes};
+
+impl From<FPrintError> for PyErr {
+    fn from(e: FPrintError) -> Self {
+        PyRuntimeError::new_err(e.to_string())
+    }
+}
+
+/// libfprint context. Keeps libfprint initialised while any device opened from it is alive.
+#[pyclass(name = "Context", unsendable)]
+pub struct PyContext {
+    devices: DiscoveredDevices,
+    _fprint: FPrint,
+}
+
+#[pymethods]
+impl PyContext {
+    #[new]
+    fn new() -> PyResult<Self> {
+        let fprint = FPrint::new()?;
+
+        Ok(PyContext {
+            devices: fprint.discover(),
+            _fprint: fprint,
+        })
+    }
+
+    /// Opens discovered device by its index.
+    fn open(slf: Py<Self>, py: Python<'_>, index: usize) -> PyResult<PyDevice> {
+        let device = match slf.borrow(py).devices.get(index) {
+            Some(device) => device.open()?,
+            None => return Err(PyRuntimeError::new_err("Device not found")),
+        };
+
+        Ok(PyDevice {
+            device: SyncDevice::new(device),
+            _ctx: slf,
+        })
+    }
+}
+
+/// Opened fingerprint reader.
+#[pyclass(name = "Device", unsendable)]
+pub struct PyDevice {
+    device: SyncDevice,
+    _ctx: Py<PyContext>,
+}
+
+#[pymethods]
+impl PyDevice {
+    /// Number of enroll stages required to enroll a finger.
+    #[getter]
+    fn enroll_stages(&self) -> i32 {
+        self.device.lock().get_nr_enroll_stages()
+    }
+
+    /// Runs enrollment until the print is complete and returns serialized template.
+    /// `callback` receives the result name of every stage.
+    #[pyo3(signature = (callback=None))]
+    fn enroll<'py>(
+        &self,
+        py: Python<'py>,
+        callback: Option<Bound<'py, PyAny>>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let device = &self.device;
+        loop {
+            let (name, template) = py.detach(|| enroll_stage(device))?;
+            if let Some(callback) = &callback {
+                callback.call1((name,))?;
+            }
+
+            if let Some(template) = template {
+                return Ok(PyBytes::new(py, &template));
+            }
+        }
+    }
+
+    /// Scans a finger and verifies it against the serialized template. Scans are retried
+    /// until the result is known, `callback` receives the reason of every retry.
+    #[pyo3(signature = (template, callback=None))]
+    fn verify(
+        &self,
+        py: Python<'_>,
+        template: &[u8],
+        callback: Option<Bound<'_, PyAny>>,
+    ) -> PyResult<bool> {
+        let mut print = PrintData::from_bytes(template)?;
+        let device = &self.device;
+
+        loop {
+            match py.detach(|| device.lock().verify_finger_image(&mut print))? {
+                VerifyResult::Match => return Ok(true),
+                VerifyResult::NoMatch => return Ok(false),
+                retry => {
+                    if let Some(callback) = &callback {
+                        callback.call1((verify_result_name(&retry),))?;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Stage by stage enrollment, for applications which want to control the enrollment loop.
+#[pyclass(name = "EnrollSession", unsendable)]
+pub struct PyEnrollSession {
+    device: Py<PyDevice>,
+    passed: i32,
+    template: Option<TemplateBytes>,
+}
+
+#[pymethods]
+impl PyEnrollSession {
+    #[new]
+    fn new(device: Py<PyDevice>) -> Self {
+        PyEnrollSession {
+            device,
+            passed: 0,
+            template: None,
+        }
+    }
+
+    /// Performs the next enroll stage and returns its result name.
+    fn next_stage(&mut self, py: Python<'_>) -> PyResult<&'static str> {
+        let device = self.device.borrow(py).device.clone();
+        let (name, template) = py.detach(|| enroll_stage(&device))?;
+        match name {
+            "complete" => {
+                self.passed += 1;
+                self.template = template;
+            }
+            "pass" => self.passed += 1,
+            "fail" => self.passed = 0,
+            _ => {}
+        }
+
+        Ok(name)
+    }
+
+    /// Number of passed stages.
+    #[getter]
+    fn passed(&self) -> i32 {
+        self.passed
+    }
+
+    /// Number of stages required to complete enrollment.
+    #[getter]
+    fn total(&self, py: Python<'_>) -> i32 {
+        self.device.borrow(py).device.lock().get_nr_enroll_stages()
+    }
+
+    /// Serialized template, available when enrollment is completed.
+    #[getter]
+    fn template<'py>(&self, py: Python<'py>) -> Option<Bound<'py, PyBytes>> {
+        self.template.as_ref().map(|data| PyBytes::new(py, data))
+    }
+}
+
+/// Collection of serialized templates with keys of their owners.
+#[pyclass(name = "Gallery", unsendable)]
+#[derive(Default)]
+pub struct PyGallery {
+    prints: Gallery<Py<PyAny>>,
+}
+
+#[pymethods]
+impl PyGallery {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the template assigned to `key`.
+    fn add(&mut self, key: Py<PyAny>, template: Vec<u8>) -> PyResult<()> {
+        let template = TemplateBytes::from(template);
+        self.prints.insert_bytes(key, &*template)?;
+
+        Ok(())
+    }
+
+    /// Removes all templates assigned to `key` and returns their number.
+    fn remove(&mut self, py: Python<'_>, key: Py<PyAny>) -> usize {
+        let before = self.prints.len();
+        self.prints
+            .retain(|other, _| !other.bind(py).eq(key.bind(py)).unwrap_or(false));
+
+        before - self.prints.len()
+    }
+
+    fn __len__(&self) -> usize {
+        self.prints.len()
+    }
+
+    /// Scans a finger and returns the key of the matched template or `None`. Scans are
+    /// retried until the result is known, `callback` receives the reason of every retry.
+    #[pyo3(signature = (device, callback=None))]
+    fn identify(
+        &self,
+        py: Python<'_>,
+        device: PyRef<'_, PyDevice>,
+        callback: Option<Bound<'_, PyAny>>,
+    ) -> PyResult<Option<Py<PyAny>>> {
+        let (prints, device) = (&self.prints, &device.device);
+        loop {
+            // Only the index leaves the closure, matched prints can not be sent between threads.
+            let result = py.detach(|| {
+                device.lock().identify(prints).map(|result| match result {
+                    IdentifyResult::Matched(matched) => IdentifyResult::Matched(matched.index),
+                    IdentifyResult::Error(reason) => IdentifyResult::Error(reason),
+                })
+            })?;
+            match result {
+                IdentifyResult::Matched(index) => {
+                    return Ok(self.prints.get_key(index).map(|key| key.clone_ref(py)))
+                }
+                IdentifyResult::Error(VerifyResult::NoMatch) => return Ok(None),
+                IdentifyResult::Error(retry) => {
+                    if let Some(callback) = &callback {
+                        callback.call1((verify_result_name(&retry),))?;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Performs an enroll stage, returns the result name and the serialized template if enrollment
+/// is completed.
+fn enroll_stage(device: &SyncDevice) -> crate::Result<(&'static str, Option<TemplateBytes>)> {
+    let result = device.lock().enroll_finger_image()?;
+    let template = match &result {
+        EnrollResult::Complete(print, _) => Some(print.as_bytes()?),
+        _ => None,
+    };
+
+    Ok((enroll_result_name(&result), template))
+}
+
+fn enroll_result_name(result: &EnrollResult) -> &'static str {
+    match result {
+        EnrollResult::Complete(_, _) => "complete",
+        EnrollResult::Fail => "fail",
+        EnrollResult::Pass(_) => "pass",
+        EnrollResult::Retry => "retry",
+        EnrollResult::RetryTooShort => "retry_too_short",
+        EnrollResult::RetryCenterFinger => "retry_center_finger",
+        EnrollResult::RetryRemoveFinger => "retry_remove_finger",
+    }
+}
+
+fn verify_result_name(result: &VerifyResult) -> &'static str {
+    match result {
+        VerifyResult::NoMatch => "no_match",
+        VerifyResult::Match => "match",
+        VerifyResult::Retry => "retry",
+        VerifyResult::RetryTooShort => "retry_too_short",
+        VerifyResult::RetryCenterFinger => "retry_center_finger",
+        VerifyResult::RetryRemoveFinger => "retry_remove_finger",
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "fprint")]
+fn fprint_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyContext>()?;
+    m.add_class::<PyDevice>()?;
+    m.add_class::<PyEnrollSession>()?;
+    m.add_class::<PyGallery>()?;
+
+    Ok(())
+}
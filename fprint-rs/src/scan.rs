@@ -0,0 +1,184 @@
+//! Scans on top of the asynchronous libfprint API which can be stopped before a finger
+//! arrives. The calling thread drives libfprint events until the scan finishes.
+
+use crate::{CancelToken, Device, PrintData, RawImage, VerifyResult};
+use std::{
+    cell::Cell,
+    convert::TryFrom,
+    os::raw::{c_int, c_void},
+    ptr,
+    time::{Duration, Instant},
+};
+
+/// How long libfprint events are waited for before checking the interrupt.
+const EVENTS_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Conditions stopping a scan.
+#[derive(Default)]
+pub(crate) struct Interrupt<'a> {
+    pub cancel: Option<&'a CancelToken>,
+    pub deadline: Option<Instant>,
+}
+
+impl Interrupt<'_> {
+    fn check(&self) -> crate::Result<()> {
+        if let Some(cancel) = self.cancel {
+            cancel.check()?;
+        }
+
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(crate::FPrintError::TimedOut),
+            _ => Ok(()),
+        }
+    }
+
+    /// How long to wait for libfprint events before checking again.
+    fn events_timeout(&self) -> Duration {
+        match self.deadline {
+            Some(deadline) => {
+                EVENTS_TIMEOUT.min(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => EVENTS_TIMEOUT,
+        }
+    }
+}
+
+enum Kind {
+    Capture(bool),
+    Verify(*mut fprint_sys::fp_print_data),
+}
+
+/// State updated by libfprint callbacks.
+#[derive(Default)]
+struct Scan {
+    result: Cell<Option<(c_int, *mut fprint_sys::fp_img)>>,
+    stopped: Cell<bool>,
+}
+
+/// Captures an image, see `Device::capture_image()`.
+pub(crate) fn capture(
+    device: &Device,
+    unconditional: bool,
+    interrupt: &Interrupt,
+) -> crate::Result<RawImage> {
+    let (result, image) = run(device, Kind::Capture(unconditional), interrupt)?;
+
+    match result {
+        0 => Ok(unsafe { RawImage::from_raw(image) }),
+        _ if result == -libc::ENOTSUP => Err(crate::FPrintError::NotSupported(
+            crate::NotSupportContext::CapturingImage,
+        )),
+        _ => Err(crate::FPrintError::Other(result)),
+    }
+}
+
+/// Scans a finger and verifies it, see `Device::verify_finger_image()`.
+pub(crate) fn verify(
+    device: &Device,
+    print: &PrintData,
+    interrupt: &Interrupt,
+) -> crate::Result<VerifyResult> {
+    let result =
+        run(device, Kind::Verify(print.as_raw()), interrupt).and_then(|(result, image)| {
+            unsafe { fprint_sys::fp_img_free(image) };
+
+            if result < 0 {
+                Err(crate::FPrintError::VerifyFailed(result))
+            } else {
+                VerifyResult::try_from(result as u32)
+            }
+        });
+
+    #[cfg(feature = "metrics")]
+    if device.get_options().is_metrics_enabled() {
+        crate::metrics::record_verify(device, &result);
+    }
+
+    result
+}
+
+/// Starts the scan and handles events until it finishes or is interrupted. Returns the result
+/// code and the image reported by libfprint.
+fn run(
+    device: &Device,
+    kind: Kind,
+    interrupt: &Interrupt,
+) -> crate::Result<(c_int, *mut fprint_sys::fp_img)> {
+    interrupt.check()?;
+
+    // Callbacks update `scan` from `fp_handle_events_timeout()` of the thread holding
+    // the lock, it is held until the scan is stopped.
+    let _lock = crate::context::lock();
+    let device = device.as_raw();
+    let scan = Scan::default();
+    let data = &scan as *const Scan as *mut c_void;
+
+    let result = unsafe {
+        match kind {
+            Kind::Capture(unconditional) => fprint_sys::fp_async_capture_start(
+                device,
+                unconditional as c_int,
+                Some(scanned),
+                data,
+            ),
+            Kind::Verify(print) => {
+                fprint_sys::fp_async_verify_start(device, print, Some(scanned), data)
+            }
+        }
+    };
+    if result < 0 {
+        return Ok((result, ptr::null_mut()));
+    }
+
+    let finished = loop {
+        if let Some(result) = scan.result.get() {
+            break Ok(result);
+        }
+        if let Err(e) = interrupt.check() {
+            break Err(e);
+        }
+        handle_events(interrupt.events_timeout());
+    };
+
+    // libfprint requires stopping the operation even when it has finished.
+    let result = unsafe {
+        match kind {
+            Kind::Capture(_) => fprint_sys::fp_async_capture_stop(device, Some(stopped), data),
+            Kind::Verify(_) => fprint_sys::fp_async_verify_stop(device, Some(stopped), data),
+        }
+    };
+    if result < 0 {
+        scan.stopped.set(true);
+    }
+    while !scan.stopped.get() {
+        handle_events(EVENTS_TIMEOUT);
+    }
+
+    // Result reported after the interrupt is discarded.
+    if let (Err(_), Some((_, image))) = (&finished, scan.result.get()) {
+        unsafe { fprint_sys::fp_img_free(image) };
+    }
+
+    finished
+}
+
+fn handle_events(timeout: Duration) {
+    let mut timeout = fprint_sys::timeval {
+        tv_sec: 0,
+        tv_usec: timeout.as_micros() as _,
+    };
+    unsafe { fprint_sys::fp_handle_events_timeout(&mut timeout) };
+}
+
+unsafe extern "C" fn scanned(
+    _: *mut fprint_sys::fp_dev,
+    result: c_int,
+    image: *mut fprint_sys::fp_img,
+    data: *mut c_void,
+) {
+    (*(data as *const Scan)).result.set(Some((result, image)));
+}
+
+unsafe extern "C" fn stopped(_: *mut fprint_sys::fp_dev, data: *mut c_void) {
+    (*(data as *const Scan)).stopped.set(true);
+}
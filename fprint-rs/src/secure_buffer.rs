@@ -0,0 +1,268 @@
+use std::{
+    fmt, io,
+    ops::{Deref, Range},
+    os::raw::c_void,
+    ptr, slice,
+    sync::{Mutex, PoisonError},
+};
+use zeroize::Zeroize;
+
+/// Size of shared arenas in pages. Larger buffers get an arena of their own.
+const ARENA_PAGES: usize = 16;
+/// Buffers are placed in arenas at multiples of this.
+const ALIGN: usize = 16;
+
+/// Locked arenas buffers are allocated in.
+static ARENAS: Mutex<Vec<Arena>> = Mutex::new(Vec::new());
+
+/// Byte buffer for secret data, like serialized prints. Memory is allocated in shared arenas
+/// of pages locked in RAM, so it is never swapped, and excluded from core dumps. Buffers are
+/// wiped on drop.
+///
+/// Small buffers share locked pages, so the locked memory grows with the total size of
+/// the buffers, not with their number, and stores of many templates stay within
+/// `RLIMIT_MEMLOCK`.
+///
+/// `MemoryStore` keeps its prints in secure buffers. Prints loaded by libfprint, including
+/// prints of a `Gallery`, live in its own allocations, which are not locked.
+pub struct SecureBuffer {
+    ptr: *mut u8,
+    len: usize,
+    /// Size of the block reserved in the arena.
+    reserved: usize,
+}
+
+// The buffer owns its block and is never modified after creation, like a `Box<[u8]>`.
+unsafe impl Send for SecureBuffer {}
+unsafe impl Sync for SecureBuffer {}
+
+impl SecureBuffer {
+    /// Copies `data` into locked memory. Fails when a new arena cannot be allocated or locked
+    /// (for example when `RLIMIT_MEMLOCK` is exceeded).
+    pub fn new(data: &[u8]) -> crate::Result<Self> {
+        let reserved = data.len().max(1).div_ceil(ALIGN) * ALIGN;
+
+        let mut arenas = ARENAS.lock().unwrap_or_else(PoisonError::into_inner);
+        let ptr = match arenas.iter_mut().find_map(|arena| arena.allocate(reserved)) {
+            Some(ptr) => ptr,
+            None => {
+                let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+                let size = reserved.div_ceil(page).max(ARENA_PAGES) * page;
+                let arena = Arena::new(size, reserved)?;
+                let ptr = arena.ptr;
+                arenas.push(arena);
+
+                ptr
+            }
+        };
+        drop(arenas);
+
+        unsafe { ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len()) };
+
+        Ok(SecureBuffer {
+            ptr,
+            len: data.len(),
+            reserved,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Deref for SecureBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl AsRef<[u8]> for SecureBuffer {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl PartialEq for SecureBuffer {
+    fn eq(&self, other: &Self) -> bool {
+        crate::constant_time_eq(self, other)
+    }
+}
+
+impl Eq for SecureBuffer {}
+
+impl fmt::Debug for SecureBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecureBuffer({} bytes)", self.len)
+    }
+}
+
+impl Drop for SecureBuffer {
+    fn drop(&mut self) {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.reserved).zeroize() };
+
+        let mut arenas = ARENAS.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(index) = arenas.iter().position(|arena| arena.contains(self.ptr)) {
+            arenas[index].release(self.ptr, self.reserved);
+            // One arena is kept for later buffers, so it is not mapped and locked again.
+            if arenas[index].is_unused() && arenas.len() > 1 {
+                arenas.swap_remove(index);
+            }
+        }
+    }
+}
+
+/// Pages locked in RAM, shared by buffers.
+struct Arena {
+    ptr: *mut u8,
+    size: usize,
+    /// Unused blocks sorted by offset, adjacent blocks are merged.
+    free: Vec<Range<usize>>,
+}
+
+// The arena owns its pages, access to it is serialized by `ARENAS`.
+unsafe impl Send for Arena {}
+
+impl Arena {
+    /// Maps and locks `size` bytes, the first `used` bytes are reserved for the caller.
+    fn new(size: usize, used: usize) -> io::Result<Self> {
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        if unsafe { libc::mlock(ptr, size) } != 0 {
+            let error = io::Error::last_os_error();
+            unsafe { libc::munmap(ptr, size) };
+
+            return Err(error);
+        }
+
+        #[cfg(target_os = "linux")]
+        unsafe {
+            libc::madvise(ptr, size, libc::MADV_DONTDUMP);
+        }
+
+        Ok(Arena {
+            ptr: ptr as *mut u8,
+            size,
+            free: Some(used..size)
+                .filter(|block| !block.is_empty())
+                .into_iter()
+                .collect(),
+        })
+    }
+
+    /// Reserves the first unused block of `size` bytes.
+    fn allocate(&mut self, size: usize) -> Option<*mut u8> {
+        let index = self.free.iter().position(|block| block.len() >= size)?;
+        let offset = self.free[index].start;
+        self.free[index].start += size;
+        if self.free[index].is_empty() {
+            self.free.remove(index);
+        }
+
+        Some(unsafe { self.ptr.add(offset) })
+    }
+
+    fn contains(&self, ptr: *mut u8) -> bool {
+        let start = self.ptr as usize;
+        (start..start + self.size).contains(&(ptr as usize))
+    }
+
+    /// Returns the block reserved by `Arena::allocate()`.
+    fn release(&mut self, ptr: *mut u8, size: usize) {
+        let start = ptr as usize - self.ptr as usize;
+        let index = self.free.partition_point(|block| block.start < start);
+        self.free.insert(index, start..start + size);
+
+        if index + 1 < self.free.len() && self.free[index].end == self.free[index + 1].start {
+            self.free[index].end = self.free.remove(index + 1).end;
+        }
+        if index > 0 && self.free[index - 1].end == self.free[index].start {
+            self.free[index - 1].end = self.free.remove(index).end;
+        }
+    }
+
+    fn is_unused(&self) -> bool {
+        self.free.len() == 1 && self.free[0] == (0..self.size)
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        // Buffers are wiped when they are dropped, the pages hold only zeros.
+        unsafe {
+            libc::munlock(self.ptr as *mut c_void, self.size);
+            libc::munmap(self.ptr as *mut c_void, self.size);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_are_reused_and_merged() {
+        let mut memory = [0u8; 64];
+        // The arena is forgotten at the end, so the memory is not unmapped.
+        let mut arena = Arena {
+            ptr: memory.as_mut_ptr(),
+            size: memory.len(),
+            free: Vec::new(),
+        };
+        arena.free.push(0..64);
+        let offset = |ptr: *mut u8| ptr as usize - memory.as_ptr() as usize;
+
+        let a = arena.allocate(16).unwrap();
+        let b = arena.allocate(32).unwrap();
+        let c = arena.allocate(16).unwrap();
+        assert_eq!((offset(a), offset(b), offset(c)), (0, 16, 48));
+        assert_eq!(arena.allocate(16), None);
+
+        arena.release(b, 32);
+        assert_eq!(arena.allocate(16), Some(b));
+        arena.release(b, 16);
+        arena.release(a, 16);
+        assert_eq!(arena.free.len(), 1);
+        assert_eq!(arena.free[0], 0..48);
+        assert!(!arena.is_unused());
+
+        arena.release(c, 16);
+        assert!(arena.is_unused());
+        std::mem::forget(arena);
+    }
+
+    #[test]
+    fn buffers_share_arenas() {
+        let data: Vec<_> = (0..200u8).map(|n| vec![n; 100 + usize::from(n)]).collect();
+        let buffers: Vec<_> = data
+            .iter()
+            .map(|data| SecureBuffer::new(data).unwrap())
+            .collect();
+        for (buffer, data) in buffers.iter().zip(&data) {
+            assert_eq!(**buffer, data[..]);
+        }
+
+        let total: usize = buffers.iter().map(|buffer| buffer.reserved).sum();
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let arenas = ARENAS.lock().unwrap();
+        assert!(arenas.len() <= total / (ARENA_PAGES * page) + 1);
+    }
+}
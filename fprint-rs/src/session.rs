@@ -0,0 +1,311 @@
+use crate::{CancelToken, Device, EnrollResult, PrintData, RetryPolicy};
+use crate::{UserGuidance, VerifyResult};
+use std::{
+    fmt::{self, Debug, Formatter},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often the cancel token is checked while waiting before a retry.
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Progress of the enrollment reported by `EnrollSession::next_stage()`.
+#[derive(Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum EnrollProgress {
+    /// Stage passed, `stage` out of `total` stages are done.
+    Passed { stage: u32, total: u32 },
+    /// Scan was not successful and the stage must be repeated. Contains one of `Retry`
+    /// enroll results with the reason.
+    Retry(EnrollResult),
+    /// Scans did not make up a print, enrollment starts over from the first stage.
+    Restarted,
+    /// Enrollment is complete.
+    Completed(PrintData),
+}
+
+/// Event of an enrollment or a verification, for frontends rendering progress, see
+/// `EnrollSession::on_event()` and `VerifySession::on_event()`. libfprint does not report
+/// the finger being put on or lifted off the sensor, only results of whole scans.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum FpEvent {
+    /// Scan of the stage `stage` out of `total` started, the user should put the finger on
+    /// the sensor. Verification has a single stage which is started for every attempt.
+    StageStarted { stage: u32, total: u32 },
+    /// Enroll stage passed, `stage` out of `total` stages are done.
+    StagePassed { stage: u32, total: u32 },
+    /// Scan was not successful and is repeated.
+    RetryNeeded(UserGuidance),
+    /// Enrollment failed and starts over from the first stage.
+    Restarted,
+    /// Enrollment or verification finished.
+    Completed,
+    /// Operation failed with the error.
+    Error(String),
+}
+
+/// Stage by stage enrollment on top of `Device::enroll_finger_image()`, for applications
+/// driving the enrollment loop themselves, e.g. to update UI between stages:
+///
+/// ```ignore
+/// let mut session = EnrollSession::new(&device);
+/// let print = loop {
+///     match session.next_stage()? {
+///         EnrollProgress::Completed(print) => break print,
+///         progress => println!("{:?}", progress),
+///     }
+/// };
+/// ```
+pub struct EnrollSession<'a> {
+    device: &'a Device,
+    passed: u32,
+    total: u32,
+    cancel: Option<CancelToken>,
+    on_event: Option<Box<dyn FnMut(FpEvent) + 'a>>,
+}
+
+impl<'a> EnrollSession<'a> {
+    pub fn new(device: &'a Device) -> Self {
+        EnrollSession {
+            device,
+            passed: 0,
+            total: device.get_nr_enroll_stages().max(0) as u32,
+            cancel: None,
+            on_event: None,
+        }
+    }
+
+    /// Sets callback called with events of every stage.
+    pub fn on_event(mut self, on_event: impl FnMut(FpEvent) + 'a) -> Self {
+        self.on_event = Some(Box::new(on_event));
+        self
+    }
+
+    /// Sets token aborting the enrollment. The token is checked before every stage, when it is
+    /// cancelled `next_stage()` returns `FPrintError::Cancelled` and the stage is not started.
+    /// Once the token is reset, the enrollment continues from the same stage.
+    pub fn cancel_token(mut self, cancel: CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Performs the next enroll stage. After `Completed` the next call starts a new enrollment.
+    pub fn next_stage(&mut self) -> crate::Result<EnrollProgress> {
+        if let Some(cancel) = &self.cancel {
+            cancel.check()?;
+        }
+
+        self.emit(FpEvent::StageStarted {
+            stage: self.passed + 1,
+            total: self.total,
+        });
+        let progress = self.scan_stage();
+
+        let event = match &progress {
+            Ok(EnrollProgress::Passed { stage, total }) => FpEvent::StagePassed {
+                stage: *stage,
+                total: *total,
+            },
+            Ok(EnrollProgress::Retry(result)) => FpEvent::RetryNeeded(result.into()),
+            Ok(EnrollProgress::Restarted) => FpEvent::Restarted,
+            Ok(EnrollProgress::Completed(_)) => FpEvent::Completed,
+            Err(e) => FpEvent::Error(e.to_string()),
+        };
+        self.emit(event);
+
+        progress
+    }
+
+    fn scan_stage(&mut self) -> crate::Result<EnrollProgress> {
+        let result = match self.device.enroll_finger_image() {
+            Ok(result) => result,
+            Err(e) => {
+                self.passed = 0;
+                return Err(e);
+            }
+        };
+
+        let progress = match result {
+            EnrollResult::Complete(print, _) => {
+                self.passed = 0;
+                EnrollProgress::Completed(print)
+            }
+            EnrollResult::Pass(_) => {
+                self.passed += 1;
+                EnrollProgress::Passed {
+                    stage: self.passed,
+                    total: self.total,
+                }
+            }
+            EnrollResult::Fail => {
+                self.passed = 0;
+                EnrollProgress::Restarted
+            }
+            retry => EnrollProgress::Retry(retry),
+        };
+
+        Ok(progress)
+    }
+
+    fn emit(&mut self, event: FpEvent) {
+        if let Some(on_event) = &mut self.on_event {
+            on_event(event);
+        }
+    }
+
+    /// Number of stages passed in the current enrollment.
+    pub fn get_passed(&self) -> u32 {
+        self.passed
+    }
+
+    /// Number of stages required to complete the enrollment.
+    pub fn get_total(&self) -> u32 {
+        self.total
+    }
+}
+
+impl Debug for EnrollSession<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("EnrollSession")
+            .field("device", &self.device)
+            .field("passed", &self.passed)
+            .field("total", &self.total)
+            .field("cancel", &self.cancel)
+            .finish()
+    }
+}
+
+/// Verification of a finger against an enrolled print, repeating unsuccessful scans
+/// according to the `RetryPolicy`:
+///
+/// ```ignore
+/// let mut session = VerifySession::new(device, print)
+///     .on_retry(|reason| println!("{}", UserGuidance::from(reason)));
+/// let matched = session.run()? == VerifyResult::Match;
+/// ```
+pub struct VerifySession {
+    device: Device,
+    print: PrintData,
+    policy: RetryPolicy,
+    on_retry: Option<Box<dyn FnMut(VerifyResult)>>,
+    on_event: Option<Box<dyn FnMut(FpEvent)>>,
+    cancel: Option<CancelToken>,
+}
+
+impl VerifySession {
+    /// Creates session using the retry policy of the device options.
+    pub fn new(device: Device, print: PrintData) -> Self {
+        VerifySession {
+            policy: device.get_options().get_retry_policy(),
+            device,
+            print,
+            on_retry: None,
+            on_event: None,
+            cancel: None,
+        }
+    }
+
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets callback called with the reason of every unsuccessful scan which is retried.
+    pub fn on_retry(mut self, on_retry: impl FnMut(VerifyResult) + 'static) -> Self {
+        self.on_retry = Some(Box::new(on_retry));
+        self
+    }
+
+    /// Sets callback called with events of every attempt.
+    pub fn on_event(mut self, on_event: impl FnMut(FpEvent) + 'static) -> Self {
+        self.on_event = Some(Box::new(on_event));
+        self
+    }
+
+    /// Sets token aborting the verification with `FPrintError::Cancelled`. Scans are stopped
+    /// while waiting for a finger.
+    pub fn cancel_token(mut self, cancel: CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Scans fingers until the result is `Match` or `NoMatch`. When the policy allows
+    /// no more attempts, the last `Retry` result is returned.
+    pub fn run(&mut self) -> crate::Result<VerifyResult> {
+        let result = self.attempt();
+
+        let event = match &result {
+            Ok(_) => FpEvent::Completed,
+            Err(e) => FpEvent::Error(e.to_string()),
+        };
+        self.emit(event);
+
+        result
+    }
+
+    fn attempt(&mut self) -> crate::Result<VerifyResult> {
+        let mut attempts = 0;
+
+        loop {
+            self.emit(FpEvent::StageStarted { stage: 1, total: 1 });
+            let result = match &self.cancel {
+                Some(cancel) => self
+                    .device
+                    .verify_finger_image_cancellable(&self.print, cancel)?,
+                None => self.device.verify_finger_image(&mut self.print)?,
+            };
+            attempts += 1;
+
+            match result {
+                VerifyResult::Match | VerifyResult::NoMatch => return Ok(result),
+                _ if !self.policy.allows(attempts) => return Ok(result),
+                reason => {
+                    if let Some(on_retry) = &mut self.on_retry {
+                        on_retry(reason);
+                    }
+                    self.emit(FpEvent::RetryNeeded(reason.into()));
+                    self.wait(self.policy.delay)?;
+                }
+            }
+        }
+    }
+
+    fn emit(&mut self, event: FpEvent) {
+        if let Some(on_event) = &mut self.on_event {
+            on_event(event);
+        }
+    }
+
+    /// Sleeps for `delay`, returning early if the verification is cancelled.
+    fn wait(&self, delay: Duration) -> crate::Result<()> {
+        let cancel = match &self.cancel {
+            Some(cancel) => cancel,
+            None => {
+                thread::sleep(delay);
+                return Ok(());
+            }
+        };
+
+        let deadline = Instant::now() + delay;
+        loop {
+            cancel.check()?;
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(());
+            }
+            thread::sleep(CANCEL_CHECK_INTERVAL.min(deadline - now));
+        }
+    }
+
+    pub fn get_device(&self) -> &Device {
+        &self.device
+    }
+
+    /// Returns the device and the print back.
+    pub fn into_inner(self) -> (Device, PrintData) {
+        (self.device, self.print)
+    }
+}
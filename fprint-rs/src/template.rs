@@ -0,0 +1,479 @@
+use crate::{Finger, PrintData, TemplateBytes};
+use std::{
+    convert::TryFrom,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use zeroize::Zeroizing;
+
+/// Leading bytes of serialized templates.
+const MAGIC: &[u8; 4] = b"FPTM";
+/// Version of the header written by `Template::to_bytes()`.
+pub const TEMPLATE_VERSION: u8 = 3;
+/// Size of the fixed fields of the header: finger, driver id, devtype, enrollment time
+/// and length of the username.
+const FIXED_HEADER_LEN: usize = 1 + 2 + 4 + 8 + 2;
+
+/// Print with information about its enrollment, so stored prints are self-describing.
+///
+/// Serialized template is `FPTM`, the format version, length of the header (`u16`), the header
+/// and the print data. Integers are little-endian. Newer versions only append fields to
+/// the header, so parsers skip fields they do not know. Version 2 appends the label,
+/// labeled templates have 0 as the finger. Version 3 appends the consent.
+#[derive(Debug)]
+pub struct Template {
+    header: Header,
+    print: PrintData,
+}
+
+/// Fields of the template besides the print.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Header {
+    finger: Option<Finger>,
+    driver_id: u16,
+    devtype: u32,
+    enrolled_at: SystemTime,
+    username: Option<String>,
+    label: Option<String>,
+    consent: Consent,
+}
+
+/// Consent given by the user for holding the print, proving lawful basis for processing.
+/// It is kept with the print by the stores and included in exports.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Consent {
+    /// Version of the policy the user agreed to.
+    pub policy_version: Option<String>,
+    /// Seconds since the Unix epoch when the consent was given.
+    pub timestamp: Option<i64>,
+    /// Id of the operator who collected the consent.
+    pub operator_id: Option<String>,
+}
+
+impl Template {
+    /// Wraps the print enrolled now. Driver id and devtype are taken from the print.
+    pub fn new(print: PrintData, finger: Finger) -> Self {
+        Self::with_slot(print, Some(finger), None)
+    }
+
+    /// Wraps the print enrolled now and saved under the `label` instead of a finger,
+    /// see `PrintStore::save_label()`.
+    pub fn labeled(print: PrintData, label: impl Into<String>) -> Self {
+        Self::with_slot(print, None, Some(label.into()))
+    }
+
+    fn with_slot(print: PrintData, finger: Option<Finger>, label: Option<String>) -> Self {
+        Template {
+            header: Header {
+                finger,
+                driver_id: print.get_driver_id(),
+                devtype: print.get_devtype(),
+                enrolled_at: SystemTime::now(),
+                username: None,
+                label,
+                consent: Consent::default(),
+            },
+            print,
+        }
+    }
+
+    /// Sets time of the enrollment. It is stored with precision of seconds.
+    pub fn with_enrolled_at(mut self, enrolled_at: SystemTime) -> Self {
+        self.header.enrolled_at = enrolled_at;
+        self
+    }
+
+    /// Sets name of the user owning the print.
+    pub fn with_username(mut self, username: impl Into<String>) -> Self {
+        self.header.username = Some(username.into());
+        self
+    }
+
+    /// Sets consent given for holding the print.
+    pub fn with_consent(mut self, consent: Consent) -> Self {
+        self.header.consent = consent;
+        self
+    }
+
+    /// Finger of the print, `None` for labeled templates.
+    pub fn get_finger(&self) -> Option<Finger> {
+        self.header.finger
+    }
+
+    /// Label of the print, `None` for templates of fingers.
+    pub fn get_label(&self) -> Option<&str> {
+        self.header.label.as_deref()
+    }
+
+    pub fn get_driver_id(&self) -> u16 {
+        self.header.driver_id
+    }
+
+    pub fn get_devtype(&self) -> u32 {
+        self.header.devtype
+    }
+
+    pub fn get_enrolled_at(&self) -> SystemTime {
+        self.header.enrolled_at
+    }
+
+    pub fn get_username(&self) -> Option<&str> {
+        self.header.username.as_deref()
+    }
+
+    pub fn get_consent(&self) -> &Consent {
+        &self.header.consent
+    }
+
+    pub fn get_print(&self) -> &PrintData {
+        &self.print
+    }
+
+    pub fn into_print(self) -> PrintData {
+        self.print
+    }
+
+    /// Serializes the header and the print.
+    pub fn to_bytes(&self) -> crate::Result<TemplateBytes> {
+        self.header.encode(&self.print.as_bytes()?)
+    }
+
+    /// Parses a template serialized by `Template::to_bytes()` of this or a later version.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> crate::Result<Self> {
+        let (header, print) = Header::decode(bytes.as_ref())?;
+
+        Ok(Template {
+            header,
+            print: PrintData::from_bytes(print)?,
+        })
+    }
+}
+
+impl Header {
+    /// Serializes the header followed by the `print` data.
+    fn encode(&self, print: &[u8]) -> crate::Result<TemplateBytes> {
+        let enrolled_at = self
+            .enrolled_at
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        let mut header = Vec::with_capacity(FIXED_HEADER_LEN);
+        header.push(self.finger.map_or(0, |finger| finger as u8));
+        header.extend_from_slice(&self.driver_id.to_le_bytes());
+        header.extend_from_slice(&self.devtype.to_le_bytes());
+        header.extend_from_slice(&enrolled_at.to_le_bytes());
+        push_string(&mut header, &self.username, "username is too long")?;
+        push_string(&mut header, &self.label, "label is too long")?;
+        self.consent.encode(&mut header)?;
+        let header_len = u16::try_from(header.len())
+            .map_err(|_| crate::FPrintError::InvalidTemplate("header is too long"))?;
+
+        let mut bytes = Zeroizing::new(Vec::with_capacity(
+            MAGIC.len() + 3 + header.len() + print.len(),
+        ));
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(TEMPLATE_VERSION);
+        bytes.extend_from_slice(&header_len.to_le_bytes());
+        bytes.extend_from_slice(&header);
+        bytes.extend_from_slice(print);
+
+        Ok(TemplateBytes::from(std::mem::take(&mut *bytes)))
+    }
+
+    /// Parses the header and returns it with the print data following it.
+    fn decode(bytes: &[u8]) -> crate::Result<(Header, &[u8])> {
+        let mut reader = Reader(bytes);
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(crate::FPrintError::InvalidTemplate("not a template"));
+        }
+        if reader.u8()? == 0 {
+            return Err(crate::FPrintError::InvalidTemplate("unknown version"));
+        }
+
+        let header_len = usize::from(reader.u16()?);
+        if header_len < FIXED_HEADER_LEN {
+            return Err(crate::FPrintError::InvalidTemplate("header is too short"));
+        }
+        let mut header = Reader(reader.take(header_len)?);
+
+        let finger = match header.u8()? {
+            0 => None,
+            finger => Some(
+                Finger::try_from(u32::from(finger))
+                    .map_err(|_| crate::FPrintError::InvalidTemplate("unknown finger"))?,
+            ),
+        };
+        let driver_id = header.u16()?;
+        let devtype = header.u32()?;
+        let enrolled_at = UNIX_EPOCH + Duration::from_secs(header.u64()?);
+        let username = header.string("username is not UTF-8")?;
+        // Version 1 ends with the username.
+        let label = match header.0 {
+            [] => None,
+            _ => header.string("label is not UTF-8")?,
+        };
+        // Version 2 ends with the label.
+        let consent = match header.0 {
+            [] => Consent::default(),
+            _ => Consent::decode(&mut header)?,
+        };
+        // Rest of the header holds fields of later versions.
+
+        if finger.is_none() && label.is_none() {
+            return Err(crate::FPrintError::InvalidTemplate("unknown finger"));
+        }
+
+        let header = Header {
+            finger,
+            driver_id,
+            devtype,
+            enrolled_at,
+            username,
+            label,
+            consent,
+        };
+
+        Ok((header, reader.0))
+    }
+}
+
+impl Consent {
+    /// Serializes the consent: the policy version, whether the timestamp is set (`u8`),
+    /// the timestamp (`i64`) and the operator id. Strings are prefixed with their length
+    /// (`u16`), unset strings are empty.
+    pub(crate) fn encode(&self, bytes: &mut Vec<u8>) -> crate::Result<()> {
+        push_string(bytes, &self.policy_version, "policy version is too long")?;
+        bytes.push(self.timestamp.is_some() as u8);
+        bytes.extend_from_slice(&self.timestamp.unwrap_or_default().to_le_bytes());
+        push_string(bytes, &self.operator_id, "operator id is too long")
+    }
+
+    /// Parses the consent serialized by `Consent::encode()`.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> crate::Result<Self> {
+        Self::decode(&mut Reader(bytes))
+    }
+
+    fn decode(reader: &mut Reader) -> crate::Result<Self> {
+        let policy_version = reader.string("policy version is not UTF-8")?;
+        let has_timestamp = reader.u8()? != 0;
+        let timestamp = reader.u64()? as i64;
+        let operator_id = reader.string("operator id is not UTF-8")?;
+
+        Ok(Consent {
+            policy_version,
+            timestamp: Some(timestamp).filter(|_| has_timestamp),
+            operator_id,
+        })
+    }
+}
+
+/// Writes the string prefixed with its length (`u16`), unset strings are written empty.
+fn push_string(
+    bytes: &mut Vec<u8>,
+    string: &Option<String>,
+    too_long: &'static str,
+) -> crate::Result<()> {
+    let string = string.as_deref().unwrap_or_default().as_bytes();
+    let len =
+        u16::try_from(string.len()).map_err(|_| crate::FPrintError::InvalidTemplate(too_long))?;
+    bytes.extend_from_slice(&len.to_le_bytes());
+    bytes.extend_from_slice(string);
+
+    Ok(())
+}
+
+/// Cursor over the serialized template.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> crate::Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(crate::FPrintError::InvalidTemplate("data is truncated"));
+        }
+
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> crate::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> crate::Result<u16> {
+        let mut bytes = [0; 2];
+        bytes.copy_from_slice(self.take(2)?);
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    fn u32(&mut self) -> crate::Result<u32> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> crate::Result<u64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    /// Reads a string prefixed with its length (`u16`), `None` if it is empty.
+    fn string(&mut self, invalid: &'static str) -> crate::Result<Option<String>> {
+        let len = usize::from(self.u16()?);
+        match self.take(len)? {
+            [] => Ok(None),
+            bytes => String::from_utf8(bytes.to_vec())
+                .map(Some)
+                .map_err(|_| crate::FPrintError::InvalidTemplate(invalid)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(username: Option<&str>) -> Header {
+        Header {
+            finger: Some(Finger::RightIndex),
+            driver_id: 0x0102,
+            devtype: 0x0304_0506,
+            enrolled_at: UNIX_EPOCH + Duration::from_secs(1_600_000_000),
+            username: username.map(str::to_string),
+            label: None,
+            consent: Consent::default(),
+        }
+    }
+
+    fn consented() -> Header {
+        Header {
+            consent: Consent {
+                policy_version: Some("2024-05".to_string()),
+                timestamp: Some(-1),
+                operator_id: Some("operator".to_string()),
+            },
+            ..labeled("badge")
+        }
+    }
+
+    fn labeled(label: &str) -> Header {
+        Header {
+            finger: None,
+            label: Some(label.to_string()),
+            ..header(Some("alice"))
+        }
+    }
+
+    fn is_invalid(result: crate::Result<(Header, &[u8])>, reason: &str) -> bool {
+        matches!(result, Err(crate::FPrintError::InvalidTemplate(r)) if r == reason)
+    }
+
+    #[test]
+    fn header_round_trip() {
+        for header in [
+            header(None),
+            header(Some("alice")),
+            labeled("badge"),
+            consented(),
+        ] {
+            let bytes = header.encode(b"print").unwrap();
+
+            let (decoded, print) = Header::decode(&bytes).unwrap();
+            assert_eq!(decoded, header);
+            assert_eq!(print, b"print");
+        }
+    }
+
+    #[test]
+    fn version_1_header_is_read() {
+        // Version 1 header ends with the username, fields of later versions are cut off.
+        let mut bytes = header(Some("alice")).encode(b"print").unwrap().to_vec();
+        let header_len = usize::from(u16::from_le_bytes([bytes[5], bytes[6]]));
+        let v1_header_len = FIXED_HEADER_LEN + "alice".len();
+        let start = MAGIC.len() + 3;
+        bytes[4] = 1;
+        bytes[5..7].copy_from_slice(&(v1_header_len as u16).to_le_bytes());
+        bytes.drain(start + v1_header_len..start + header_len);
+
+        let (decoded, print) = Header::decode(&bytes).unwrap();
+        assert_eq!(decoded, header(Some("alice")));
+        assert_eq!(print, b"print");
+    }
+
+    #[test]
+    fn truncated_header_is_rejected() {
+        let bytes = header(Some("alice")).encode(b"").unwrap();
+
+        for len in 0..bytes.len() {
+            assert!(is_invalid(
+                Header::decode(&bytes[..len]),
+                "data is truncated"
+            ));
+        }
+        assert!(Header::decode(&bytes).is_ok());
+    }
+
+    #[test]
+    fn too_long_username_is_rejected() {
+        let empty_header_len = header(None).encode(b"").unwrap().len() - MAGIC.len() - 3;
+        let username = "a".repeat(usize::from(u16::MAX) - empty_header_len + 1);
+        let result = header(Some(&username)).encode(b"print");
+        assert!(matches!(
+            result,
+            Err(crate::FPrintError::InvalidTemplate("header is too long"))
+        ));
+
+        let username = "a".repeat(usize::from(u16::MAX) + 1);
+        let result = header(Some(&username)).encode(b"print");
+        assert!(matches!(
+            result,
+            Err(crate::FPrintError::InvalidTemplate("username is too long"))
+        ));
+
+        let username = "a".repeat(usize::from(u16::MAX) - empty_header_len);
+        let bytes = header(Some(&username)).encode(b"print").unwrap();
+        assert_eq!(Header::decode(&bytes).unwrap().0, header(Some(&username)));
+    }
+
+    #[test]
+    fn unknown_fields_are_skipped() {
+        // Later version appending 3 bytes of fields to the header.
+        let mut bytes = header(Some("alice")).encode(b"print").unwrap().to_vec();
+        let header_len = u16::from_le_bytes([bytes[5], bytes[6]]);
+        let end = MAGIC.len() + 3 + usize::from(header_len);
+        bytes[4] = TEMPLATE_VERSION + 1;
+        bytes[5..7].copy_from_slice(&(header_len + 3).to_le_bytes());
+        bytes.splice(end..end, vec![0xff; 3]);
+
+        let (decoded, print) = Header::decode(&bytes).unwrap();
+        assert_eq!(decoded, header(Some("alice")));
+        assert_eq!(print, b"print");
+    }
+
+    #[test]
+    fn invalid_headers_are_rejected() {
+        let bytes = header(None).encode(b"print").unwrap().to_vec();
+
+        let mut magic = bytes.clone();
+        magic[0] = b'X';
+        assert!(is_invalid(Header::decode(&magic), "not a template"));
+
+        let mut version = bytes.clone();
+        version[4] = 0;
+        assert!(is_invalid(Header::decode(&version), "unknown version"));
+
+        let mut short = bytes.clone();
+        short[5..7].copy_from_slice(&(FIXED_HEADER_LEN as u16 - 1).to_le_bytes());
+        assert!(is_invalid(Header::decode(&short), "header is too short"));
+
+        let mut finger = bytes.clone();
+        finger[7] = 11;
+        assert!(is_invalid(Header::decode(&finger), "unknown finger"));
+
+        let mut unlabeled = bytes;
+        unlabeled[7] = 0;
+        assert!(is_invalid(Header::decode(&unlabeled), "unknown finger"));
+    }
+}
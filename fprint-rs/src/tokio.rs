@@ -0,0 +1,26 @@
+//! Integration with the [tokio](https://tokio.rs) runtime.
+//!
+//! `TokioDevice` runs blocking operations of a `Device` on the blocking thread pool of the
+//! runtime with `spawn_blocking()`, so they can be awaited from async code without stalling
+//! its worker threads:
+//!
+//! ```ignore
+//! let device = TokioDevice::new(devices.get(0).unwrap().open()?);
+//!
+//! let mut progress = device.enroll();
+//! while let Some(progress) = progress.next().await {
+//!     match progress? {
+//!         EnrollProgress::Completed(print) => save(print.to_bytes()?),
+//!         progress => println!("{:?}", progress),
+//!     }
+//! }
+//!
+//! let outcome = device.verify(&template).await?;
+//! ```
+//!
+//! See `offload` module for other executors.
+
+pub use crate::offload::TokioSpawner;
+
+/// `Device` with operations running on the blocking thread pool of tokio.
+pub type TokioDevice = crate::offload::BlockingDevice<TokioSpawner>;
@@ -0,0 +1,148 @@
+use crate::{
+    Consent, Device, DiskStore, Durability, Finger, PrintData, PrintStore, StoredPrint,
+    FPRINTD_STORAGE_DIR,
+};
+use std::{io, path::PathBuf};
+
+/// Print storage keyed by system users. It is a `DiskStore` with user names as keys, so prints
+/// are saved under `<base>/<username>/<driver id>/<devtype>/<finger>` and can be shared with
+/// fprintd. Use `UidStore::key()` to get the key of a uid.
+///
+/// Unlike `PrintData::save_to_disk()`, prints are not tied to the home directory of the current
+/// user, which allows a privileged service to manage prints of every local user.
+#[derive(Debug, Clone)]
+pub struct UidStore {
+    store: DiskStore,
+}
+
+impl UidStore {
+    /// Creates store in the fprintd storage directory.
+    pub fn new() -> Self {
+        Self::at(FPRINTD_STORAGE_DIR)
+    }
+
+    /// Creates store in a custom base directory.
+    pub fn at(base: impl Into<PathBuf>) -> Self {
+        UidStore {
+            store: DiskStore::at(base),
+        }
+    }
+
+    /// Sets how print files are written, see `DiskStore::with_durability()`.
+    pub fn with_durability(mut self, durability: Durability) -> Self {
+        self.store = self.store.with_durability(durability);
+        self
+    }
+
+    /// Key of the user's prints, i.e. the user name. Returns `FPrintError::UnknownUser`
+    /// if there is no such user.
+    pub fn key(&self, uid: u32) -> crate::Result<String> {
+        let user = uzers::get_user_by_uid(uid).ok_or(crate::FPrintError::UnknownUser(uid))?;
+
+        user.name().to_str().map(str::to_string).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "User name is not UTF-8").into()
+        })
+    }
+
+    /// Lists uids of local users which have at least one enrolled print, regardless
+    /// of the device type, sorted.
+    pub fn enrolled_users(&self) -> crate::Result<Vec<u32>> {
+        let mut uids: Vec<_> = self
+            .store
+            .keys()?
+            .iter()
+            .filter_map(uzers::get_user_by_name)
+            .map(|user| user.uid())
+            .collect();
+        uids.sort_unstable();
+
+        Ok(uids)
+    }
+}
+
+impl Default for UidStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PrintStore for UidStore {
+    fn save_with_consent(
+        &self,
+        key: &str,
+        finger: Finger,
+        data: &PrintData,
+        consent: &Consent,
+    ) -> crate::Result<()> {
+        self.store.save_with_consent(key, finger, data, consent)
+    }
+
+    fn add_with_consent(
+        &self,
+        key: &str,
+        finger: Finger,
+        data: &PrintData,
+        consent: &Consent,
+    ) -> crate::Result<()> {
+        self.store.add_with_consent(key, finger, data, consent)
+    }
+
+    fn load(&self, key: &str, device: &Device, finger: Finger) -> crate::Result<PrintData> {
+        self.store.load(key, device, finger)
+    }
+
+    fn load_all(
+        &self,
+        key: &str,
+        device: &Device,
+        finger: Finger,
+    ) -> crate::Result<Vec<PrintData>> {
+        self.store.load_all(key, device, finger)
+    }
+
+    fn delete(&self, key: &str, device: &Device, finger: Finger) -> crate::Result<()> {
+        self.store.delete(key, device, finger)
+    }
+
+    fn list(&self, key: &str, device: &Device) -> crate::Result<Vec<Finger>> {
+        self.store.list(key, device)
+    }
+
+    fn save_label_with_consent(
+        &self,
+        key: &str,
+        label: &str,
+        data: &PrintData,
+        consent: &Consent,
+    ) -> crate::Result<()> {
+        self.store
+            .save_label_with_consent(key, label, data, consent)
+    }
+
+    fn load_label(&self, key: &str, device: &Device, label: &str) -> crate::Result<PrintData> {
+        self.store.load_label(key, device, label)
+    }
+
+    fn delete_label(&self, key: &str, device: &Device, label: &str) -> crate::Result<()> {
+        self.store.delete_label(key, device, label)
+    }
+
+    fn labels(&self, key: &str, device: &Device) -> crate::Result<Vec<String>> {
+        self.store.labels(key, device)
+    }
+
+    fn keys(&self) -> crate::Result<Vec<String>> {
+        self.store.keys()
+    }
+
+    fn secure_delete(&self, key: &str) -> crate::Result<usize> {
+        self.store.secure_delete(key)
+    }
+
+    fn for_each_entry(
+        &self,
+        f: &mut dyn FnMut(StoredPrint) -> crate::Result<()>,
+    ) -> crate::Result<()> {
+        self.store.for_each_entry(f)
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{Device, Finger, Gallery, PrintData, PrintStore};
+use std::{fmt::Display, marker::PhantomData, str::FromStr};
+
+/// Prints of users on top of a `PrintStore`. Users are identified by any id convertible
+/// to and from a string, which is used as the key of the store:
+///
+/// ```ignore
+/// let users = UserStore::<_, u64>::new(DiskStore::at("/var/lib/myapp/prints"));
+/// users.save(&42, Finger::RightIndex, &print)?;
+///
+/// let gallery = users.gallery(&device)?;
+/// if let IdentifyResult::Matched(matched) = device.identify(&gallery)? {
+///     let (user, finger) = matched.key;
+///     println!("Hello, user {} ({})!", user, finger);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct UserStore<S, U = String> {
+    store: S,
+    users: PhantomData<fn() -> U>,
+}
+
+impl<S: PrintStore, U: Display + FromStr + Clone> UserStore<S, U> {
+    pub fn new(store: S) -> Self {
+        UserStore {
+            store,
+            users: PhantomData,
+        }
+    }
+
+    pub fn get_store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    /// Saves the print of the user's finger, see `PrintStore::save()`.
+    pub fn save(&self, user: &U, finger: Finger, data: &PrintData) -> crate::Result<()> {
+        self.store.save(&user.to_string(), finger, data)
+    }
+
+    /// Saves another template of the user's finger, see `PrintStore::add()`.
+    pub fn add(&self, user: &U, finger: Finger, data: &PrintData) -> crate::Result<()> {
+        self.store.add(&user.to_string(), finger, data)
+    }
+
+    /// Loads the print of the user's finger, see `PrintStore::load()`.
+    pub fn load(&self, user: &U, device: &Device, finger: Finger) -> crate::Result<PrintData> {
+        self.store.load(&user.to_string(), device, finger)
+    }
+
+    /// Removes the print of the user's finger, see `PrintStore::delete()`.
+    pub fn delete(&self, user: &U, device: &Device, finger: Finger) -> crate::Result<()> {
+        self.store.delete(&user.to_string(), device, finger)
+    }
+
+    /// Removes all prints of the user with the device type of the `device`. Returns number
+    /// of removed fingers.
+    pub fn delete_user(&self, user: &U, device: &Device) -> crate::Result<usize> {
+        let key = user.to_string();
+        let fingers = self.store.list(&key, device)?;
+        for &finger in &fingers {
+            self.store.delete(&key, device, finger)?;
+        }
+
+        Ok(fingers.len())
+    }
+
+    /// Lists enrolled fingers of the user with the device type of the `device`.
+    pub fn fingers(&self, user: &U, device: &Device) -> crate::Result<Vec<Finger>> {
+        self.store.list(&user.to_string(), device)
+    }
+
+    /// Lists users with at least one saved print. Keys which are not valid ids are skipped.
+    pub fn users(&self) -> crate::Result<Vec<U>> {
+        Ok(self
+            .store
+            .keys()?
+            .iter()
+            .filter_map(|key| key.parse().ok())
+            .collect())
+    }
+
+    /// Verifies the user's finger against all its templates, see `PrintStore::verify()`.
+    pub fn verify(
+        &self,
+        user: &U,
+        device: &Device,
+        finger: Finger,
+    ) -> crate::Result<crate::VerifyOutcome> {
+        self.store.verify(&user.to_string(), device, finger)
+    }
+
+    /// Loads all prints compatible with the `device` into a gallery for identification.
+    /// Matched prints are keyed by the user and the finger.
+    pub fn gallery(&self, device: &Device) -> crate::Result<Gallery<(U, Finger)>> {
+        let mut gallery = Gallery::new();
+        for key in self.store.keys()? {
+            let user: U = match key.parse() {
+                Ok(user) => user,
+                Err(_) => continue,
+            };
+
+            for finger in self.store.list(&key, device)? {
+                for print in self.store.load_all(&key, device, finger)? {
+                    gallery.insert((user.clone(), finger), print)?;
+                }
+            }
+        }
+
+        Ok(gallery)
+    }
+}
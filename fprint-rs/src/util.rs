@@ -0,0 +1,35 @@
+use std::hint::black_box;
+
+/// Compares two byte slices in time depending only on the length of the longer one,
+/// so the comparison of secret data (like serialized prints or checksums) does not leak
+/// the length of the common prefix. Slices of different length are never equal.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let len = a.len().max(b.len());
+    let mut diff = (a.len() != b.len()) as u8;
+
+    for i in 0..len {
+        let x = a.get(i).copied().unwrap_or(0);
+        let y = b.get(i).copied().unwrap_or(0);
+        diff |= black_box(x ^ y);
+    }
+
+    black_box(diff) == 0
+}
+
+/// Lowercase hex representation of the bytes.
+#[cfg(any(feature = "audit", feature = "proof"))]
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Hex encoded HMAC-SHA256 of the payload.
+#[cfg(any(feature = "audit", feature = "proof"))]
+pub(crate) fn hmac_sha256(key: &[u8], payload: &[u8]) -> crate::Result<String> {
+    use hmac::{Hmac, Mac};
+
+    let mut mac =
+        Hmac::<sha2::Sha256>::new_from_slice(key).map_err(|_| crate::FPrintError::InvalidKey)?;
+    mac.update(payload);
+
+    Ok(hex(&mac.finalize().into_bytes()))
+}
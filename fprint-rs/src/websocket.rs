@@ -0,0 +1,189 @@
+//! WebSocket bridge for browser kiosks.
+//!
+//! Bridge accepts JSON commands from a WebSocket client and streams enrollment progress and
+//! verification results back as JSON events, so web frontends can drive the reader without
+//! native plugins.
+//!
+//! Commands (`finger` is the libfprint finger code, e.g. `7` for the right index finger):
+//!
+//! ```json
+//! {"command": "enroll", "finger": 7}
+//! {"command": "verify", "finger": 7}
+//! ```
+//!
+//! Events:
+//!
+//! ```json
+//! {"event": "enroll_stage", "stage": 1, "total": 5, "result": "Pass"}
+//! {"event": "enrolled", "finger": 7}
+//! {"event": "verify_retry", "reason": "RetryCenterFinger"}
+//! {"event": "verified", "matched": true}
+//! {"event": "error", "message": "Requested fingerprint not found (finger: RightIndex)"}
+//! ```
+
+use crate::{Device, EnrollResult, Finger, VerifyResult};
+use serde::{Deserialize, Serialize};
+use std::{
+    convert::TryFrom,
+    net::{TcpListener, TcpStream},
+};
+use tungstenite::{Message, WebSocket};
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum Command {
+    Enroll { finger: u32 },
+    Verify { finger: u32 },
+}
+
+/// Event sent to the client.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum BridgeEvent {
+    /// Enrollment stage finished with `result`; `stage` is the number of passed stages.
+    EnrollStage {
+        stage: i32,
+        total: i32,
+        result: String,
+    },
+    /// Enrollment completed and print was saved to disk.
+    Enrolled {
+        finger: u32,
+    },
+    /// Verification scan did not succeed and the user should scan again.
+    VerifyRetry {
+        reason: String,
+    },
+    /// Verification completed.
+    Verified {
+        matched: bool,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// WebSocket server operating a single device. Clients are served one at a time.
+pub struct WsBridge<'a> {
+    device: &'a Device,
+}
+
+impl<'a> WsBridge<'a> {
+    pub fn new(device: &'a Device) -> Self {
+        WsBridge { device }
+    }
+
+    /// Accepts clients from the `listener` and serves them until the listener fails.
+    /// Errors of a single client (failed handshake, disconnect) don't stop the server.
+    pub fn serve(&self, listener: TcpListener) -> crate::Result<()> {
+        for stream in listener.incoming() {
+            let _ = self.serve_client(stream?);
+        }
+
+        Ok(())
+    }
+
+    /// Performs WebSocket handshake and executes commands of the client until it disconnects.
+    pub fn serve_client(&self, stream: TcpStream) -> crate::Result<()> {
+        let mut socket = tungstenite::accept(stream)
+            .map_err(|e| crate::FPrintError::WebSocketHandshake(e.to_string()))?;
+
+        loop {
+            let text = match socket.read()? {
+                Message::Text(text) => text,
+                Message::Close(_) => return Ok(()),
+                _ => continue,
+            };
+
+            let command = match serde_json::from_str(&text) {
+                Ok(command) => command,
+                Err(e) => {
+                    send(&mut socket, &error_event(format!("Invalid command: {}", e)))?;
+                    continue;
+                }
+            };
+
+            match self.execute(&mut socket, command) {
+                Ok(()) => {}
+                Err(crate::FPrintError::WebSocket(e)) => {
+                    return Err(crate::FPrintError::WebSocket(e))
+                }
+                Err(e) => send(&mut socket, &error_event(e.to_string()))?,
+            }
+        }
+    }
+
+    fn execute(&self, socket: &mut WebSocket<TcpStream>, command: Command) -> crate::Result<()> {
+        match command {
+            Command::Enroll { finger } => self.enroll(socket, Finger::try_from(finger)?),
+            Command::Verify { finger } => self.verify(socket, Finger::try_from(finger)?),
+        }
+    }
+
+    fn enroll(&self, socket: &mut WebSocket<TcpStream>, finger: Finger) -> crate::Result<()> {
+        let total = self.device.get_nr_enroll_stages();
+        let mut stage = 0;
+
+        loop {
+            let result = self.device.enroll_finger_image()?;
+            match result {
+                EnrollResult::Complete(_, _) | EnrollResult::Pass(_) => stage += 1,
+                EnrollResult::Fail => stage = 0,
+                _ => {}
+            }
+
+            send(
+                socket,
+                &BridgeEvent::EnrollStage {
+                    stage,
+                    total,
+                    result: result.to_string(),
+                },
+            )?;
+
+            if let EnrollResult::Complete(print, _) = result {
+                print.save_to_disk(finger)?;
+                return send(
+                    socket,
+                    &BridgeEvent::Enrolled {
+                        finger: finger as u32,
+                    },
+                );
+            }
+        }
+    }
+
+    fn verify(&self, socket: &mut WebSocket<TcpStream>, finger: Finger) -> crate::Result<()> {
+        let mut print = self.device.load_data(finger)?;
+
+        loop {
+            let matched = match self.device.verify_finger_image(&mut print)? {
+                VerifyResult::Match => true,
+                VerifyResult::NoMatch => false,
+                reason => {
+                    send(
+                        socket,
+                        &BridgeEvent::VerifyRetry {
+                            reason: reason.to_string(),
+                        },
+                    )?;
+                    continue;
+                }
+            };
+
+            return send(socket, &BridgeEvent::Verified { matched });
+        }
+    }
+}
+
+fn error_event(message: String) -> BridgeEvent {
+    BridgeEvent::Error { message }
+}
+
+fn send(socket: &mut WebSocket<TcpStream>, event: &BridgeEvent) -> crate::Result<()> {
+    let json = serde_json::to_string(event)?;
+    socket.send(Message::text(json))?;
+
+    Ok(())
+}
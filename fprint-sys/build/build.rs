@@ -2,11 +2,13 @@ use std::env;
 use std::path::PathBuf;
 
 fn main() {
-    let mut build_path = PathBuf::from(env::var("OUT_DIR").unwrap());
-    let lib = pkg_config::Config::new().probe("libfprint").unwrap();
+    let mut build_path = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    let lib = pkg_config::Config::new()
+        .probe("libfprint")
+        .unwrap_or_else(|e| panic!("libfprint is not found: {}", e));
 
     for path in lib.include_paths.iter() {
-        println!("cargo:include={}", path.to_str().unwrap());
+        println!("cargo:include={}", path.display());
     }
 
     let bindgen = bindgen::Builder::default().header("build/wrapper.h");
@@ -16,7 +18,9 @@ fn main() {
         .blacklist_type("max_align_t")
         .blacklist_type("__fsid_t")
         .generate()
-        .unwrap();
+        .expect("Unable to generate libfprint bindings");
     build_path.push("fprint.rs");
-    let _ = bindings.write_to_file(build_path);
+    bindings
+        .write_to_file(build_path)
+        .unwrap_or_else(|e| panic!("Unable to write libfprint bindings: {}", e));
 }